@@ -0,0 +1,69 @@
+use std::{
+    env, fs,
+    io::{self, Read},
+    process::ExitCode,
+};
+
+use ptx_parser::parser::{format, FormatOptions};
+
+const USAGE: &str = "usage: ptxfmt [--check] [--strip-comments] [<file.ptx>...]";
+
+/// Formats the given files in place, or standard input to standard output.
+/// With `--check`, nothing is written and the exit code tells whether any
+/// input is not formatted.
+fn main() -> ExitCode {
+    match run(env::args().skip(1).collect()) {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::FAILURE,
+        Err(message) => {
+            eprintln!("{message}");
+            ExitCode::from(2)
+        }
+    }
+}
+
+/// Returns whether every input was already formatted, in `--check` mode.
+fn run(args: Vec<String>) -> Result<bool, String> {
+    let mut options = FormatOptions::default();
+    let mut check = false;
+    let mut paths = Vec::new();
+    for arg in &args {
+        match arg.as_str() {
+            "--check" => check = true,
+            "--strip-comments" => options.strip_comments = true,
+            "-h" | "--help" => {
+                println!("{USAGE}");
+                return Ok(true)
+            }
+            flag if flag.starts_with("--") => return Err(format!("unknown flag `{flag}`\n{USAGE}")),
+            path => paths.push(path),
+        }
+    }
+
+    if paths.is_empty() {
+        let mut source = String::new();
+        io::stdin().read_to_string(&mut source).map_err(|error| format!("<stdin>: {error}"))?;
+        let formatted = format(&source, options).map_err(|error| format!("<stdin>: {error}"))?;
+        if check {
+            return Ok(formatted == source)
+        }
+        print!("{formatted}");
+        return Ok(true)
+    }
+
+    let mut formatted_all = true;
+    for path in paths {
+        let source = fs::read_to_string(path).map_err(|error| format!("{path}: {error}"))?;
+        let formatted = format(&source, options).map_err(|error| format!("{path}: {error}"))?;
+        if formatted == source {
+            continue
+        }
+        if check {
+            println!("{path}");
+            formatted_all = false;
+        } else {
+            fs::write(path, formatted).map_err(|error| format!("{path}: {error}"))?;
+        }
+    }
+    Ok(formatted_all)
+}
@@ -1,2 +1,4 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
 pub mod parser;
 mod ptx_files;
@@ -0,0 +1,112 @@
+use std::{env, fs, process::ExitCode};
+
+use ptx_parser::parser::{call_graph_to_dot, cfg_to_dot, Cfg, Coverage, ParseOptions, PtxFile, SourceMap};
+
+const USAGE: &str = "usage: ptx-parser coverage [--strict] <file.ptx>\n       ptx-parser dot [--strict] [--function <name>] <file.ptx>";
+
+fn main() -> ExitCode {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let result = match args.first().map(String::as_str) {
+        Some("coverage") => coverage(&args[1..]),
+        Some("dot") => dot(&args[1..]),
+        _ => Err(USAGE.to_string()),
+    };
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(message) => {
+            eprintln!("{message}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Splits `args` into options and the single input path.
+fn parse_args(args: &[String]) -> Result<(ParseOptions, &str), String> {
+    let mut options = ParseOptions::default();
+    let mut path = None;
+    for arg in args {
+        match arg.as_str() {
+            "--strict" => options = ParseOptions::strict(),
+            flag if flag.starts_with("--") => return Err(format!("unknown flag `{flag}`\n{USAGE}")),
+            arg if path.is_none() => path = Some(arg),
+            _ => return Err(USAGE.to_string()),
+        }
+    }
+    Ok((options, path.ok_or(USAGE)?))
+}
+
+fn coverage(args: &[String]) -> Result<(), String> {
+    let (options, path) = parse_args(args)?;
+    let source = fs::read_to_string(path).map_err(|error| format!("{path}: {error}"))?;
+    let (ptx, diagnostics) = PtxFile::parse_with_options(&source, options)
+        .map_err(|error| format!("{path}: {error}"))?;
+    let map = SourceMap::new(&source);
+    let coverage = Coverage::new(&ptx);
+
+    println!("{:<40} {:>8} {:>8} {:>8}", "function", "typed", "partial", "unknown");
+    for function in coverage.functions() {
+        let counts = function.counts();
+        println!(
+            "{:<40} {:>8} {:>8} {:>8}",
+            function.name(), counts.fully_typed, counts.partially_typed, counts.unknown,
+        );
+        for span in function.samples() {
+            let start = map.line_column(span.start);
+            let text = map.text(*span).lines().next().unwrap_or_default();
+            println!("    {path}:{}:{}: {}", start.line, start.column, text.trim());
+        }
+    }
+    let total = coverage.total();
+    println!(
+        "{:<40} {:>8} {:>8} {:>8}",
+        "total", total.fully_typed, total.partially_typed, total.unknown,
+    );
+
+    println!();
+    println!("{:<40} {:>8} {:>8} {:>8}", "opcode", "typed", "partial", "unknown");
+    for (category, counts) in coverage.categories() {
+        println!(
+            "{:<40} {:>8} {:>8} {:>8}",
+            category.to_string(), counts.fully_typed, counts.partially_typed, counts.unknown,
+        );
+    }
+
+    for diagnostic in &diagnostics {
+        eprintln!("{path}: {diagnostic}");
+    }
+    Ok(())
+}
+
+/// Prints the call graph, or with `--function` the control flow graph of
+/// one function, as Graphviz DOT.
+fn dot(args: &[String]) -> Result<(), String> {
+    let mut function = None;
+    let mut rest = Vec::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--function" => function = Some(args.next().ok_or(USAGE)?.as_str()),
+            _ => rest.push(arg.clone()),
+        }
+    }
+    let (options, path) = parse_args(&rest)?;
+    let source = fs::read_to_string(path).map_err(|error| format!("{path}: {error}"))?;
+    let (ptx, diagnostics) = PtxFile::parse_with_options(&source, options)
+        .map_err(|error| format!("{path}: {error}"))?;
+    for diagnostic in &diagnostics {
+        eprintln!("{path}: {diagnostic}");
+    }
+
+    match function {
+        None => print!("{}", call_graph_to_dot(&ptx)),
+        Some(name) => {
+            let body = ptx
+                .functions()
+                .iter()
+                .find_map(|function| function.body().filter(|_| function.name() == name))
+                .ok_or_else(|| format!("{path}: no function body named `{name}`"))?;
+            print!("{}", cfg_to_dot(name, &Cfg::new(body)));
+        }
+    }
+    Ok(())
+}
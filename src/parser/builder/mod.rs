@@ -0,0 +1,881 @@
+//! Typed construction of PTX modules.
+//!
+//! The builders construct the [owned AST](super::owned) directly, typed the
+//! way the parser types the printed module. Its spans are empty, since
+//! there is no source.
+
+use core::{
+    fmt,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use super::{instruction::parse::is_identifier, owned, Modifier, Opcode, Span, StateSpace, Type};
+
+/// Register classes in declaration order, named like nvcc does.
+const CLASSES: [(&str, Type); 6] = [
+    ("p", Type::Pred),
+    ("rs", Type::B16),
+    ("r", Type::B32),
+    ("rd", Type::B64),
+    ("f", Type::F32),
+    ("fd", Type::F64),
+];
+
+/// Builds a module from functions and globals.
+#[derive(Debug, Clone)]
+pub struct ModuleBuilder {
+    version: (u32, u32),
+    target: String,
+    address_size: u32,
+    items: Vec<owned::Item>,
+}
+
+/// Tells apart the registers of different functions.
+static FUNCTIONS: AtomicUsize = AtomicUsize::new(0);
+
+/// Builds a kernel or device function.
+#[derive(Debug, Clone)]
+pub struct FunctionBuilder {
+    id: usize,
+    name: String,
+    entry: bool,
+    parameters: Vec<Param>,
+    registers: [u32; CLASSES.len()],
+    labels: Vec<bool>,
+    statements: Vec<owned::BodyLine>,
+}
+
+/// Emits statements at the end of a function, optionally starting at a label.
+#[derive(Debug)]
+pub struct BlockBuilder<'f> {
+    function: &'f mut FunctionBuilder,
+}
+
+/// A virtual register together with the type of the values it holds and
+/// the function it belongs to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Reg {
+    function: usize,
+    ty: Type,
+    class: usize,
+    index: u32,
+}
+
+/// A branch target, placed with [`FunctionBuilder::labeled`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Label(usize);
+
+/// A function parameter.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Param {
+    ty: Type,
+    name: String,
+}
+
+/// An instruction operand.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Register(Reg),
+    Special(Special),
+    Immediate(i64),
+    F32(f32),
+    F64(f64),
+    /// The address of a global variable or function.
+    Symbol(String),
+    /// `[base+offset]`.
+    Address(Reg, i64),
+}
+
+/// Read-only special registers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Special {
+    Tid(Axis),
+    Ntid(Axis),
+    Ctaid(Axis),
+    Nctaid(Axis),
+    LaneId,
+    WarpId,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Axis {
+    X,
+    Y,
+    Z,
+}
+
+/// Comparison operators of `setp`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Compare {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+/// An error found while building a module.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BuildError {
+    /// An operand's type does not match the instruction type.
+    TypeMismatch { expected: Type, found: Type },
+    /// An immediate cannot be used with the instruction type.
+    InvalidImmediate(Type),
+    /// No register class holds values of this type.
+    UnsupportedRegister(Type),
+    /// A modifier the parser would not recognize, or a vector width other
+    /// than 2, 4 or 8.
+    UnknownModifier(String),
+    /// A register allocated by another function.
+    ForeignRegister(Reg),
+    /// A label was placed more than once.
+    DuplicateLabel(Label),
+    /// A label was created but never placed.
+    UnplacedLabel { function: String, label: Label },
+    /// A function or global name is not a PTX identifier.
+    InvalidName(String),
+}
+
+impl ModuleBuilder {
+    /// Starts a module for PTX ISA `version` and `target`, e.g. `(7, 5)` and `sm_80`,
+    /// with 64-bit addresses.
+    pub fn new(version: (u32, u32), target: &str) -> Self {
+        Self { version, target: target.to_string(), address_size: 64, items: Vec::new() }
+    }
+
+    pub fn address_size(self, address_size: u32) -> Self {
+        Self { address_size, ..self }
+    }
+
+    /// Declares a `.global` variable and returns its address.
+    pub fn global(&mut self, ty: Type, name: &str) -> Result<Value, BuildError> {
+        self.declare(format!(".{ty} {name}"), name)
+    }
+
+    /// Declares a `.global` array of `len` elements and returns its address.
+    pub fn global_array(&mut self, ty: Type, name: &str, len: usize) -> Result<Value, BuildError> {
+        self.declare(format!(".align {} .{ty} {name}[{len}]", ty.bits().div_ceil(8)), name)
+    }
+
+    fn declare(&mut self, raw_string: String, name: &str) -> Result<Value, BuildError> {
+        check_name(name)?;
+        let global = owned::Global { span: Span::default(), raw_string, comments: owned::Comments::default() };
+        self.items.push(owned::Item::Global(global));
+        Ok(Value::Symbol(name.to_string()))
+    }
+
+    /// Adds a finished function, checking its name and that all of its
+    /// labels were placed.
+    pub fn function(&mut self, function: FunctionBuilder) -> Result<(), BuildError> {
+        check_name(&function.name)?;
+        if let Some(label) = function.labels.iter().position(|placed| !placed) {
+            return Err(BuildError::UnplacedLabel { function: function.name, label: Label(label) })
+        }
+        self.items.push(owned::Item::Function(function.into_function()));
+        Ok(())
+    }
+
+    pub fn build(self) -> owned::PtxFile {
+        let (major, minor) = self.version;
+        let preamble = owned::Preamble {
+            version: (major.to_string(), minor.to_string()),
+            target: self.target,
+            address_size: self.address_size.to_string(),
+        };
+        owned::PtxFile { header_comments: Vec::new(), preamble, items: self.items }
+    }
+}
+
+impl FunctionBuilder {
+    /// Starts a `.visible .entry` kernel.
+    pub fn entry(name: &str) -> Self {
+        Self::new(name, true)
+    }
+
+    /// Starts a `.func` device function.
+    pub fn func(name: &str) -> Self {
+        Self::new(name, false)
+    }
+
+    fn new(name: &str, entry: bool) -> Self {
+        Self {
+            id: FUNCTIONS.fetch_add(1, Ordering::Relaxed),
+            name: name.to_string(),
+            entry,
+            parameters: Vec::new(),
+            registers: [0; CLASSES.len()],
+            labels: Vec::new(),
+            statements: Vec::new(),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Adds a parameter, named `<function>_param_<index>`.
+    pub fn param(&mut self, ty: Type) -> Param {
+        let name = format!("{}_param_{}", self.name, self.parameters.len());
+        let param = Param { ty, name };
+        self.parameters.push(param.clone());
+        param
+    }
+
+    /// Allocates a register for values of type `ty`.
+    pub fn reg(&mut self, ty: Type) -> Result<Reg, BuildError> {
+        let class = match ty {
+            Type::Pred => 0,
+            Type::F32 => 4,
+            Type::F64 => 5,
+            _ => match ty.bits() {
+                8 | 16 => 1,
+                32 => 2,
+                64 => 3,
+                _ => return Err(BuildError::UnsupportedRegister(ty)),
+            },
+        };
+        self.registers[class] += 1;
+        Ok(Reg { function: self.id, ty, class, index: self.registers[class] })
+    }
+
+    /// Creates a label to be placed later with [`FunctionBuilder::labeled`].
+    pub fn label(&mut self) -> Label {
+        self.labels.push(false);
+        Label(self.labels.len() - 1)
+    }
+
+    /// Continues emitting at the end of the function.
+    pub fn block(&mut self) -> BlockBuilder<'_> {
+        BlockBuilder { function: self }
+    }
+
+    /// Places `label` at the end of the function and continues emitting after it.
+    pub fn labeled(&mut self, label: Label) -> Result<BlockBuilder<'_>, BuildError> {
+        if core::mem::replace(&mut self.labels[label.0], true) {
+            return Err(BuildError::DuplicateLabel(label))
+        }
+        self.statements.push(owned::BodyLine::Label(label.to_string()));
+        Ok(self.block())
+    }
+
+    /// The finished function, declaring every register class in use.
+    fn into_function(self) -> owned::Function {
+        let parameters: Vec<_> =
+            self.parameters.iter().map(|param| format!("\n\t.param .{} {}", param.ty, param.name)).collect();
+        let mut parameters = parameters.join(",");
+        if !parameters.is_empty() {
+            parameters.push('\n');
+        }
+        let registers = CLASSES.iter().zip(self.registers).filter(|(_, count)| *count > 0);
+        let registers = registers.map(|((prefix, ty), count)| {
+            owned::BodyLine::Register(format!(".{ty} %{prefix}<{}>", count + 1))
+        });
+        let body = registers
+            .chain(self.statements)
+            .map(|line| owned::Statement { span: Span::default(), line, comments: owned::Comments::default() })
+            .collect();
+        let signature = owned::FunctionSignature {
+            visible: self.entry,
+            entry: self.entry,
+            return_value: None,
+            name: self.name,
+            parameters: Some(parameters),
+        };
+        owned::Function { span: Span::default(), signature, body: Some(body), comments: owned::Comments::default() }
+    }
+}
+
+impl BlockBuilder<'_> {
+    pub fn reg(&mut self, ty: Type) -> Result<Reg, BuildError> {
+        self.function.reg(ty)
+    }
+
+    /// Emits `opcode` with `modifiers` and `operands`. The modifiers must be
+    /// ones the parser recognizes and the registers must belong to this
+    /// function. If the modifiers contain exactly one type, register and
+    /// immediate operands are checked against it; addresses are not.
+    pub fn emit(&mut self, opcode: Opcode, modifiers: &[Modifier], operands: &[Value]) -> Result<(), BuildError> {
+        let unknown = modifiers.iter().find(|modifier| match modifier {
+            Modifier::Known(name) => Modifier::new(name) != **modifier,
+            Modifier::Vector(width) => !matches!(width, 2 | 4 | 8),
+            Modifier::Unknown(_) => true,
+            Modifier::Type(_) | Modifier::StateSpace(..) => false,
+        });
+        if let Some(modifier) = unknown {
+            return Err(BuildError::UnknownModifier(modifier.to_string()))
+        }
+        operands.iter().try_for_each(|operand| self.owns(operand))?;
+        let mut types = modifiers.iter().filter_map(|modifier| match modifier {
+            Modifier::Type(ty) => Some(*ty),
+            _ => None,
+        });
+        if let (Some(ty), None) = (types.next(), types.next()) {
+            operands.iter().try_for_each(|operand| check(ty, operand))?;
+        }
+        self.push(opcode, modifiers, operands.iter().map(Value::operand).collect());
+        Ok(())
+    }
+
+    /// `mov.<ty> dst, src`
+    pub fn mov(&mut self, ty: Type, src: impl Into<Value>) -> Result<Reg, BuildError> {
+        let dst = self.reg(ty)?;
+        self.emit(Opcode::Mov, &[Modifier::Type(ty)], &[dst.into(), src.into()])?;
+        Ok(dst)
+    }
+
+    /// Reads a special register into a new `.u32` register.
+    pub fn special(&mut self, special: Special) -> Result<Reg, BuildError> {
+        self.mov(Type::U32, special)
+    }
+
+    /// `ld.param.<ty> dst, [param]`
+    pub fn ld_param(&mut self, param: &Param) -> Result<Reg, BuildError> {
+        let dst = self.reg(param.ty)?;
+        let address = owned::Operand::Address { base: param.name.clone(), offset: None };
+        let modifiers = [Modifier::StateSpace(StateSpace::Param, None), Modifier::Type(param.ty)];
+        self.push(Opcode::Ld, &modifiers, vec![Value::from(dst).operand(), address]);
+        Ok(dst)
+    }
+
+    /// `ld.<space>.<ty> dst, [address+offset]`
+    pub fn ld(&mut self, space: StateSpace, ty: Type, address: Reg, offset: i64) -> Result<Reg, BuildError> {
+        let dst = self.reg(ty)?;
+        let modifiers = [Modifier::StateSpace(space, None), Modifier::Type(ty)];
+        self.emit(Opcode::Ld, &modifiers, &[dst.into(), Value::Address(address, offset)])?;
+        Ok(dst)
+    }
+
+    /// `st.<space>.<ty> [address+offset], value`
+    pub fn st(
+        &mut self,
+        space: StateSpace,
+        ty: Type,
+        address: Reg,
+        offset: i64,
+        value: impl Into<Value>,
+    ) -> Result<(), BuildError> {
+        let modifiers = [Modifier::StateSpace(space, None), Modifier::Type(ty)];
+        self.emit(Opcode::St, &modifiers, &[Value::Address(address, offset), value.into()])
+    }
+
+    /// `cvta.to.<space>.<ty> dst, address`, converting a generic address.
+    pub fn cvta_to(&mut self, space: StateSpace, address: Reg) -> Result<Reg, BuildError> {
+        let ty = if address.ty.bits() == 32 { Type::U32 } else { Type::U64 };
+        let dst = self.reg(ty)?;
+        let modifiers = [Modifier::Known("to"), Modifier::StateSpace(space, None), Modifier::Type(ty)];
+        self.emit(Opcode::Cvta, &modifiers, &[dst.into(), address.into()])?;
+        Ok(dst)
+    }
+
+    pub fn add(&mut self, ty: Type, a: impl Into<Value>, b: impl Into<Value>) -> Result<Reg, BuildError> {
+        self.binary(Opcode::Add, &[], ty, a.into(), b.into())
+    }
+
+    pub fn sub(&mut self, ty: Type, a: impl Into<Value>, b: impl Into<Value>) -> Result<Reg, BuildError> {
+        self.binary(Opcode::Sub, &[], ty, a.into(), b.into())
+    }
+
+    /// `mul.lo` for integers, `mul.rn` for floats.
+    pub fn mul(&mut self, ty: Type, a: impl Into<Value>, b: impl Into<Value>) -> Result<Reg, BuildError> {
+        let mode = if is_float(ty) { "rn" } else { "lo" };
+        self.binary(Opcode::Mul, &[Modifier::Known(mode)], ty, a.into(), b.into())
+    }
+
+    /// `mul.wide.<ty>`, whose result is twice as wide as `ty`.
+    pub fn mul_wide(&mut self, ty: Type, a: impl Into<Value>, b: impl Into<Value>) -> Result<Reg, BuildError> {
+        let wide = match ty {
+            Type::S16 => Type::S32,
+            Type::S32 => Type::S64,
+            Type::U16 => Type::U32,
+            Type::U32 => Type::U64,
+            _ => return Err(BuildError::UnsupportedRegister(ty)),
+        };
+        let (a, b) = (a.into(), b.into());
+        self.check(ty, &a)?;
+        self.check(ty, &b)?;
+        let dst = self.reg(wide)?;
+        self.push(Opcode::Mul, &[Modifier::Known("wide"), Modifier::Type(ty)], operands(&[dst.into(), a, b]));
+        Ok(dst)
+    }
+
+    /// `mad.lo` for integers, `fma.rn` for floats.
+    pub fn mad(
+        &mut self,
+        ty: Type,
+        a: impl Into<Value>,
+        b: impl Into<Value>,
+        c: impl Into<Value>,
+    ) -> Result<Reg, BuildError> {
+        let dst = self.reg(ty)?;
+        let (opcode, mode) = if is_float(ty) { (Opcode::Fma, "rn") } else { (Opcode::Mad, "lo") };
+        let modifiers = [Modifier::Known(mode), Modifier::Type(ty)];
+        self.emit(opcode, &modifiers, &[dst.into(), a.into(), b.into(), c.into()])?;
+        Ok(dst)
+    }
+
+    /// `setp.<compare>.<ty> p, a, b`
+    pub fn setp(&mut self, compare: Compare, ty: Type, a: impl Into<Value>, b: impl Into<Value>) -> Result<Reg, BuildError> {
+        let (a, b) = (a.into(), b.into());
+        self.check(ty, &a)?;
+        self.check(ty, &b)?;
+        let dst = self.reg(Type::Pred)?;
+        let modifiers = [Modifier::Known(compare.name()), Modifier::Type(ty)];
+        self.push(Opcode::Setp, &modifiers, operands(&[dst.into(), a, b]));
+        Ok(dst)
+    }
+
+    /// `cvt.<to>.<from> dst, src`, rounding to nearest into floats and
+    /// towards zero into integers.
+    pub fn cvt(&mut self, to: Type, from: Type, src: impl Into<Value>) -> Result<Reg, BuildError> {
+        let src = src.into();
+        self.check(from, &src)?;
+        let dst = self.reg(to)?;
+        let rounding = match (is_float(to), is_float(from)) {
+            (true, false) => Some("rn"),
+            (true, true) if to.bits() < from.bits() => Some("rn"),
+            (false, true) => Some("rzi"),
+            _ => None,
+        };
+        let types = [Modifier::Type(to), Modifier::Type(from)];
+        let modifiers: Vec<_> = rounding.map(Modifier::Known).into_iter().chain(types).collect();
+        self.push(Opcode::Cvt, &modifiers, operands(&[dst.into(), src]));
+        Ok(dst)
+    }
+
+    /// `bra.uni label`
+    pub fn branch(&mut self, label: Label) {
+        let goto = owned::Goto { predicate: None, uniform: true, label: label.to_string() };
+        self.function.statements.push(owned::BodyLine::Goto(goto));
+    }
+
+    /// `@p bra label`, or `@!p bra label` if `negated`.
+    pub fn branch_if(&mut self, predicate: Reg, negated: bool, label: Label) -> Result<(), BuildError> {
+        self.check(Type::Pred, &predicate.into())?;
+        let predicate = match negated {
+            true => owned::Predicate::False(predicate.to_string()),
+            false => owned::Predicate::True(predicate.to_string()),
+        };
+        let goto = owned::Goto { predicate: Some(predicate), uniform: false, label: label.to_string() };
+        self.function.statements.push(owned::BodyLine::Goto(goto));
+        Ok(())
+    }
+
+    pub fn ret(&mut self) {
+        self.function.statements.push(owned::BodyLine::Return { uniform: false });
+    }
+
+    /// Emits an instruction without checking its operands.
+    fn push(&mut self, opcode: Opcode, modifiers: &[Modifier], operands: Vec<owned::Operand>) {
+        let modifiers = modifiers.iter().map(|modifier| (*modifier).into()).collect();
+        let instruction = owned::Instruction { predicate: None, opcode, modifiers, operands };
+        self.function.statements.push(owned::BodyLine::Instruction(instruction));
+    }
+
+    /// Checks that `value` belongs to this function, then its type.
+    fn check(&self, ty: Type, value: &Value) -> Result<(), BuildError> {
+        self.owns(value)?;
+        check(ty, value)
+    }
+
+    /// Checks that the register of `value`, if any, belongs to this function.
+    fn owns(&self, value: &Value) -> Result<(), BuildError> {
+        match value {
+            Value::Register(register) | Value::Address(register, _) if register.function != self.function.id => {
+                Err(BuildError::ForeignRegister(*register))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn binary(&mut self, opcode: Opcode, modifiers: &[Modifier], ty: Type, a: Value, b: Value) -> Result<Reg, BuildError> {
+        let dst = self.reg(ty)?;
+        let modifiers: Vec<_> = modifiers.iter().copied().chain([Modifier::Type(ty)]).collect();
+        self.emit(opcode, &modifiers, &[dst.into(), a, b])?;
+        Ok(dst)
+    }
+}
+
+impl Reg {
+    pub fn ty(&self) -> Type {
+        self.ty
+    }
+}
+
+impl Param {
+    pub fn ty(&self) -> Type {
+        self.ty
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+impl Value {
+    /// The operand as the parser types it.
+    fn operand(&self) -> owned::Operand {
+        match self {
+            Value::Register(_) | Value::Special(_) => owned::Operand::Register(self.to_string()),
+            Value::Immediate(_) | Value::F32(_) | Value::F64(_) => owned::Operand::Immediate(self.to_string()),
+            Value::Symbol(symbol) => owned::Operand::Symbol(symbol.clone()),
+            Value::Address(base, 0) => owned::Operand::Address { base: base.to_string(), offset: None },
+            Value::Address(base, offset) => {
+                owned::Operand::Address { base: base.to_string(), offset: Some(offset.to_string()) }
+            }
+        }
+    }
+}
+
+impl Compare {
+    fn name(self) -> &'static str {
+        match self {
+            Compare::Eq => "eq",
+            Compare::Ne => "ne",
+            Compare::Lt => "lt",
+            Compare::Le => "le",
+            Compare::Gt => "gt",
+            Compare::Ge => "ge",
+        }
+    }
+}
+
+fn operands(values: &[Value]) -> Vec<owned::Operand> {
+    values.iter().map(Value::operand).collect()
+}
+
+fn check_name(name: &str) -> Result<(), BuildError> {
+    match is_identifier(name) {
+        true => Ok(()),
+        false => Err(BuildError::InvalidName(name.to_string())),
+    }
+}
+
+fn is_float(ty: Type) -> bool {
+    matches!(ty, Type::F16 | Type::Bf16 | Type::Tf32 | Type::F32 | Type::F64)
+}
+
+fn is_integer(ty: Type) -> bool {
+    matches!(ty, Type::S8 | Type::S16 | Type::S32 | Type::S64 | Type::U8 | Type::U16 | Type::U32 | Type::U64)
+}
+
+fn is_bits(ty: Type) -> bool {
+    matches!(ty, Type::B8 | Type::B16 | Type::B32 | Type::B64 | Type::B128)
+}
+
+/// Checks `value` against the instruction type `ty`. Following the PTX
+/// rules, bit types match any type of the same size, and signed and
+/// unsigned integers of the same size match each other.
+fn check(ty: Type, value: &Value) -> Result<(), BuildError> {
+    let found = match value {
+        Value::Register(register) => register.ty,
+        Value::Special(_) => Type::U32,
+        Value::Immediate(_) if is_integer(ty) || is_bits(ty) => return Ok(()),
+        Value::F32(_) if ty == Type::F32 => return Ok(()),
+        Value::F64(_) if ty == Type::F64 => return Ok(()),
+        Value::Immediate(_) | Value::F32(_) | Value::F64(_) => return Err(BuildError::InvalidImmediate(ty)),
+        Value::Symbol(_) | Value::Address(..) => return Ok(()),
+    };
+    let compatible = ty == found
+        || ty.bits() == found.bits()
+            && (is_bits(ty) || is_bits(found) || is_integer(ty) && is_integer(found));
+    match compatible {
+        true => Ok(()),
+        false => Err(BuildError::TypeMismatch { expected: ty, found }),
+    }
+}
+
+impl From<Reg> for Value {
+    fn from(register: Reg) -> Self {
+        Value::Register(register)
+    }
+}
+
+impl From<Special> for Value {
+    fn from(special: Special) -> Self {
+        Value::Special(special)
+    }
+}
+
+impl From<i32> for Value {
+    fn from(immediate: i32) -> Self {
+        Value::Immediate(immediate.into())
+    }
+}
+
+impl From<i64> for Value {
+    fn from(immediate: i64) -> Self {
+        Value::Immediate(immediate)
+    }
+}
+
+impl From<f32> for Value {
+    fn from(immediate: f32) -> Self {
+        Value::F32(immediate)
+    }
+}
+
+impl From<f64> for Value {
+    fn from(immediate: f64) -> Self {
+        Value::F64(immediate)
+    }
+}
+
+impl fmt::Display for Reg {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "%{}{}", CLASSES[self.class].0, self.index)
+    }
+}
+
+impl fmt::Display for Label {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "$L__BB{}", self.0)
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Register(register) => register.fmt(f),
+            Value::Special(special) => special.fmt(f),
+            Value::Immediate(immediate) => immediate.fmt(f),
+            Value::F32(immediate) => write!(f, "0f{:08X}", immediate.to_bits()),
+            Value::F64(immediate) => write!(f, "0d{:016X}", immediate.to_bits()),
+            Value::Symbol(symbol) => f.write_str(symbol),
+            Value::Address(base, 0) => write!(f, "[{base}]"),
+            Value::Address(base, offset) => write!(f, "[{base}+{offset}]"),
+        }
+    }
+}
+
+impl fmt::Display for Special {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (name, axis) = match self {
+            Special::Tid(axis) => ("tid", axis),
+            Special::Ntid(axis) => ("ntid", axis),
+            Special::Ctaid(axis) => ("ctaid", axis),
+            Special::Nctaid(axis) => ("nctaid", axis),
+            Special::LaneId => return f.write_str("%laneid"),
+            Special::WarpId => return f.write_str("%warpid"),
+        };
+        let axis = match axis {
+            Axis::X => "x",
+            Axis::Y => "y",
+            Axis::Z => "z",
+        };
+        write!(f, "%{name}.{axis}")
+    }
+}
+
+impl fmt::Display for Compare {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl fmt::Display for BuildError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BuildError::TypeMismatch { expected, found } => {
+                write!(f, "expected an operand of type `.{expected}`, found `.{found}`")
+            }
+            BuildError::InvalidImmediate(ty) => write!(f, "immediate is not valid for type `.{ty}`"),
+            BuildError::UnsupportedRegister(ty) => write!(f, "no register class for type `.{ty}`"),
+            BuildError::UnknownModifier(modifier) => write!(f, "unknown modifier `.{modifier}`"),
+            BuildError::ForeignRegister(register) => write!(f, "register `{register}` belongs to another function"),
+            BuildError::DuplicateLabel(label) => write!(f, "label `{label}` is placed twice"),
+            BuildError::UnplacedLabel { function, label } => {
+                write!(f, "label `{label}` of function `{function}` is never placed")
+            }
+            BuildError::InvalidName(name) => write!(f, "`{name}` is not a valid identifier"),
+        }
+    }
+}
+
+impl std::error::Error for BuildError {}
+
+#[cfg(test)]
+mod test_builder {
+    use crate::parser::{
+        owned::{BodyLine, Function, Item, PtxFile as OwnedPtxFile},
+        Modifier, Opcode, ParseOptions, PtxFile, StateSpace, Type,
+    };
+
+    use super::{Axis, BuildError, Compare, FunctionBuilder, ModuleBuilder, Special, Value};
+
+    /// The kernel of `ptx_files::kernel`, built programmatically.
+    fn square_kernel() -> Result<ModuleBuilder, BuildError> {
+        let mut module = ModuleBuilder::new((6, 0), "sm_30");
+        let mut kernel = FunctionBuilder::entry("square_kernel");
+        let input = kernel.param(Type::U64);
+        let output = kernel.param(Type::U64);
+        let len = kernel.param(Type::U32);
+        let (body, exit) = (kernel.label(), kernel.label());
+
+        let mut block = kernel.block();
+        let len = block.ld_param(&len)?;
+        let tid = block.special(Special::Tid(Axis::X))?;
+        let ctaid = block.special(Special::Ctaid(Axis::X))?;
+        let ntid = block.special(Special::Ntid(Axis::X))?;
+        let index = block.mad(Type::S32, ctaid, ntid, tid)?;
+        let in_bounds = block.setp(Compare::Lt, Type::S32, index, len)?;
+        block.branch_if(in_bounds, false, body)?;
+        block.branch(exit);
+
+        let mut block = kernel.labeled(body)?;
+        let input = block.ld_param(&input)?;
+        let output = block.ld_param(&output)?;
+        let output = block.cvta_to(StateSpace::Global, output)?;
+        let input = block.cvta_to(StateSpace::Global, input)?;
+        let offset = block.mul_wide(Type::S32, index, 4)?;
+        let output = block.add(Type::S64, output, offset)?;
+        let input = block.add(Type::S64, input, offset)?;
+        let x = block.ld(StateSpace::Global, Type::F32, input, 0)?;
+        let square = block.mul(Type::F32, x, x)?;
+        block.st(StateSpace::Global, Type::F32, output, 0, square)?;
+
+        kernel.labeled(exit)?.ret();
+        module.function(kernel)?;
+        Ok(module)
+    }
+
+    #[test]
+    fn builds_square_kernel() {
+        let ptx = square_kernel().unwrap().build();
+        let kernel = ptx.functions().next().unwrap();
+        assert_eq!(kernel.name(), "square_kernel");
+        let lines = lines(kernel);
+        assert_eq!(lines.len(), 25);
+        assert!(matches!(lines[4], BodyLine::Instruction(_)));
+        assert_eq!(lines[12], BodyLine::Label("$L__BB0".to_string()));
+        assert_eq!(lines[24], BodyLine::Return { uniform: false });
+        let source = ptx.to_string();
+        assert!(source.contains("\n\t.reg .b32 %r<6>;\n"));
+        assert!(source.contains("\n\tmad.lo.s32 %r5, %r3, %r4, %r2;\n"));
+        assert!(source.contains("\n\t@%p1 bra $L__BB0;\n"));
+        assert!(source.contains("\n\tmul.wide.s32 %rd5, %r5, 4;\n"));
+        assert!(source.contains("\n\tst.global.f32 [%rd6], %f2;\n"));
+    }
+
+    fn lines(function: &Function) -> Vec<BodyLine> {
+        function.body.iter().flatten().map(|statement| statement.line.clone()).collect()
+    }
+
+    /// Asserts that parsing the printed module gives back the built one,
+    /// apart from spans.
+    fn assert_parses_back(built: &OwnedPtxFile) {
+        let source = built.to_string();
+        let (ptx, diagnostics) = PtxFile::parse_with_options(&source, ParseOptions::strict()).unwrap();
+        assert!(diagnostics.is_empty());
+        let parsed = ptx.into_owned();
+        assert_eq!(parsed.preamble, built.preamble);
+        assert_eq!(parsed.items.len(), built.items.len());
+        for (parsed, built) in parsed.items.iter().zip(&built.items) {
+            match (parsed, built) {
+                (Item::Function(parsed), Item::Function(built)) => {
+                    assert_eq!(parsed.signature, built.signature);
+                    assert_eq!(lines(parsed), lines(built));
+                }
+                (Item::Global(parsed), Item::Global(built)) => assert_eq!(parsed.raw_string, built.raw_string),
+                _ => panic!("items differ"),
+            }
+        }
+    }
+
+    #[test]
+    fn parses_back() {
+        assert_parses_back(&square_kernel().unwrap().build());
+
+        let mut module = ModuleBuilder::new((7, 5), "sm_80");
+        let table = module.global_array(Type::U32, "table", 4).unwrap();
+        module.global(Type::F64, "x").unwrap();
+        let mut function = FunctionBuilder::func("f");
+        let param = function.param(Type::U64);
+        let label = function.label();
+        let mut block = function.labeled(label).unwrap();
+        let address = block.ld_param(&param).unwrap();
+        let value = block.ld(StateSpace::Global, Type::F32, address, -4).unwrap();
+        let int = block.cvt(Type::S32, Type::F32, value).unwrap();
+        let half = block.cvt(Type::F16, Type::F32, value).unwrap();
+        let wide = block.cvt(Type::F64, Type::S32, int).unwrap();
+        let done = block.setp(Compare::Ge, Type::F64, wide, 1.0f64).unwrap();
+        block.branch_if(done, true, label).unwrap();
+        block.mov(Type::U64, table).unwrap();
+        block.st(StateSpace::Global, Type::F16, address, 0, half).unwrap();
+        block.ret();
+        module.function(function).unwrap();
+        assert_parses_back(&module.build());
+    }
+
+    #[test]
+    fn checks_types() {
+        let mut kernel = FunctionBuilder::entry("k");
+        let label = kernel.label();
+        let mut block = kernel.block();
+        let float = block.mov(Type::F32, 1.5f32).unwrap();
+        let int = block.mov(Type::U32, 7).unwrap();
+        let bits = block.mov(Type::B32, int).unwrap();
+        assert_eq!(
+            block.add(Type::S32, float, int),
+            Err(BuildError::TypeMismatch { expected: Type::S32, found: Type::F32 }),
+        );
+        assert_eq!(block.add(Type::F32, float, 1), Err(BuildError::InvalidImmediate(Type::F32)));
+        assert!(block.add(Type::S32, int, bits).is_ok());
+        assert!(block.branch_if(int, false, label).is_err());
+
+        let mut block = kernel.block();
+        let modifiers = [Modifier::Known("approx"), Modifier::Type(Type::F32)];
+        assert!(block.emit(Opcode::Sqrt, &modifiers, &[float.into(), float.into()]).is_ok());
+        assert!(block.emit(Opcode::Sqrt, &modifiers, &[float.into(), Value::from(2)]).is_err());
+        assert_eq!(
+            block.emit(Opcode::Sqrt, &[Modifier::Unknown("fast")], &[]),
+            Err(BuildError::UnknownModifier("fast".to_string())),
+        );
+        assert_eq!(
+            block.emit(Opcode::Sqrt, &[Modifier::Known("bogus")], &[]),
+            Err(BuildError::UnknownModifier("bogus".to_string())),
+        );
+        assert!(block.emit(Opcode::Ld, &[Modifier::Vector(3)], &[]).is_err());
+    }
+
+    #[test]
+    fn checks_register_owners() {
+        let mut other = FunctionBuilder::func("g");
+        let foreign = other.reg(Type::U32).unwrap();
+        let predicate = other.reg(Type::Pred).unwrap();
+        let mut kernel = FunctionBuilder::entry("k");
+        let label = kernel.label();
+        let mut block = kernel.block();
+        let own = block.mov(Type::U32, 1).unwrap();
+        assert_eq!(block.add(Type::U32, own, foreign), Err(BuildError::ForeignRegister(foreign)));
+        assert_eq!(block.setp(Compare::Lt, Type::U32, foreign, own), Err(BuildError::ForeignRegister(foreign)));
+        assert_eq!(block.ld(StateSpace::Global, Type::U32, foreign, 0), Err(BuildError::ForeignRegister(foreign)));
+        assert_eq!(block.branch_if(predicate, false, label), Err(BuildError::ForeignRegister(predicate)));
+        assert!(block.add(Type::U32, own, own).is_ok());
+    }
+
+    #[test]
+    fn checks_labels() {
+        let mut kernel = FunctionBuilder::func("f");
+        let label = kernel.label();
+        kernel.block().branch(label);
+        assert!(matches!(ModuleBuilder::new((7, 5), "sm_80").function(kernel.clone()), Err(BuildError::UnplacedLabel { .. })));
+        kernel.labeled(label).unwrap().ret();
+        assert!(matches!(kernel.labeled(label), Err(BuildError::DuplicateLabel(_))));
+        let mut module = ModuleBuilder::new((7, 5), "sm_80");
+        module.global_array(Type::U32, "table", 4).unwrap();
+        module.function(kernel).unwrap();
+        assert_eq!(module.build().items.len(), 2);
+    }
+
+    #[test]
+    fn checks_names() {
+        let mut module = ModuleBuilder::new((7, 5), "sm_80");
+        assert_eq!(module.global(Type::U32, "1st"), Err(BuildError::InvalidName("1st".to_string())));
+        assert_eq!(
+            module.function(FunctionBuilder::entry("my kernel")),
+            Err(BuildError::InvalidName("my kernel".to_string())),
+        );
+        assert!(module.build().items.is_empty());
+    }
+}
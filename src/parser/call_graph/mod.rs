@@ -0,0 +1,302 @@
+use std::collections::{HashMap, VecDeque};
+
+use super::{
+    visit::{walk_file, walk_function, walk_instruction, walk_operand, Visitor},
+    Function, Instruction, Opcode, Operand, PtxFile, Span,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CallKind {
+    /// `call f, ...`.
+    Direct,
+    /// `call %rd1, ...`, to a function whose address is taken.
+    Indirect,
+}
+
+/// A call site, or for an indirect call one of its candidate callees.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Call<'a> {
+    pub caller: &'a str,
+    pub callee: &'a str,
+    pub kind: CallKind,
+    /// The `call` instruction.
+    pub span: Span,
+}
+
+/// The calls between the functions of a module.
+///
+/// An indirect call may reach any function whose address is taken, either
+/// by a global initializer such as a vtable or by an instruction like
+/// `mov.u64 %rd1, f;`, so it gets an edge to each of them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CallGraph<'a> {
+    functions: Vec<&'a str>,
+    indices: HashMap<&'a str, usize>,
+    entries: Vec<&'a str>,
+    address_taken: Vec<&'a str>,
+    calls: Vec<Call<'a>>,
+    /// For each function, the positions in `calls` of the calls it makes.
+    calls_from: Vec<Vec<usize>>,
+    /// For each function, the positions in `calls` of the calls to it.
+    calls_to: Vec<Vec<usize>>,
+}
+
+impl<'a> CallGraph<'a> {
+    pub fn new(ptx: &PtxFile<'a>) -> Self {
+        let mut functions = Vec::new();
+        let mut indices = HashMap::new();
+        let mut is_entry = Vec::new();
+        for function in ptx.functions() {
+            let name = function.name();
+            let index = *indices.entry(name).or_insert_with(|| {
+                functions.push(name);
+                is_entry.push(false);
+                functions.len() - 1
+            });
+            is_entry[index] |= function.is_entry();
+        }
+        let entries = functions.iter().zip(is_entry).filter(|(_, entry)| *entry).map(|(name, _)| *name).collect();
+
+        let mut collector = Collector {
+            indices: &indices,
+            address_taken: Vec::new(),
+            taken: vec![false; functions.len()],
+            caller: "",
+            sites: Vec::new(),
+        };
+        for initializer in ptx.globals().iter().filter_map(|global| global.initializer()) {
+            initializer
+                .split(|c: char| !c.is_ascii_alphanumeric() && c != '_' && c != '$')
+                .for_each(|name| collector.take_address(name));
+        }
+        walk_file(&mut collector, ptx);
+        let Collector { address_taken, sites, .. } = collector;
+
+        let mut calls = Vec::new();
+        for (caller, callee, span) in sites {
+            match callee {
+                Some(callee) => calls.push(Call { caller, callee, kind: CallKind::Direct, span }),
+                None => calls.extend(
+                    address_taken.iter().map(|&callee| Call { caller, callee, kind: CallKind::Indirect, span }),
+                ),
+            }
+        }
+        let mut calls_from = vec![Vec::new(); functions.len()];
+        let mut calls_to = vec![Vec::new(); functions.len()];
+        for (position, call) in calls.iter().enumerate() {
+            calls_from[indices[call.caller]].push(position);
+            calls_to[indices[call.callee]].push(position);
+        }
+        Self { functions, indices, entries, address_taken, calls, calls_from, calls_to }
+    }
+
+    /// Every function, once each, in source order.
+    pub fn functions(&self) -> &[&'a str] {
+        &self.functions
+    }
+
+    pub fn entries(&self) -> &[&'a str] {
+        &self.entries
+    }
+
+    /// The functions that indirect calls may reach.
+    pub fn address_taken(&self) -> &[&'a str] {
+        &self.address_taken
+    }
+
+    /// The calls in source order.
+    pub fn calls(&self) -> &[Call<'a>] {
+        &self.calls
+    }
+
+    pub fn calls_from(&self, caller: &str) -> impl Iterator<Item = &Call<'a>> {
+        self.adjacent(&self.calls_from, caller)
+    }
+
+    pub fn calls_to(&self, callee: &str) -> impl Iterator<Item = &Call<'a>> {
+        self.adjacent(&self.calls_to, callee)
+    }
+
+    fn adjacent<'r>(&'r self, lists: &'r [Vec<usize>], name: &str) -> impl Iterator<Item = &'r Call<'a>> {
+        let list = self.indices.get(name).map_or(&[][..], |&index| &lists[index]);
+        list.iter().map(|&position| &self.calls[position])
+    }
+
+    /// The functions that `from` may call, directly or transitively,
+    /// starting with `from` itself, in breadth-first order.
+    pub fn reachable(&self, from: &str) -> Vec<&'a str> {
+        let Some(&from) = self.indices.get(from) else { return Vec::new() };
+        self.search([from]).1
+    }
+
+    /// The functions that no entry may call, in source order.
+    pub fn unreachable(&self) -> Vec<&'a str> {
+        let (reached, _) = self.search(self.entries.iter().map(|entry| self.indices[entry]));
+        self.functions.iter().zip(reached).filter(|(_, reached)| !reached).map(|(name, _)| *name).collect()
+    }
+
+    /// A breadth-first search from the functions at `starts`, which returns
+    /// whether each function was reached and the reached functions in order.
+    fn search(&self, starts: impl IntoIterator<Item = usize>) -> (Vec<bool>, Vec<&'a str>) {
+        let mut reached = vec![false; self.functions.len()];
+        let mut order = Vec::new();
+        let mut queue = VecDeque::new();
+        for start in starts {
+            if !core::mem::replace(&mut reached[start], true) {
+                order.push(self.functions[start]);
+                queue.push_back(start);
+            }
+        }
+        while let Some(caller) = queue.pop_front() {
+            for &position in &self.calls_from[caller] {
+                let callee = self.indices[self.calls[position].callee];
+                if !core::mem::replace(&mut reached[callee], true) {
+                    order.push(self.functions[callee]);
+                    queue.push_back(callee);
+                }
+            }
+        }
+        (reached, order)
+    }
+}
+
+/// Collects the call sites, with `None` as the callee of an indirect call,
+/// and the functions whose address is taken.
+struct Collector<'f, 'a> {
+    indices: &'f HashMap<&'a str, usize>,
+    address_taken: Vec<&'a str>,
+    /// Whether each function is in `address_taken`.
+    taken: Vec<bool>,
+    caller: &'a str,
+    sites: Vec<(&'a str, Option<&'a str>, Span)>,
+}
+
+impl<'a> Collector<'_, 'a> {
+    fn take_address(&mut self, name: &'a str) {
+        if let Some(&index) = self.indices.get(name) {
+            if !core::mem::replace(&mut self.taken[index], true) {
+                self.address_taken.push(name);
+            }
+        }
+    }
+}
+
+impl<'a> Visitor<'a> for Collector<'_, 'a> {
+    fn visit_function(&mut self, function: &Function<'a>) {
+        self.caller = function.name();
+        walk_function(self, function);
+    }
+
+    fn visit_instruction(&mut self, instruction: &Instruction<'a>) {
+        if instruction.opcode() != Opcode::Call {
+            return walk_instruction(self, instruction)
+        }
+        // `call (retval0), callee, (param0, ...), prototype;`, where only the
+        // lists may take an address.
+        let (lists, rest): (Vec<_>, Vec<_>) =
+            instruction.operands().partition(|operand| matches!(operand, Operand::List(_)));
+        lists.iter().for_each(|list| self.visit_operand(list));
+        let callee = match rest.first() {
+            Some(Operand::Symbol(name)) => match self.indices.contains_key(name) {
+                true => Some(*name),
+                false => return,
+            },
+            Some(Operand::Register(_)) => None,
+            _ => return,
+        };
+        self.sites.push((self.caller, callee, instruction.span()));
+    }
+
+    fn visit_operand(&mut self, operand: &Operand<'a>) {
+        if let Operand::Symbol(name) = operand {
+            self.take_address(name);
+        }
+        walk_operand(self, operand);
+    }
+}
+
+#[cfg(test)]
+mod test_call_graph {
+    use crate::{
+        parser::PtxFile,
+        ptx_files::{a, kernel},
+    };
+
+    use super::{CallGraph, CallKind};
+
+    const PANIC: &str = "_ZN4core9panicking5panic17h691abfa2aca02139E";
+    const DROP: &str = "_ZN4core3ptr88drop_in_place$LT$core$$panic$$panic_info$$PanicInfo$$internal_constructor$$NoPayload$GT$17h87ccdb342c4f9dd2E";
+    const TYPE_ID: &str = "_ZN36_$LT$T$u20$as$u20$core$$any$$Any$GT$7type_id17h2da4916f5531a2feE";
+
+    /// A vtable of `f` and `g`, an indirect call from `k` and a direct call
+    /// from `g` to `h`. `unused` is never called.
+    const INDIRECT: &str = ".version 7.5\n.target sm_30\n.address_size 64\n\
+        .func f();\n.func g();\n.func h();\n.func unused();\n\
+        .global .align 8 .u64 vtable[3] = {f, 0, g};\n\
+        .visible .entry k()\n{\n.reg .b64 %rd<2>;\n\
+        ld.global.u64 %rd1, [vtable];\ncall %rd1, ();\nret;\n}\n\
+        .func g()\n{\ncall h, ();\nret;\n}\n";
+
+    #[test]
+    fn direct() {
+        let ptx = PtxFile::try_from(a::_PTX).unwrap();
+        let graph = CallGraph::new(&ptx);
+        assert_eq!(graph.entries(), ["run_length", "thread_id"]);
+        assert_eq!(graph.address_taken(), [DROP, TYPE_ID]);
+        assert!(graph.calls().iter().all(|call| call.kind == CallKind::Direct));
+        let call = graph.calls_to(PANIC).next().unwrap();
+        assert!(a::_PTX[call.span.start..call.span.end].starts_with("call.uni"));
+        assert_eq!(graph.calls_from("run_length").count(), 3);
+        assert_eq!(graph.calls_from("thread_id").count(), 0);
+        assert_eq!(graph.reachable("thread_id"), ["thread_id"]);
+        let reachable = graph.reachable("run_length");
+        assert_eq!(reachable[0], "run_length");
+        assert!(reachable.contains(&PANIC));
+        assert!(graph.unreachable().contains(&DROP));
+        assert!(graph.reachable("missing").is_empty());
+    }
+
+    #[test]
+    fn indirect() {
+        let ptx = PtxFile::try_from(INDIRECT).unwrap();
+        let graph = CallGraph::new(&ptx);
+        assert_eq!(graph.address_taken(), ["f", "g"]);
+        let calls: Vec<_> = graph.calls().iter().map(|call| (call.caller, call.callee, call.kind)).collect();
+        assert_eq!(calls, [("k", "f", CallKind::Indirect), ("k", "g", CallKind::Indirect), ("g", "h", CallKind::Direct)]);
+        assert_eq!(graph.reachable("k"), ["k", "f", "g", "h"]);
+        assert_eq!(graph.unreachable(), ["unused"]);
+        assert_eq!(graph.calls_to("g").map(|call| call.caller).collect::<Vec<_>>(), ["k"]);
+        assert_eq!(graph.calls_from("missing").count(), 0);
+    }
+
+    #[test]
+    fn address_taken_by_instructions() {
+        let input = INDIRECT.replace("ld.global.u64 %rd1, [vtable];", "mov.u64 %rd1, h;").replace("{f, 0, g}", "{0, 0, 0}");
+        let ptx = PtxFile::try_from(input.as_str()).unwrap();
+        let graph = CallGraph::new(&ptx);
+        assert_eq!(graph.address_taken(), ["h"]);
+        assert_eq!(graph.reachable("k"), ["k", "h"]);
+    }
+
+    #[test]
+    fn address_taken_in_lists() {
+        let input = INDIRECT
+            .replace("{f, 0, g}", "{0, 0, 0}")
+            .replace("ld.global.u64 %rd1, [vtable];", "st.global.v2.u64 [vtable], {f, unused};\nld.global.u64 %rd1, [vtable];")
+            .replace("call h, ();", "call h, (g);");
+        let ptx = PtxFile::try_from(input.as_str()).unwrap();
+        let graph = CallGraph::new(&ptx);
+        assert_eq!(graph.address_taken(), ["f", "unused", "g"]);
+        assert_eq!(graph.reachable("k"), ["k", "f", "unused", "g", "h"]);
+        assert!(graph.unreachable().is_empty());
+    }
+
+    #[test]
+    fn no_calls() {
+        let ptx = PtxFile::try_from(kernel::_PTX).unwrap();
+        let graph = CallGraph::new(&ptx);
+        assert_eq!(graph.functions(), ["square_kernel"]);
+        assert!(graph.calls().is_empty());
+        assert!(graph.unreachable().is_empty());
+    }
+}
@@ -0,0 +1,280 @@
+use std::collections::{BTreeSet, VecDeque};
+
+use super::{BlockId, Cfg};
+use crate::parser::Statement;
+
+/// The facts of a dataflow analysis at a point of a function.
+pub trait Lattice: Clone + PartialEq {
+    /// Merges `other` into `self`, where control flow meets.
+    fn join(&mut self, other: &Self);
+}
+
+/// Sets join by union, as for may analyses like liveness.
+impl<T: Ord + Clone> Lattice for BTreeSet<T> {
+    fn join(&mut self, other: &Self) {
+        self.extend(other.iter().cloned());
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Direction {
+    /// Facts flow from the entry along the edges, like reaching definitions.
+    Forward,
+    /// Facts flow from the exits against the edges, like liveness.
+    Backward,
+}
+
+/// A dataflow problem over the statements of a [`Cfg`].
+pub trait Analysis<'a> {
+    type Domain: Lattice;
+
+    const DIRECTION: Direction;
+
+    /// The facts before any are known, which joining leaves unchanged.
+    fn bottom(&self, cfg: &Cfg<'a>) -> Self::Domain;
+
+    /// The facts at the start of the entry, or at the end of the exits for
+    /// a backward analysis.
+    fn boundary(&self, cfg: &Cfg<'a>) -> Self::Domain {
+        self.bottom(cfg)
+    }
+
+    /// Updates the facts before a statement to those after it, or the other
+    /// way around for a backward analysis.
+    fn transfer(&self, statement: &Statement<'a>, state: &mut Self::Domain);
+}
+
+/// The fixed point of an [`Analysis`], found with a worklist of blocks.
+///
+/// The transfer functions must be monotone and the lattice must have no
+/// infinite ascending chains for this to terminate.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Solution<D> {
+    start: Vec<D>,
+    end: Vec<D>,
+}
+
+impl<D: Lattice> Solution<D> {
+    pub fn new<'a, A: Analysis<'a, Domain = D>>(cfg: &Cfg<'a>, analysis: &A) -> Self {
+        let bottom = analysis.bottom(cfg);
+        let mut start = vec![bottom.clone(); cfg.len()];
+        let mut end = vec![bottom.clone(); cfg.len()];
+        let boundaries: Vec<_> = match A::DIRECTION {
+            Direction::Forward => vec![cfg.entry()],
+            Direction::Backward => cfg.exits().collect(),
+        };
+        let mut queued = vec![true; cfg.len()];
+        let mut worklist: VecDeque<_> = match A::DIRECTION {
+            Direction::Forward => (0..cfg.len()).map(BlockId).collect(),
+            Direction::Backward => (0..cfg.len()).rev().map(BlockId).collect(),
+        };
+
+        while let Some(block) = worklist.pop_front() {
+            queued[block.0] = false;
+            let (inputs, into, out, next) = match A::DIRECTION {
+                Direction::Forward => (cfg.predecessors(block), &end, &mut start, cfg.successors(block)),
+                Direction::Backward => (cfg.successors(block), &start, &mut end, cfg.predecessors(block)),
+            };
+            let mut state = match boundaries.contains(&block) {
+                true => analysis.boundary(cfg),
+                false => bottom.clone(),
+            };
+            inputs.iter().for_each(|input| state.join(&into[input.0]));
+            out[block.0] = state.clone();
+            transfer_block(cfg, analysis, block, &mut state);
+
+            let result = match A::DIRECTION {
+                Direction::Forward => &mut end[block.0],
+                Direction::Backward => &mut start[block.0],
+            };
+            if *result != state {
+                *result = state;
+                for next in next {
+                    if !queued[next.0] {
+                        queued[next.0] = true;
+                        worklist.push_back(*next);
+                    }
+                }
+            }
+        }
+        Self { start, end }
+    }
+
+    /// The facts at the start of a block, in program order.
+    pub fn start(&self, block: BlockId) -> &D {
+        &self.start[block.0]
+    }
+
+    /// The facts at the end of a block, in program order.
+    pub fn end(&self, block: BlockId) -> &D {
+        &self.end[block.0]
+    }
+
+    /// The facts before each statement of a block and then at its end, in
+    /// program order.
+    pub fn points<'a, A: Analysis<'a, Domain = D>>(&self, cfg: &Cfg<'a>, analysis: &A, block: BlockId) -> Vec<D> {
+        let statements = cfg.statements(block);
+        match A::DIRECTION {
+            Direction::Forward => {
+                let mut state = self.start(block).clone();
+                let mut points = vec![state.clone()];
+                for statement in statements {
+                    analysis.transfer(statement, &mut state);
+                    points.push(state.clone());
+                }
+                points
+            }
+            Direction::Backward => {
+                let mut state = self.end(block).clone();
+                let mut points = vec![state.clone()];
+                for statement in statements.iter().rev() {
+                    analysis.transfer(statement, &mut state);
+                    points.push(state.clone());
+                }
+                points.reverse();
+                points
+            }
+        }
+    }
+}
+
+fn transfer_block<'a, A: Analysis<'a>>(cfg: &Cfg<'a>, analysis: &A, block: BlockId, state: &mut A::Domain) {
+    let statements = cfg.statements(block);
+    match A::DIRECTION {
+        Direction::Forward => statements.iter().for_each(|statement| analysis.transfer(statement, state)),
+        Direction::Backward => statements.iter().rev().for_each(|statement| analysis.transfer(statement, state)),
+    }
+}
+
+#[cfg(test)]
+mod test_dataflow {
+    use std::collections::BTreeSet;
+
+    use crate::parser::{BodyLine, Operand, PtxFile, Statement};
+
+    use super::{Analysis, BlockId, Cfg, Direction, Lattice, Solution};
+
+    /// `%r1` is written on both paths to block 3, `%r2` on one of them.
+    const INPUT: &str = ".version 7.5\n.target sm_30\n.address_size 64\n.visible .entry k()\n{\n\
+        @%p1 bra $L2;\n\
+        mov.u32 %r1, 1;\nmov.u32 %r2, 1;\nbra.uni $L3;\n\
+        $L2:\nmov.u32 %r1, 2;\n\
+        $L3:\nadd.s32 %r3, %r1, %r2;\nret;\n}\n";
+
+    /// The destination of an instruction, as a stand-in for real register
+    /// accesses.
+    fn written<'a>(statement: &Statement<'a>) -> Option<&'a str> {
+        match statement.line() {
+            BodyLine::Instruction(instruction) => match instruction.operands().next() {
+                Some(Operand::Register(register)) => Some(register),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// The registers written on some path.
+    struct MaybeWritten;
+
+    impl<'a> Analysis<'a> for MaybeWritten {
+        type Domain = BTreeSet<&'a str>;
+
+        const DIRECTION: Direction = Direction::Forward;
+
+        fn bottom(&self, _cfg: &Cfg<'a>) -> Self::Domain {
+            BTreeSet::new()
+        }
+
+        fn transfer(&self, statement: &Statement<'a>, state: &mut Self::Domain) {
+            state.extend(written(statement));
+        }
+    }
+
+    /// The registers written on every path, with `None` for a block no
+    /// path has reached yet.
+    #[derive(Debug, Clone, PartialEq)]
+    struct Written<'a>(Option<BTreeSet<&'a str>>);
+
+    impl Lattice for Written<'_> {
+        fn join(&mut self, other: &Self) {
+            match (&mut self.0, &other.0) {
+                (Some(written), Some(other)) => written.retain(|register| other.contains(register)),
+                (None, Some(other)) => self.0 = Some(other.clone()),
+                (_, None) => {}
+            }
+        }
+    }
+
+    struct DefinitelyWritten;
+
+    impl<'a> Analysis<'a> for DefinitelyWritten {
+        type Domain = Written<'a>;
+
+        const DIRECTION: Direction = Direction::Forward;
+
+        fn bottom(&self, _cfg: &Cfg<'a>) -> Self::Domain {
+            Written(None)
+        }
+
+        fn boundary(&self, _cfg: &Cfg<'a>) -> Self::Domain {
+            Written(Some(BTreeSet::new()))
+        }
+
+        fn transfer(&self, statement: &Statement<'a>, state: &mut Self::Domain) {
+            if let (Some(written), Some(register)) = (&mut state.0, written(statement)) {
+                written.insert(register);
+            }
+        }
+    }
+
+    /// The statements from each point to an exit, counted backwards.
+    struct Remaining;
+
+    impl<'a> Analysis<'a> for Remaining {
+        type Domain = BTreeSet<usize>;
+
+        const DIRECTION: Direction = Direction::Backward;
+
+        fn bottom(&self, _cfg: &Cfg<'a>) -> Self::Domain {
+            BTreeSet::new()
+        }
+
+        fn boundary(&self, _cfg: &Cfg<'a>) -> Self::Domain {
+            BTreeSet::from([0])
+        }
+
+        fn transfer(&self, _statement: &Statement<'a>, state: &mut Self::Domain) {
+            *state = state.iter().map(|count| count + 1).collect();
+        }
+    }
+
+    fn set<'a>(registers: &[&'a str]) -> BTreeSet<&'a str> {
+        registers.iter().copied().collect()
+    }
+
+    #[test]
+    fn may_and_must() {
+        let ptx = PtxFile::try_from(INPUT).unwrap();
+        let cfg = Cfg::new(ptx.functions()[0].body().unwrap());
+        let join = BlockId(3);
+        let maybe = Solution::new(&cfg, &MaybeWritten);
+        assert_eq!(*maybe.start(join), set(&["%r1", "%r2"]));
+        assert_eq!(*maybe.end(join), set(&["%r1", "%r2", "%r3"]));
+        let definitely = Solution::new(&cfg, &DefinitelyWritten);
+        assert_eq!(definitely.start(join).0, Some(set(&["%r1"])));
+        assert_eq!(definitely.start(BlockId(0)).0, Some(set(&[])));
+        let points = definitely.points(&cfg, &DefinitelyWritten, BlockId(1));
+        assert_eq!(points.iter().map(|point| point.0.clone().unwrap().len()).collect::<Vec<_>>(), [0, 1, 2, 2]);
+    }
+
+    #[test]
+    fn backward() {
+        let ptx = PtxFile::try_from(INPUT).unwrap();
+        let cfg = Cfg::new(ptx.functions()[0].body().unwrap());
+        let remaining = Solution::new(&cfg, &Remaining);
+        // Through block 1 or block 2, then the three statements of block 3.
+        assert_eq!(*remaining.start(BlockId(0)), BTreeSet::from([6, 7]));
+        assert_eq!(*remaining.end(BlockId(3)), BTreeSet::from([0]));
+        assert_eq!(remaining.points(&cfg, &Remaining, BlockId(3)), [[3], [2], [1], [0]].map(BTreeSet::from));
+    }
+}
@@ -0,0 +1,195 @@
+use super::{BlockId, Cfg};
+
+/// The dominator or post-dominator tree of a [`Cfg`].
+///
+/// The post-dominator tree has a root for each exit block. Blocks that the
+/// roots do not reach, i.e. unreachable blocks for dominators and blocks
+/// that never exit for post-dominators, are not in the tree.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DominatorTree {
+    roots: Vec<BlockId>,
+    immediate: Vec<Option<BlockId>>,
+    reachable: Vec<bool>,
+    children: Vec<Vec<BlockId>>,
+}
+
+impl DominatorTree {
+    pub fn dominators(cfg: &Cfg) -> Self {
+        Self::new(cfg.len(), vec![cfg.entry()], |id| cfg.successors(id), |id| cfg.predecessors(id))
+    }
+
+    pub fn post_dominators(cfg: &Cfg) -> Self {
+        Self::new(cfg.len(), cfg.exits().collect(), |id| cfg.predecessors(id), |id| cfg.successors(id))
+    }
+
+    /// Computes the tree with the iterative algorithm of Cooper, Harvey and
+    /// Kennedy, below a virtual node that precedes the roots.
+    fn new<'c>(
+        len: usize,
+        roots: Vec<BlockId>,
+        successors: impl Fn(BlockId) -> &'c [BlockId],
+        predecessors: impl Fn(BlockId) -> &'c [BlockId],
+    ) -> Self {
+        let virtual_root = len;
+        let order = postorder(len, &roots, &successors);
+        let mut number = vec![usize::MAX; len + 1];
+        for (index, node) in order.iter().enumerate() {
+            number[*node] = index;
+        }
+        number[virtual_root] = order.len();
+
+        let mut idom = vec![None; len + 1];
+        idom[virtual_root] = Some(virtual_root);
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for &node in order.iter().rev() {
+                let virtual_predecessor = roots.contains(&BlockId(node)).then_some(virtual_root);
+                let mut processed = predecessors(BlockId(node))
+                    .iter()
+                    .map(|id| id.0)
+                    .chain(virtual_predecessor)
+                    .filter(|predecessor| idom[*predecessor].is_some());
+                let Some(first) = processed.next() else { continue };
+                let new = processed.fold(first, |a, b| intersect(&idom, &number, a, b));
+                if idom[node] != Some(new) {
+                    idom[node] = Some(new);
+                    changed = true;
+                }
+            }
+        }
+
+        let mut children = vec![Vec::new(); len];
+        let immediate: Vec<_> = (0..len)
+            .map(|node| idom[node].filter(|&parent| parent != virtual_root).map(BlockId))
+            .collect();
+        for (node, parent) in immediate.iter().enumerate() {
+            if let Some(parent) = parent {
+                children[parent.0].push(BlockId(node));
+            }
+        }
+        let reachable = (0..len).map(|node| idom[node].is_some()).collect();
+        Self { roots, immediate, reachable, children }
+    }
+
+    pub fn roots(&self) -> &[BlockId] {
+        &self.roots
+    }
+
+    /// The closest strict dominator, or `None` for a root or a block not in the tree.
+    pub fn immediate(&self, id: BlockId) -> Option<BlockId> {
+        self.immediate[id.0]
+    }
+
+    pub fn children(&self, id: BlockId) -> &[BlockId] {
+        &self.children[id.0]
+    }
+
+    pub fn contains(&self, id: BlockId) -> bool {
+        self.reachable[id.0]
+    }
+
+    /// Whether `a` dominates `b`. Every block in the tree dominates itself.
+    pub fn dominates(&self, a: BlockId, b: BlockId) -> bool {
+        if !self.contains(a) || !self.contains(b) {
+            return false
+        }
+        let mut node = Some(b);
+        while let Some(id) = node {
+            if id == a {
+                return true
+            }
+            node = self.immediate(id);
+        }
+        false
+    }
+}
+
+/// The nodes reachable from the roots, in postorder.
+fn postorder<'c>(len: usize, roots: &[BlockId], successors: &impl Fn(BlockId) -> &'c [BlockId]) -> Vec<usize> {
+    let mut visited = vec![false; len];
+    let mut order = Vec::with_capacity(len);
+    for root in roots {
+        if visited[root.0] {
+            continue
+        }
+        visited[root.0] = true;
+        let mut stack = vec![(root.0, 0)];
+        while let Some((node, next)) = stack.last_mut() {
+            match successors(BlockId(*node)).get(*next) {
+                Some(successor) => {
+                    *next += 1;
+                    if !visited[successor.0] {
+                        visited[successor.0] = true;
+                        stack.push((successor.0, 0));
+                    }
+                }
+                None => {
+                    order.push(*node);
+                    stack.pop();
+                }
+            }
+        }
+    }
+    order
+}
+
+fn intersect(idom: &[Option<usize>], number: &[usize], mut a: usize, mut b: usize) -> usize {
+    while a != b {
+        while number[a] < number[b] {
+            a = idom[a].expect("processed nodes have a dominator");
+        }
+        while number[b] < number[a] {
+            b = idom[b].expect("processed nodes have a dominator");
+        }
+    }
+    a
+}
+
+#[cfg(test)]
+mod test_dominators {
+    use crate::parser::PtxFile;
+
+    use super::{BlockId, Cfg, DominatorTree};
+
+    /// A diamond, `0 -> {1, 2} -> 3`, then a loop `4 -> 4` with an exit to
+    /// `5`, an early return in `6`, and the unreachable block `7`.
+    const INPUT: &str = ".version 7.5\n.target sm_30\n.address_size 64\n.visible .entry k()\n{\n\
+        @%p1 bra $L2;\n\
+        mov.u32 %r1, 1;\nbra.uni $L3;\n\
+        $L2:\nmov.u32 %r1, 2;\n\
+        $L3:\nadd.s32 %r1, %r1, 1;\n\
+        $L4:\nadd.s32 %r1, %r1, 1;\n@%p2 bra $L4;\n\
+        @%p3 bra $L6;\n\
+        $L6:\n@%p4 ret;\n\
+        exit;\nmov.u32 %r1, 3;\n}\n";
+
+    #[test]
+    fn dominators() {
+        let ptx = PtxFile::try_from(INPUT).unwrap();
+        let cfg = Cfg::new(ptx.functions()[0].body().unwrap());
+        assert_eq!(cfg.len(), 9);
+        let tree = DominatorTree::dominators(&cfg);
+        let idom = |index| tree.immediate(BlockId(index)).map(BlockId::index);
+        assert_eq!((0..9).map(idom).collect::<Vec<_>>(), [None, Some(0), Some(0), Some(0), Some(3), Some(4), Some(5), Some(6), None]);
+        assert!(tree.dominates(BlockId(0), BlockId(6)));
+        assert!(tree.dominates(BlockId(4), BlockId(4)));
+        assert!(!tree.dominates(BlockId(1), BlockId(3)));
+        assert!(!tree.contains(BlockId(8)));
+        assert_eq!(tree.children(BlockId(0)), [BlockId(1), BlockId(2), BlockId(3)]);
+    }
+
+    #[test]
+    fn post_dominators() {
+        let ptx = PtxFile::try_from(INPUT).unwrap();
+        let cfg = Cfg::new(ptx.functions()[0].body().unwrap());
+        let tree = DominatorTree::post_dominators(&cfg);
+        // The predicated `ret` in 6 and `exit` in 7 both leave the function.
+        assert_eq!(tree.roots(), [BlockId(6), BlockId(7), BlockId(8)]);
+        assert_eq!(tree.immediate(BlockId(6)), None);
+        assert_eq!(tree.immediate(BlockId(1)), Some(BlockId(3)));
+        assert_eq!(tree.immediate(BlockId(0)), Some(BlockId(3)));
+        assert!(tree.dominates(BlockId(6), BlockId(0)));
+        assert!(!tree.dominates(BlockId(7), BlockId(0)));
+    }
+}
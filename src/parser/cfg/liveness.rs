@@ -0,0 +1,170 @@
+use std::collections::{BTreeSet, HashMap};
+
+use super::{Analysis, BlockId, Cfg, Direction, Solution};
+use crate::parser::{BodyLine, Resolution, Span, Statement, SymbolKind, SymbolTable, UseKind};
+
+/// The registers live before and after each statement of a function.
+///
+/// A register is live where a later statement may read the value it holds.
+/// A predicated write may not happen, so the value before it stays live.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Liveness<'a> {
+    /// For each block, the registers live before each statement, then
+    /// those live out of the block.
+    live: Vec<Vec<BTreeSet<&'a str>>>,
+}
+
+/// The register accesses of an instruction, reads before writes.
+#[derive(Debug, Clone, Default)]
+pub(super) struct Step<'a> {
+    pub(super) reads: Vec<(&'a str, Span)>,
+    pub(super) writes: Vec<(&'a str, Span)>,
+    pub(super) predicated: bool,
+}
+
+impl<'a> Liveness<'a> {
+    /// `table` resolves the registers of the module the function is from.
+    pub fn new(cfg: &Cfg<'a>, table: &SymbolTable<'a>) -> Self {
+        let analysis = Live { steps: steps(cfg, table) };
+        let solution = Solution::new(cfg, &analysis);
+        let live = cfg.blocks().map(|(id, _)| solution.points(cfg, &analysis, id)).collect();
+        Self { live }
+    }
+
+    pub fn live_in(&self, block: BlockId) -> &BTreeSet<&'a str> {
+        &self.live[block.0][0]
+    }
+
+    pub fn live_out(&self, block: BlockId) -> &BTreeSet<&'a str> {
+        self.live[block.0].last().expect("a block has its live out set")
+    }
+
+    /// The registers live before the statement at `index` of the block.
+    pub fn live_before(&self, block: BlockId, index: usize) -> &BTreeSet<&'a str> {
+        &self.live[block.0][index]
+    }
+
+    /// The registers live after the statement at `index` of the block.
+    pub fn live_after(&self, block: BlockId, index: usize) -> &BTreeSet<&'a str> {
+        &self.live[block.0][index + 1]
+    }
+}
+
+/// Liveness as a backward analysis over sets of registers.
+struct Live<'a> {
+    steps: HashMap<Span, Vec<Step<'a>>>,
+}
+
+impl<'a> Analysis<'a> for Live<'a> {
+    type Domain = BTreeSet<&'a str>;
+
+    const DIRECTION: Direction = Direction::Backward;
+
+    fn bottom(&self, _cfg: &Cfg<'a>) -> Self::Domain {
+        BTreeSet::new()
+    }
+
+    fn transfer(&self, statement: &Statement<'a>, live: &mut Self::Domain) {
+        for step in self.steps[&statement.span()].iter().rev() {
+            if !step.predicated {
+                step.writes.iter().for_each(|(register, _)| {
+                    live.remove(register);
+                });
+            }
+            live.extend(step.reads.iter().map(|(register, _)| *register));
+        }
+    }
+}
+
+/// The register accesses of each statement of `cfg` by span, collected
+/// once rather than on every visit of the solver.
+pub(super) fn steps<'a>(cfg: &Cfg<'a>, table: &SymbolTable<'a>) -> HashMap<Span, Vec<Step<'a>>> {
+    let statements = cfg.blocks().flat_map(|(id, _)| cfg.statements(id));
+    statements.map(|statement| (statement.span(), statement_steps(statement, table))).collect()
+}
+
+/// The register accesses of a statement. A call sequence has a step for
+/// each of its statements, and other statements have one.
+fn statement_steps<'a>(statement: &Statement<'a>, table: &SymbolTable<'a>) -> Vec<Step<'a>> {
+    let predicated = match statement.line() {
+        BodyLine::FunctionCall(call) => {
+            return call.statements().flatten().flat_map(|statement| statement_steps(&statement, table)).collect()
+        }
+        BodyLine::Instruction(instruction) => instruction.predicate().is_some(),
+        BodyLine::Goto(goto) => goto.predicate().is_some(),
+        _ => false,
+    };
+    let mut step = Step { predicated, ..Default::default() };
+    let span = statement.span();
+    let references = table.references();
+    let start = references.partition_point(|reference| reference.span().start < span.start);
+    for reference in references[start..].iter().take_while(|reference| reference.span().end <= span.end) {
+        let Resolution::Symbol(id) = reference.resolution() else { continue };
+        if table.symbol(id).kind() != SymbolKind::Register {
+            continue
+        }
+        match reference.kind() {
+            UseKind::Read => step.reads.push((reference.name(), reference.span())),
+            UseKind::Write => step.writes.push((reference.name(), reference.span())),
+            _ => {}
+        }
+    }
+    vec![step]
+}
+
+#[cfg(test)]
+mod test_liveness {
+    use std::collections::BTreeSet;
+
+    use crate::parser::{PtxFile, SymbolTable};
+
+    use super::{BlockId, Cfg, Liveness};
+
+    /// A loop in block 1 that adds `%r1` to `%r2`, then a predicated write
+    /// to `%r2` and a store through `%r4`, which is never written.
+    const INPUT: &str = ".version 7.5\n.target sm_30\n.address_size 64\n\
+        .visible .entry k(.param .u32 n)\n{\n.reg .pred %p<2>;\n.reg .b32 %r<5>;\n\
+        ld.param.u32 %r1, [n];\nmov.u32 %r2, 0;\nmov.u32 %r3, 1;\n\
+        $L1:\nadd.s32 %r2, %r2, %r1;\nsetp.lt.s32 %p1, %r2, 100;\n@%p1 bra $L1;\n\
+        @%p1 mov.u32 %r2, 7;\nst.global.u32 [%r4], %r2;\nret;\n}\n";
+
+    fn set<'a>(registers: &[&'a str]) -> BTreeSet<&'a str> {
+        registers.iter().copied().collect()
+    }
+
+    #[test]
+    fn loop_and_predicated_write() {
+        let ptx = PtxFile::try_from(INPUT).unwrap();
+        let table = SymbolTable::new(&ptx);
+        let cfg = Cfg::new(ptx.functions()[0].body().unwrap());
+        let liveness = Liveness::new(&cfg, &table);
+        assert_eq!(*liveness.live_in(BlockId(0)), set(&["%r4"]));
+        assert_eq!(*liveness.live_in(BlockId(1)), set(&["%r1", "%r2", "%r4"]));
+        assert_eq!(*liveness.live_out(BlockId(1)), set(&["%p1", "%r1", "%r2", "%r4"]));
+        // The predicated `mov` does not end the liveness of `%r2`.
+        assert_eq!(*liveness.live_in(BlockId(2)), set(&["%p1", "%r2", "%r4"]));
+        assert!(liveness.live_out(BlockId(2)).is_empty());
+        // `setp` writes `%p1`, which the branch reads.
+        assert_eq!(*liveness.live_before(BlockId(1), 2), set(&["%r1", "%r2", "%r4"]));
+        assert_eq!(*liveness.live_after(BlockId(1), 2), set(&["%p1", "%r1", "%r2", "%r4"]));
+        // `%r3` is written but never read.
+        assert!(!liveness.live_after(BlockId(0), 4).contains("%r3"));
+    }
+
+    #[test]
+    fn call_sequences() {
+        let input = ".version 7.5\n.target sm_30\n.address_size 64\n\
+            .func (.param .b32 ret) f(.param .b32 x);\n\
+            .visible .entry k()\n{\n.reg .b32 %r<3>;\nmov.u32 %r1, 1;\n{\n.param .b32 param0;\n\
+            st.param.b32 [param0+0], %r1;\n.param .b32 retval0;\n\
+            call.uni (retval0), f, (param0);\nld.param.b32 %r2, [retval0+0];\n}\n\
+            st.global.u32 [%r2], %r2;\nret;\n}\n";
+        let ptx = PtxFile::try_from(input).unwrap();
+        let table = SymbolTable::new(&ptx);
+        let cfg = Cfg::new(ptx.functions()[1].body().unwrap());
+        let liveness = Liveness::new(&cfg, &table);
+        assert!(liveness.live_in(BlockId(0)).is_empty());
+        assert_eq!(*liveness.live_before(BlockId(0), 2), set(&["%r1"]));
+        assert_eq!(*liveness.live_after(BlockId(0), 2), set(&["%r2"]));
+    }
+}
@@ -0,0 +1,233 @@
+use std::collections::BTreeMap;
+
+use super::{BlockId, Cfg, DominatorTree, Edge};
+use crate::parser::{BodyLine, Statement};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct LoopId(usize);
+
+/// A natural loop: the blocks that reach one of its back edges without
+/// passing through its header. Back edges to the same header form one loop.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Loop {
+    header: BlockId,
+    latches: Vec<BlockId>,
+    body: Vec<BlockId>,
+    exits: Vec<Edge>,
+    parent: Option<LoopId>,
+    depth: usize,
+}
+
+/// The natural loops of a [`Cfg`], ordered by header.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Loops {
+    loops: Vec<Loop>,
+    innermost: Vec<Option<LoopId>>,
+}
+
+impl Loops {
+    pub fn new(cfg: &Cfg, dominators: &DominatorTree) -> Self {
+        let mut latches = BTreeMap::<BlockId, Vec<BlockId>>::new();
+        for edge in cfg.edges() {
+            if dominators.dominates(edge.to, edge.from) && !latches.get(&edge.to).is_some_and(|l| l.contains(&edge.from)) {
+                latches.entry(edge.to).or_default().push(edge.from);
+            }
+        }
+
+        let mut loops: Vec<_> = latches.into_iter().map(|(header, latches)| {
+            let mut contains = vec![false; cfg.len()];
+            contains[header.0] = true;
+            let mut stack = latches.clone();
+            while let Some(block) = stack.pop() {
+                if !contains[block.0] && dominators.contains(block) {
+                    contains[block.0] = true;
+                    stack.extend_from_slice(cfg.predecessors(block));
+                }
+            }
+            let body: Vec<_> = (0..cfg.len()).filter(|index| contains[*index]).map(BlockId).collect();
+            let exits = cfg.edges().iter().filter(|edge| contains[edge.from.0] && !contains[edge.to.0]).copied().collect();
+            Loop { header, latches, body, exits, parent: None, depth: 1 }
+        }).collect();
+
+        // Natural loops with different headers are either nested or disjoint.
+        for index in 0..loops.len() {
+            let header = loops[index].header;
+            loops[index].parent = (0..loops.len())
+                .filter(|other| *other != index && loops[*other].contains(header))
+                .min_by_key(|other| loops[*other].body.len())
+                .map(LoopId);
+        }
+        for index in 0..loops.len() {
+            let mut parent = loops[index].parent;
+            while let Some(id) = parent {
+                loops[index].depth += 1;
+                parent = loops[id.0].parent;
+            }
+        }
+
+        let mut innermost = vec![None::<LoopId>; cfg.len()];
+        for (index, current) in loops.iter().enumerate() {
+            for block in &current.body {
+                let slot = &mut innermost[block.0];
+                let deeper = match slot {
+                    Some(id) => loops[id.0].depth < current.depth,
+                    None => true,
+                };
+                if deeper {
+                    *slot = Some(LoopId(index));
+                }
+            }
+        }
+        Self { loops, innermost }
+    }
+
+    pub fn len(&self) -> usize {
+        self.loops.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.loops.is_empty()
+    }
+
+    pub fn get(&self, id: LoopId) -> &Loop {
+        &self.loops[id.0]
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (LoopId, &Loop)> {
+        self.loops.iter().enumerate().map(|(index, current)| (LoopId(index), current))
+    }
+
+    /// The innermost loop containing `block`.
+    pub fn innermost(&self, block: BlockId) -> Option<LoopId> {
+        self.innermost[block.0]
+    }
+
+    /// How many loops contain `block`.
+    pub fn depth(&self, block: BlockId) -> usize {
+        self.innermost(block).map_or(0, |id| self.loops[id.0].depth)
+    }
+
+    /// `.pragma "nounroll"` statements that take no effect where they are:
+    /// those that are neither before the first instruction of a loop header
+    /// nor, applying to the whole function, of the entry block.
+    pub fn misplaced_nounroll<'c, 'a>(&'c self, cfg: &'c Cfg<'a>) -> impl Iterator<Item = &'c Statement<'a>> {
+        cfg.blocks().flat_map(move |(id, _)| {
+            let is_header = id == cfg.entry() || self.loops.iter().any(|current| current.header == id);
+            let statements = cfg.statements(id);
+            let leading = if is_header { statements.iter().take_while(|statement| is_declaration(statement)).count() } else { 0 };
+            statements[leading..].iter().filter(|statement| is_nounroll(statement))
+        })
+    }
+}
+
+impl Loop {
+    pub fn header(&self) -> BlockId {
+        self.header
+    }
+
+    /// The sources of the back edges to the header.
+    pub fn latches(&self) -> &[BlockId] {
+        &self.latches
+    }
+
+    /// The blocks of the loop, including the header and nested loops, in source order.
+    pub fn body(&self) -> &[BlockId] {
+        &self.body
+    }
+
+    pub fn contains(&self, block: BlockId) -> bool {
+        self.body.binary_search(&block).is_ok()
+    }
+
+    /// The edges leaving the loop.
+    pub fn exits(&self) -> &[Edge] {
+        &self.exits
+    }
+
+    /// The innermost enclosing loop.
+    pub fn parent(&self) -> Option<LoopId> {
+        self.parent
+    }
+
+    /// 1 for an outermost loop.
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    /// Whether the header starts with `.pragma "nounroll";`, before any instruction.
+    pub fn is_nounroll(&self, cfg: &Cfg) -> bool {
+        cfg.statements(self.header).iter().take_while(|statement| is_declaration(statement)).any(|statement| is_nounroll(&statement))
+    }
+}
+
+impl LoopId {
+    pub fn index(self) -> usize {
+        self.0
+    }
+}
+
+/// Labels and directives, which may come before the first instruction of a block.
+fn is_declaration(statement: &&Statement) -> bool {
+    matches!(statement.line(), BodyLine::Label(_) | BodyLine::Directive(_) | BodyLine::Register(_))
+}
+
+fn is_nounroll(statement: &&Statement) -> bool {
+    match statement.line() {
+        BodyLine::Directive(directive) => directive.name() == "pragma" && directive.arguments().trim() == "\"nounroll\"",
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod test_loops {
+    use crate::parser::PtxFile;
+
+    use super::{BlockId, Cfg, DominatorTree, LoopId, Loops};
+
+    /// An outer loop on `$L1` around an inner loop on `$L2`, the latter
+    /// marked `nounroll`, and a stray pragma in the exit block.
+    const INPUT: &str = ".version 7.5\n.target sm_30\n.address_size 64\n.visible .entry k()\n{\n\
+        mov.u32 %r1, 0;\n\
+        $L1:\nmov.u32 %r2, 0;\n\
+        $L2:\n.pragma \"nounroll\";\nadd.s32 %r2, %r2, 1;\n@%p1 bra $L2;\n\
+        add.s32 %r1, %r1, 1;\n@%p2 bra $L1;\n\
+        .pragma \"nounroll\";\n@%p3 bra $L3;\nret;\n\
+        $L3:\ntrap;\n}\n";
+
+    fn loops(cfg: &Cfg) -> Loops {
+        Loops::new(cfg, &DominatorTree::dominators(cfg))
+    }
+
+    #[test]
+    fn nested() {
+        let ptx = PtxFile::try_from(INPUT).unwrap();
+        let cfg = Cfg::new(ptx.functions()[0].body().unwrap());
+        let loops = loops(&cfg);
+        assert_eq!(loops.len(), 2);
+        let (outer, inner) = (loops.get(LoopId(0)), loops.get(LoopId(1)));
+        assert_eq!(outer.header(), BlockId(1));
+        assert_eq!(outer.body(), [BlockId(1), BlockId(2), BlockId(3)]);
+        assert_eq!(outer.latches(), [BlockId(3)]);
+        assert_eq!(outer.exits().iter().map(|edge| edge.to).collect::<Vec<_>>(), [BlockId(4)]);
+        assert_eq!((outer.parent(), outer.depth()), (None, 1));
+
+        assert_eq!(inner.header(), BlockId(2));
+        assert_eq!(inner.body(), [BlockId(2)]);
+        assert_eq!((inner.parent(), inner.depth()), (Some(LoopId(0)), 2));
+        assert_eq!(loops.depth(BlockId(2)), 2);
+        assert_eq!(loops.depth(BlockId(3)), 1);
+        assert_eq!(loops.depth(BlockId(0)), 0);
+        assert_eq!(loops.innermost(BlockId(2)), Some(LoopId(1)));
+    }
+
+    #[test]
+    fn nounroll() {
+        let ptx = PtxFile::try_from(INPUT).unwrap();
+        let cfg = Cfg::new(ptx.functions()[0].body().unwrap());
+        let loops = loops(&cfg);
+        assert!(!loops.get(LoopId(0)).is_nounroll(&cfg));
+        assert!(loops.get(LoopId(1)).is_nounroll(&cfg));
+        let misplaced: Vec<_> = loops.misplaced_nounroll(&cfg).map(|statement| statement.span().start).collect();
+        assert_eq!(misplaced, [INPUT.rfind(".pragma").unwrap()]);
+    }
+}
@@ -0,0 +1,504 @@
+//! Control flow graphs of function bodies.
+//!
+//! A body is split into basic blocks at labels and after each branch,
+//! `brx.idx`, return, `exit` and `trap`. A predicated terminator may also
+//! fall through. Call sequences are ordinary statements, since the call
+//! returns. Labels are looked up without their `$`, so `$L1` and `L1` are
+//! the same block.
+//!
+//! Dominators, natural loops, register liveness, reaching definitions and
+//! register pressure are computed over the graph, the dataflow analyses
+//! among them with the solver for [`Analysis`].
+
+use std::{collections::HashMap, ops::Range};
+
+use super::{BodyLine, FunctionBody, Opcode, Operand, Statement};
+
+mod dataflow;
+mod dominators;
+mod liveness;
+mod loops;
+mod pressure;
+mod reaching;
+
+pub use dataflow::{Analysis, Direction, Lattice, Solution};
+pub use dominators::DominatorTree;
+pub use liveness::Liveness;
+pub use loops::{Loop, LoopId, Loops};
+pub use pressure::{Point, Pressure, RegisterPressure};
+pub use reaching::{Definition, DefinitionId, ReachingDefinitions};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct BlockId(usize);
+
+/// How a basic block ends.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Terminator {
+    /// The block falls through into the next one, or off the end of the body.
+    FallThrough,
+    Branch,
+    /// `brx.idx` through a `.branchtargets` table.
+    JumpTable,
+    Return,
+    Exit,
+    Trap,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EdgeKind {
+    FallThrough,
+    /// An unpredicated branch.
+    Jump,
+    /// A predicated branch, taken when its predicate holds.
+    Conditional,
+    /// One of the targets of a `brx.idx`.
+    JumpTable,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Edge {
+    pub from: BlockId,
+    pub to: BlockId,
+    pub kind: EdgeKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BasicBlock<'a> {
+    label: Option<&'a str>,
+    statements: Range<usize>,
+    terminator: Terminator,
+    predicated: bool,
+    successors: Vec<BlockId>,
+    predecessors: Vec<BlockId>,
+}
+
+/// The control flow graph of a function body.
+///
+/// The first block is the entry. Statements that fail to parse are left
+/// out.
+#[derive(Debug)]
+pub struct Cfg<'a> {
+    statements: Vec<Statement<'a>>,
+    blocks: Vec<BasicBlock<'a>>,
+    edges: Vec<Edge>,
+    labels: HashMap<&'a str, BlockId>,
+}
+
+impl<'a> Cfg<'a> {
+    pub fn new(body: FunctionBody<'a>) -> Self {
+        let statements: Vec<_> = body.flatten().collect();
+        let blocks = split(&statements);
+        let mut labels = HashMap::new();
+        for (index, block) in blocks.iter().enumerate() {
+            if let Some(label) = block.label {
+                labels.entry(label).or_insert(BlockId(index));
+            }
+        }
+        let mut cfg = Self { statements, blocks, edges: Vec::new(), labels };
+        let tables = branch_tables(&cfg.statements);
+        for index in 0..cfg.blocks.len() {
+            let edges = cfg.edges_from(BlockId(index), &tables);
+            cfg.edges.extend(edges);
+        }
+        for edge in &cfg.edges {
+            let successors = &mut cfg.blocks[edge.from.0].successors;
+            if !successors.contains(&edge.to) {
+                successors.push(edge.to);
+                cfg.blocks[edge.to.0].predecessors.push(edge.from);
+            }
+        }
+        cfg
+    }
+
+    pub fn entry(&self) -> BlockId {
+        BlockId(0)
+    }
+
+    pub fn len(&self) -> usize {
+        self.blocks.len()
+    }
+
+    /// Always false, since even an empty body has an entry block.
+    pub fn is_empty(&self) -> bool {
+        self.blocks.is_empty()
+    }
+
+    pub fn block(&self, id: BlockId) -> &BasicBlock<'a> {
+        &self.blocks[id.0]
+    }
+
+    /// The blocks in source order.
+    pub fn blocks(&self) -> impl Iterator<Item = (BlockId, &BasicBlock<'a>)> {
+        self.blocks.iter().enumerate().map(|(index, block)| (BlockId(index), block))
+    }
+
+    pub fn statements(&self, id: BlockId) -> &[Statement<'a>] {
+        &self.statements[self.blocks[id.0].statements.clone()]
+    }
+
+    pub fn successors(&self, id: BlockId) -> &[BlockId] {
+        &self.blocks[id.0].successors
+    }
+
+    pub fn predecessors(&self, id: BlockId) -> &[BlockId] {
+        &self.blocks[id.0].predecessors
+    }
+
+    /// Every edge, grouped by source block. A predicated branch to the
+    /// next block has both a conditional and a fall-through edge.
+    pub fn edges(&self) -> &[Edge] {
+        &self.edges
+    }
+
+    /// The block starting at `label`, given without any `$`.
+    pub fn block_of(&self, label: &str) -> Option<BlockId> {
+        self.labels.get(label).copied()
+    }
+
+    /// The blocks where control may leave the function: those ending in a
+    /// return, `exit` or `trap`, predicated or not, and a last block that
+    /// falls off the end of the body.
+    pub fn exits(&self) -> impl Iterator<Item = BlockId> + '_ {
+        self.blocks()
+            .filter(|(id, block)| match block.terminator {
+                Terminator::Return | Terminator::Exit | Terminator::Trap => true,
+                Terminator::FallThrough => id.0 + 1 == self.blocks.len(),
+                Terminator::Branch | Terminator::JumpTable => false,
+            })
+            .map(|(id, _)| id)
+    }
+
+    fn edges_from(&self, from: BlockId, tables: &HashMap<&'a str, Vec<&'a str>>) -> Vec<Edge> {
+        let block = &self.blocks[from.0];
+        let last = block.statements.clone().last().map(|index| self.statements[index].line());
+        let mut targets = Vec::new();
+        match (block.terminator, last) {
+            (Terminator::Branch, Some(line)) => {
+                let kind = if block.predicated { EdgeKind::Conditional } else { EdgeKind::Jump };
+                match branch_target(line).and_then(|label| self.block_of(label)) {
+                    Some(to) => targets.push((to, kind)),
+                    // Without a known target, any labeled block may be one.
+                    None => targets.extend(self.labeled_blocks().into_iter().map(|to| (to, kind))),
+                }
+            }
+            (Terminator::JumpTable, Some(BodyLine::Instruction(instruction))) => {
+                let table = instruction.operands().nth(1).and_then(|operand| match operand {
+                    Operand::Symbol(name) => tables.get(name.trim_start_matches('$')),
+                    _ => None,
+                });
+                let blocks = match table {
+                    Some(labels) => {
+                        let mut blocks: Vec<_> = labels.iter().filter_map(|label| self.block_of(label)).collect();
+                        blocks.sort();
+                        blocks.dedup();
+                        blocks
+                    }
+                    // Without its table, any labeled block may be a target.
+                    None => self.labeled_blocks(),
+                };
+                targets.extend(blocks.into_iter().map(|to| (to, EdgeKind::JumpTable)));
+            }
+            _ => {}
+        }
+        let falls_through = block.terminator == Terminator::FallThrough || block.predicated;
+        if falls_through && from.0 + 1 < self.blocks.len() {
+            targets.push((BlockId(from.0 + 1), EdgeKind::FallThrough));
+        }
+        targets.into_iter().map(|(to, kind)| Edge { from, to, kind }).collect()
+    }
+
+    /// The blocks that start with a label, in source order.
+    fn labeled_blocks(&self) -> Vec<BlockId> {
+        let mut blocks: Vec<_> = self.labels.values().copied().collect();
+        blocks.sort();
+        blocks
+    }
+}
+
+impl<'a> BasicBlock<'a> {
+    fn new(label: Option<&'a str>, statements: Range<usize>, terminator: Terminator, predicated: bool) -> Self {
+        Self { label, statements, terminator, predicated, successors: Vec::new(), predecessors: Vec::new() }
+    }
+
+    /// The label the block starts with, without any `$`.
+    pub fn label(&self) -> Option<&'a str> {
+        self.label
+    }
+
+    pub fn terminator(&self) -> Terminator {
+        self.terminator
+    }
+
+    /// Whether the terminator is predicated, so that control may also fall through.
+    pub fn is_predicated(&self) -> bool {
+        self.predicated
+    }
+
+    pub fn successors(&self) -> &[BlockId] {
+        &self.successors
+    }
+
+    pub fn predecessors(&self) -> &[BlockId] {
+        &self.predecessors
+    }
+}
+
+impl BlockId {
+    /// The position of the block in source order.
+    pub fn index(self) -> usize {
+        self.0
+    }
+}
+
+/// Splits the statements into blocks, which have no edges yet.
+fn split<'a>(statements: &[Statement<'a>]) -> Vec<BasicBlock<'a>> {
+    let mut blocks = Vec::new();
+    let mut start = 0;
+    let mut label = None;
+    for (index, statement) in statements.iter().enumerate() {
+        if let BodyLine::Label(name) = statement.line() {
+            if index > start || label.is_some() {
+                blocks.push(BasicBlock::new(label, start..index, Terminator::FallThrough, false));
+            }
+            (start, label) = (index, Some(name.trim_start_matches('$')));
+        }
+        if let Some((terminator, predicated)) = terminator(statement.line()) {
+            blocks.push(BasicBlock::new(label.take(), start..index + 1, terminator, predicated));
+            start = index + 1;
+        }
+    }
+    if start < statements.len() || label.is_some() || blocks.is_empty() {
+        blocks.push(BasicBlock::new(label, start..statements.len(), Terminator::FallThrough, false));
+    }
+    blocks
+}
+
+/// Returns how `line` ends a block, and whether it is predicated.
+fn terminator(line: &BodyLine) -> Option<(Terminator, bool)> {
+    match line {
+        BodyLine::Goto(goto) => Some((Terminator::Branch, goto.predicate().is_some())),
+        BodyLine::Return { .. } => Some((Terminator::Return, false)),
+        BodyLine::Instruction(instruction) => {
+            let terminator = match instruction.opcode() {
+                Opcode::Bra => Terminator::Branch,
+                Opcode::Brx => Terminator::JumpTable,
+                Opcode::Ret => Terminator::Return,
+                Opcode::Exit => Terminator::Exit,
+                Opcode::Trap => Terminator::Trap,
+                _ => return None,
+            };
+            Some((terminator, instruction.predicate().is_some()))
+        }
+        _ => None,
+    }
+}
+
+/// The label a branch jumps to, without its `$`.
+fn branch_target<'a>(line: &BodyLine<'a>) -> Option<&'a str> {
+    match line {
+        BodyLine::Goto(goto) => Some(goto.label().trim_start_matches('$')),
+        BodyLine::Instruction(instruction) => instruction.operands().find_map(|operand| match operand {
+            Operand::Symbol(label) => Some(label.trim_start_matches('$')),
+            _ => None,
+        }),
+        _ => None,
+    }
+}
+
+/// Collects the `.branchtargets` tables by name, without `$`s. A table is
+/// declared as `$name: .branchtargets $L1, $L2;` or `name: .branchtargets ...`.
+fn branch_tables<'a>(statements: &[Statement<'a>]) -> HashMap<&'a str, Vec<&'a str>> {
+    let mut tables = HashMap::new();
+    let labels = |targets: &'a str| targets.split(',').map(|label| label.trim().trim_start_matches('$')).collect();
+    for pair in statements.windows(2) {
+        if let (BodyLine::Label(name), BodyLine::Directive(directive)) = (pair[0].line(), pair[1].line()) {
+            if directive.name() == "branchtargets" {
+                tables.insert(name.trim_start_matches('$'), labels(directive.arguments()));
+            }
+        }
+    }
+    tables
+}
+
+#[cfg(test)]
+mod test_cfg {
+    use crate::{
+        parser::{BodyLine, PtxFile, SymbolTable},
+        ptx_files::{a, b, c, d, kernel},
+    };
+
+    use super::{BlockId, Cfg, DominatorTree, EdgeKind, Liveness, Loops, ReachingDefinitions, Terminator};
+
+    fn cfg(input: &str) -> Cfg<'_> {
+        let ptx = PtxFile::try_from(input).unwrap();
+        Cfg::new(ptx.functions()[0].body().unwrap())
+    }
+
+    fn body(statements: &str) -> String {
+        format!(".version 7.5\n.target sm_30\n.address_size 64\n.visible .entry k()\n{{\n{statements}}}\n")
+    }
+
+    fn successors(cfg: &Cfg, index: usize) -> Vec<usize> {
+        cfg.successors(BlockId(index)).iter().map(|id| id.index()).collect()
+    }
+
+    #[test]
+    fn kernel() {
+        let ptx = PtxFile::try_from(kernel::_PTX).unwrap();
+        let cfg = Cfg::new(ptx.functions()[0].body().unwrap());
+        // The entry ends with `@%p1 bra $L__BB0_2;`, then `bra.uni $L__BB0_1;`,
+        // then the two labeled blocks.
+        assert_eq!(cfg.len(), 4);
+        assert_eq!(successors(&cfg, 0), [2, 1]);
+        assert_eq!(successors(&cfg, 1), [3]);
+        assert_eq!(successors(&cfg, 2), [3]);
+        assert_eq!(successors(&cfg, 3), [0usize; 0]);
+        assert_eq!(cfg.predecessors(BlockId(3)), [BlockId(1), BlockId(2)]);
+        assert_eq!(cfg.block_of("L__BB0_1"), Some(BlockId(3)));
+        assert_eq!(cfg.block(BlockId(3)).terminator(), Terminator::Return);
+        assert!(cfg.block(BlockId(0)).is_predicated());
+        let kinds: Vec<_> = cfg.edges().iter().map(|edge| edge.kind).collect();
+        assert_eq!(kinds, [EdgeKind::Conditional, EdgeKind::FallThrough, EdgeKind::Jump, EdgeKind::FallThrough]);
+        assert_eq!(cfg.exits().collect::<Vec<_>>(), [BlockId(3)]);
+    }
+
+    #[test]
+    fn jump_tables() {
+        let input = body("$ts: .branchtargets $L1, $L2;\ntable: .branchtargets $L2;\n\
+            brx.idx %r1, $ts;\nbrx.idx.uni %r1, table;\n$L1:\nexit;\n$L2:\ntrap;\n");
+        let cfg = cfg(&input);
+        // Each table label starts a block, the second with the first `brx.idx`.
+        assert_eq!(cfg.block(BlockId(0)).label(), Some("ts"));
+        assert_eq!(cfg.block(BlockId(1)).label(), Some("table"));
+        assert_eq!(successors(&cfg, 0), [1]);
+        assert_eq!(cfg.block(BlockId(1)).terminator(), Terminator::JumpTable);
+        assert_eq!(successors(&cfg, 1), [3, 4]);
+        assert_eq!(successors(&cfg, 2), [4]);
+        assert_eq!(cfg.block(BlockId(3)).terminator(), Terminator::Exit);
+        assert_eq!(cfg.block(BlockId(4)).terminator(), Terminator::Trap);
+        assert_eq!(cfg.exits().count(), 2);
+    }
+
+    #[test]
+    fn plain_labels() {
+        let input = body("@%p1 bra LBB0_2;
+mov.u32 %r1, 0;
+LBB0_2:
+ret;
+");
+        let cfg = cfg(&input);
+        assert!(matches!(cfg.statements(BlockId(0))[0].line(), BodyLine::Goto(goto) if goto.label() == "LBB0_2"));
+        assert_eq!(cfg.len(), 3);
+        assert_eq!(cfg.block_of("LBB0_2"), Some(BlockId(2)));
+        assert_eq!(successors(&cfg, 0), [2, 1]);
+        assert_eq!(cfg.block(BlockId(2)).terminator(), Terminator::Return);
+        assert_eq!(cfg.statements(BlockId(2)).len(), 2);
+    }
+
+    #[test]
+    fn unknown_targets() {
+        // A branch to a register, or to a label outside the body.
+        let input = body("@%p1 bra %r1;
+$L1:
+bra missing;
+$L2:
+ret;
+");
+        let cfg = cfg(&input);
+        assert_eq!(successors(&cfg, 0), [1, 2]);
+        assert_eq!(successors(&cfg, 1), [1, 2]);
+        let kinds: Vec<_> = cfg.edges().iter().map(|edge| edge.kind).collect();
+        assert_eq!(kinds[..3], [EdgeKind::Conditional, EdgeKind::Conditional, EdgeKind::FallThrough]);
+    }
+
+    #[test]
+    fn predicated_terminators() {
+        let input = body("@%p1 ret;\n@!%p1 exit;\nbra $L1;\nmov.u32 %r1, 0;\n$L1:\n$L2:\n@%p2 bra $L2;\nadd.s32 %r1, %r1, 1;\n");
+        let cfg = cfg(&input);
+        assert_eq!(successors(&cfg, 0), [1]);
+        assert_eq!(successors(&cfg, 1), [2]);
+        // `bra $L1` jumps over the unreachable `mov`.
+        assert_eq!(successors(&cfg, 2), [4]);
+        assert_eq!(successors(&cfg, 3), [4]);
+        assert!(cfg.predecessors(BlockId(3)).is_empty());
+        // An empty block for `$L1:`, and a loop on `$L2`.
+        assert_eq!(cfg.statements(BlockId(4)).len(), 1);
+        assert_eq!(successors(&cfg, 4), [5]);
+        assert_eq!(successors(&cfg, 5), [5, 6]);
+        // The body falls off its end.
+        assert_eq!(successors(&cfg, 6), [0usize; 0]);
+        assert_eq!(cfg.block(BlockId(6)).terminator(), Terminator::FallThrough);
+    }
+
+    #[test]
+    fn empty_body() {
+        let input = body("");
+        let cfg = cfg(&input);
+        assert_eq!(cfg.len(), 1);
+        assert!(cfg.statements(cfg.entry()).is_empty());
+    }
+
+    /// The one sweep over every function of the fixtures, which checks the
+    /// shape of a few of them.
+    #[test]
+    fn fixtures() {
+        // The blocks, edges, exits, loops and register definitions of each
+        // function.
+        // `rust_begin_unwind` spins forever in all but `a`, and `run_length`
+        // nests two loops in `a`.
+        let expected = [
+            (a::_PTX, "run_length", 10, 14, 1, 2, 32),
+            (a::_PTX, "rust_begin_unwind", 1, 0, 1, 0, 5),
+            (b::_PTX, "run_length", 14, 22, 1, 1, 38),
+            (b::_PTX, "rust_begin_unwind", 1, 1, 0, 1, 0),
+            (c::_PTX, "run_length", 8, 11, 1, 1, 28),
+            (d::_PTX, "run_length", 8, 11, 1, 1, 28),
+            (kernel::_PTX, "square_kernel", 4, 4, 1, 0, 15),
+        ];
+        for (input, name, blocks, edges, exits, loops, definitions) in expected {
+            let ptx = PtxFile::try_from(input).unwrap();
+            let table = SymbolTable::new(&ptx);
+            let function = ptx.functions().iter().find(|function| function.name() == name).unwrap();
+            let cfg = Cfg::new(function.body().unwrap());
+            assert_eq!((cfg.len(), cfg.edges().len(), cfg.exits().count()), (blocks, edges, exits), "{name}");
+            assert_eq!(Loops::new(&cfg, &DominatorTree::dominators(&cfg)).len(), loops, "{name}");
+            assert_eq!(ReachingDefinitions::new(&cfg, &table).definitions().count(), definitions, "{name}");
+        }
+
+        for input in [a::_PTX, b::_PTX, c::_PTX, d::_PTX, kernel::_PTX] {
+            let ptx = PtxFile::try_from(input).unwrap();
+            let table = SymbolTable::new(&ptx);
+            for function in ptx.functions().iter().filter(|function| !function.is_declaration()) {
+                let cfg = Cfg::new(function.body().unwrap());
+                let total: usize = cfg.blocks().map(|(id, _)| cfg.statements(id).len()).sum();
+                assert_eq!(total, function.body().unwrap().count());
+                for (id, block) in cfg.blocks() {
+                    for successor in block.successors() {
+                        assert!(cfg.predecessors(*successor).contains(&id));
+                    }
+                }
+
+                let dominators = DominatorTree::dominators(&cfg);
+                for (_, current) in Loops::new(&cfg, &dominators).iter() {
+                    assert!(current.body().iter().all(|block| dominators.dominates(current.header(), *block)));
+                    assert!(current.exits().iter().all(|edge| !current.contains(edge.to)));
+                }
+                let post_dominators = DominatorTree::post_dominators(&cfg);
+                for exit in cfg.exits() {
+                    assert!(post_dominators.roots().contains(&exit));
+                    assert_ne!(cfg.block(exit).terminator(), Terminator::Branch);
+                }
+
+                let liveness = Liveness::new(&cfg, &table);
+                assert!(liveness.live_in(cfg.entry()).is_empty(), "{}", function.name());
+                let reaching = ReachingDefinitions::new(&cfg, &table);
+                for (id, definition) in reaching.definitions() {
+                    for span in reaching.uses(id) {
+                        assert_eq!(span.text(input), definition.register());
+                        assert!(reaching.reaching(*span).contains(&id));
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,191 @@
+use std::collections::HashMap;
+
+use super::{BlockId, Cfg, Liveness};
+use crate::parser::{global::declared_name, BodyLine, Statement, Type};
+
+/// A point between statements: before the statement at `index` of `block`,
+/// or at its end if `index` is the number of statements.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Point {
+    pub block: BlockId,
+    pub index: usize,
+}
+
+/// A number of registers, in 32-bit slots apart from predicates.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Pressure {
+    /// A 64-bit register takes two slots and a `.v4 .f32` register four.
+    /// 8- and 16-bit registers take a whole slot.
+    pub slots: u32,
+    pub predicates: u32,
+}
+
+/// How many registers are live at each point of a function.
+///
+/// Unlike the `.reg` declarations, which count every register ever used,
+/// this only counts those that hold a value that is read later.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RegisterPressure {
+    pressure: Vec<Vec<Pressure>>,
+    declared: Pressure,
+}
+
+/// The size of a register declared by `.reg`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Width {
+    slots: u32,
+    predicate: bool,
+}
+
+impl RegisterPressure {
+    /// Sizes the registers of `liveness` by their declarations in `cfg`.
+    pub fn new(cfg: &Cfg, liveness: &Liveness) -> Self {
+        let mut widths = HashMap::new();
+        let mut declared = Pressure::default();
+        let statements = cfg.blocks().flat_map(|(id, _)| cfg.statements(id));
+        statements.for_each(|statement| declare(statement, &mut widths, &mut declared));
+
+        let pressure = cfg
+            .blocks()
+            .map(|(id, _)| {
+                let points = 0..=cfg.statements(id).len();
+                points.map(|index| measure(liveness.live_before(id, index), &widths)).collect()
+            })
+            .collect();
+        Self { pressure, declared }
+    }
+
+    pub fn at(&self, point: Point) -> Pressure {
+        self.pressure[point.block.0][point.index]
+    }
+
+    /// The first point with the most live slots, and the pressure there.
+    pub fn peak(&self) -> (Point, Pressure) {
+        self.max_by_key(|pressure| pressure.slots)
+    }
+
+    /// The first point with the most live predicates, and how many.
+    pub fn peak_predicates(&self) -> (Point, u32) {
+        let (point, pressure) = self.max_by_key(|pressure| pressure.predicates);
+        (point, pressure.predicates)
+    }
+
+    /// What the `.reg` declarations of the function add up to.
+    pub fn declared(&self) -> Pressure {
+        self.declared
+    }
+
+    fn max_by_key(&self, key: impl Fn(&Pressure) -> u32) -> (Point, Pressure) {
+        let points = self.pressure.iter().enumerate().flat_map(|(block, points)| {
+            points.iter().enumerate().map(move |(index, pressure)| (Point { block: BlockId(block), index }, *pressure))
+        });
+        // `max_by_key` returns the last maximum.
+        points.fold((Point { block: BlockId(0), index: 0 }, Pressure::default()), |peak, current| {
+            match key(&current.1) > key(&peak.1) {
+                true => current,
+                false => peak,
+            }
+        })
+    }
+}
+
+/// Records the widths of the registers a `.reg` statement declares, like
+/// `.reg .b64 %rd<5>` or `.reg .v2 .f32 %v`.
+fn declare<'a>(statement: &Statement<'a>, widths: &mut HashMap<&'a str, Width>, declared: &mut Pressure) {
+    let register = match statement.line() {
+        BodyLine::Register(register) => register,
+        BodyLine::FunctionCall(call) => {
+            return call.statements().flatten().for_each(|statement| declare(&statement, widths, declared))
+        }
+        _ => return,
+    };
+    let (mut bits, mut lanes, mut predicate) = (32, 1, false);
+    let mut rest = register.raw_string().trim_start();
+    while let Some(directive) = rest.strip_prefix('.') {
+        let (word, tail) = directive.split_once(char::is_whitespace).unwrap_or((directive, ""));
+        match word {
+            "v2" => lanes = 2,
+            "v4" => lanes = 4,
+            "v8" => lanes = 8,
+            word => match Type::from_name(word) {
+                Some(Type::Pred) => predicate = true,
+                Some(ty) => bits = ty.bits(),
+                None => {}
+            },
+        }
+        rest = tail.trim_start();
+    }
+    let width = Width { slots: lanes * bits.div_ceil(32), predicate };
+    for declaration in rest.split(',') {
+        let (name, count) = match declared_name(declaration).split_once('<') {
+            Some((prefix, count)) => (prefix, count.trim_end_matches('>').parse().unwrap_or(0)),
+            None => (declared_name(declaration), 1),
+        };
+        widths.insert(name, width);
+        match predicate {
+            true => declared.predicates += count,
+            false => declared.slots += count * width.slots,
+        }
+    }
+}
+
+fn measure<'a>(live: impl IntoIterator<Item = &'a &'a str>, widths: &HashMap<&str, Width>) -> Pressure {
+    let mut pressure = Pressure::default();
+    for register in live {
+        let prefix = register.trim_end_matches(|c: char| c.is_ascii_digit());
+        let width = widths.get(register).or_else(|| widths.get(prefix));
+        match width {
+            Some(Width { predicate: true, .. }) => pressure.predicates += 1,
+            Some(width) => pressure.slots += width.slots,
+            None => pressure.slots += 1,
+        }
+    }
+    pressure
+}
+
+#[cfg(test)]
+mod test_pressure {
+    use crate::{
+        parser::{PtxFile, SymbolTable},
+        ptx_files::kernel,
+    };
+
+    use super::{BlockId, Cfg, Liveness, Point, Pressure, RegisterPressure};
+
+    /// `%rd1`, `%v1` and `%p2` are live across the loop in block 1.
+    const INPUT: &str = ".version 7.5\n.target sm_30\n.address_size 64\n\
+        .visible .entry k(.param .u64 n)\n{\n.reg .pred %p<3>;\n.reg .b32 %r<3>;\n.reg .b64 %rd<2>;\n\
+        .reg .v4 .f32 %v<2>;\n\
+        ld.param.u64 %rd1, [n];\nld.global.v4.f32 %v1, [%rd1];\nmov.u32 %r1, 0;\nsetp.eq.u64 %p2, %rd1, 0;\n\
+        $L1:\nadd.s32 %r1, %r1, 1;\nsetp.lt.s32 %p1, %r1, 100;\n@%p1 bra $L1;\n\
+        st.global.v4.f32 [%rd1], %v1;\n@%p2 st.global.u32 [%rd1], %r1;\nret;\n}\n";
+
+    #[test]
+    fn widths() {
+        let ptx = PtxFile::try_from(INPUT).unwrap();
+        let table = SymbolTable::new(&ptx);
+        let cfg = Cfg::new(ptx.functions()[0].body().unwrap());
+        let pressure = RegisterPressure::new(&cfg, &Liveness::new(&cfg, &table));
+        assert_eq!(pressure.declared(), Pressure { slots: 3 + 2 * 2 + 2 * 4, predicates: 3 });
+        // `%rd1` after the first load.
+        assert_eq!(pressure.at(Point { block: BlockId(0), index: 5 }), Pressure { slots: 2, predicates: 0 });
+        let (point, peak) = pressure.peak();
+        // Before `setp.eq`, with `%rd1`, `%v1` and `%r1` live.
+        assert_eq!(point, Point { block: BlockId(0), index: 7 });
+        assert_eq!(peak, Pressure { slots: 2 + 4 + 1, predicates: 0 });
+        // `%p1` and `%p2` are both live before the branch.
+        assert_eq!(pressure.peak_predicates(), (Point { block: BlockId(1), index: 3 }, 2));
+    }
+
+    #[test]
+    fn kernel() {
+        let ptx = PtxFile::try_from(kernel::_PTX).unwrap();
+        let table = SymbolTable::new(&ptx);
+        let cfg = Cfg::new(ptx.functions()[0].body().unwrap());
+        let pressure = RegisterPressure::new(&cfg, &Liveness::new(&cfg, &table));
+        let (_, peak) = pressure.peak();
+        assert!(peak.slots > 0);
+        assert!(peak.slots <= pressure.declared().slots);
+        assert!(peak.predicates <= pressure.declared().predicates);
+    }
+}
@@ -0,0 +1,194 @@
+use std::collections::{BTreeSet, HashMap};
+
+use super::{
+    liveness::{steps, Step},
+    Analysis, Cfg, Direction, Solution,
+};
+use crate::parser::{Span, Statement, SymbolTable};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct DefinitionId(usize);
+
+/// A write to a register.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Definition<'a> {
+    register: &'a str,
+    span: Span,
+    predicated: bool,
+}
+
+/// The definitions that reach each register read of a function, and the
+/// reads that each definition reaches.
+///
+/// A predicated write may not happen, so the definitions before it still
+/// reach past it. A read that no definition reaches may see an
+/// uninitialized register.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReachingDefinitions<'a> {
+    definitions: Vec<Definition<'a>>,
+    ids: HashMap<Span, DefinitionId>,
+    reaching: HashMap<Span, Vec<DefinitionId>>,
+    uses: Vec<Vec<Span>>,
+}
+
+impl<'a> ReachingDefinitions<'a> {
+    /// `table` resolves the registers of the module the function is from.
+    pub fn new(cfg: &Cfg<'a>, table: &SymbolTable<'a>) -> Self {
+        let steps = steps(cfg, table);
+        let mut definitions = Vec::new();
+        let mut ids = HashMap::new();
+        let statements = cfg.blocks().flat_map(|(id, _)| cfg.statements(id));
+        for step in statements.flat_map(|statement| &steps[&statement.span()]) {
+            for &(register, span) in &step.writes {
+                ids.insert(span, DefinitionId(definitions.len()));
+                definitions.push(Definition { register, span, predicated: step.predicated });
+            }
+        }
+
+        let analysis = Reach { steps: &steps, definitions: &definitions, ids: &ids };
+        let solution = Solution::new(cfg, &analysis);
+        let mut reaching = HashMap::new();
+        let mut uses = vec![Vec::new(); definitions.len()];
+        for (id, _) in cfg.blocks() {
+            let mut state = solution.start(id).clone();
+            for step in cfg.statements(id).iter().flat_map(|statement| &steps[&statement.span()]) {
+                for &(register, span) in &step.reads {
+                    let seen: Vec<_> = state.iter().copied().filter(|id| definitions[id.0].register == register).collect();
+                    seen.iter().for_each(|id| uses[id.0].push(span));
+                    reaching.insert(span, seen);
+                }
+                analysis.define(step, &mut state);
+            }
+        }
+        Self { definitions, ids, reaching, uses }
+    }
+
+    pub fn definitions(&self) -> impl Iterator<Item = (DefinitionId, &Definition<'a>)> {
+        self.definitions.iter().enumerate().map(|(index, definition)| (DefinitionId(index), definition))
+    }
+
+    pub fn definition(&self, id: DefinitionId) -> &Definition<'a> {
+        &self.definitions[id.0]
+    }
+
+    /// The definition made by the register operand at `span`.
+    pub fn definition_at(&self, span: Span) -> Option<DefinitionId> {
+        self.ids.get(&span).copied()
+    }
+
+    /// The definitions that reach the register read at `span`.
+    pub fn reaching(&self, span: Span) -> &[DefinitionId] {
+        self.reaching.get(&span).map_or(&[], Vec::as_slice)
+    }
+
+    /// The reads that a definition reaches, in source order.
+    pub fn uses(&self, id: DefinitionId) -> &[Span] {
+        &self.uses[id.0]
+    }
+
+    /// The definitions that reach no read.
+    pub fn unused(&self) -> impl Iterator<Item = DefinitionId> + '_ {
+        self.definitions().map(|(id, _)| id).filter(|id| self.uses[id.0].is_empty())
+    }
+}
+
+/// Reaching definitions as a forward analysis over sets of definitions.
+struct Reach<'r, 'a> {
+    steps: &'r HashMap<Span, Vec<Step<'a>>>,
+    definitions: &'r [Definition<'a>],
+    ids: &'r HashMap<Span, DefinitionId>,
+}
+
+impl Reach<'_, '_> {
+    /// Adds the definitions of a step, which replace those of the same
+    /// registers unless the step is predicated.
+    fn define(&self, step: &Step, reaching: &mut BTreeSet<DefinitionId>) {
+        for (register, span) in &step.writes {
+            if !step.predicated {
+                reaching.retain(|id| self.definitions[id.0].register != *register);
+            }
+            reaching.insert(self.ids[span]);
+        }
+    }
+}
+
+impl<'a> Analysis<'a> for Reach<'_, 'a> {
+    type Domain = BTreeSet<DefinitionId>;
+
+    const DIRECTION: Direction = Direction::Forward;
+
+    fn bottom(&self, _cfg: &Cfg<'a>) -> Self::Domain {
+        BTreeSet::new()
+    }
+
+    fn transfer(&self, statement: &Statement<'a>, reaching: &mut Self::Domain) {
+        self.steps[&statement.span()].iter().for_each(|step| self.define(step, reaching));
+    }
+}
+
+impl<'a> Definition<'a> {
+    pub fn register(&self) -> &'a str {
+        self.register
+    }
+
+    /// The written operand.
+    pub fn span(&self) -> Span {
+        self.span
+    }
+
+    pub fn is_predicated(&self) -> bool {
+        self.predicated
+    }
+}
+
+impl DefinitionId {
+    pub fn index(self) -> usize {
+        self.0
+    }
+}
+
+#[cfg(test)]
+mod test_reaching {
+    use crate::parser::{PtxFile, Span, SymbolTable};
+
+    use super::{Cfg, ReachingDefinitions};
+
+    /// `%r2` is set before a loop, in it, and by a predicated `mov` after
+    /// it. `%r3` is never read and `%r4` never written.
+    const INPUT: &str = ".version 7.5\n.target sm_30\n.address_size 64\n\
+        .visible .entry k(.param .u32 n)\n{\n.reg .pred %p<2>;\n.reg .b32 %r<5>;\n\
+        ld.param.u32 %r1, [n];\nmov.u32 %r2, 0;\nmov.u32 %r3, 1;\n\
+        $L1:\nadd.s32 %r2, %r2, %r1;\nsetp.lt.s32 %p1, %r2, 100;\n@%p1 bra $L1;\n\
+        @%p1 mov.u32 %r2, 7;\nst.global.u32 [%r4], %r2;\nret;\n}\n";
+
+    /// The span of the last `name` in the first occurrence of `context`.
+    fn at(context: &str, name: &str) -> Span {
+        let start = INPUT.find(context).unwrap() + context.rfind(name).unwrap();
+        Span::new(start, start + name.len())
+    }
+
+    #[test]
+    fn loop_and_predicated_write() {
+        let ptx = PtxFile::try_from(INPUT).unwrap();
+        let table = SymbolTable::new(&ptx);
+        let cfg = Cfg::new(ptx.functions()[0].body().unwrap());
+        let analysis = ReachingDefinitions::new(&cfg, &table);
+        assert_eq!(analysis.definitions().count(), 6);
+
+        let before = analysis.definition_at(at("mov.u32 %r2, 0", "%r2")).unwrap();
+        let in_loop = analysis.definition_at(at("add.s32 %r2", "%r2")).unwrap();
+        let predicated = analysis.definition_at(at("@%p1 mov.u32 %r2", "%r2")).unwrap();
+        assert!(analysis.definition(predicated).is_predicated());
+        assert_eq!(analysis.definition(in_loop).register(), "%r2");
+
+        assert_eq!(analysis.reaching(at("add.s32 %r2, %r2", "%r2")), [before, in_loop]);
+        // The predicated `mov` does not hide the definition in the loop.
+        assert_eq!(analysis.reaching(at("[%r4], %r2", "%r2")), [in_loop, predicated]);
+        assert!(analysis.reaching(at("[%r4]", "%r4")).is_empty());
+        assert_eq!(analysis.uses(before).len(), 1);
+        assert_eq!(analysis.uses(in_loop).len(), 3);
+
+        let unused: Vec<_> = analysis.unused().map(|id| analysis.definition(id).register()).collect();
+        assert_eq!(unused, ["%r3"]);
+    }
+}
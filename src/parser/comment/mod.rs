@@ -1,11 +1,88 @@
+use nom::{character::complete::space0, sequence::preceded};
+
 pub(crate) mod parse;
 
-#[derive(Debug, PartialEq)]
-pub(crate) enum Comment<'a> {
+/// A `// line` or `/* block */` comment, without its delimiters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum Comment<'a> {
     Line(&'a str),
     Block(&'a str),
 }
 
+/// The comments attached to a module item or statement.
+///
+/// Leading comments are those between the previous item or statement and
+/// this one. The trailing comment starts on the same line, after it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Comments<'a> {
+    leading: &'a str,
+    trailing: Option<Comment<'a>>,
+}
+
+/// Iterates over the comments in a run of whitespace and comments.
+#[derive(Debug, Clone)]
+pub struct LeadingComments<'a> {
+    rest: &'a str,
+}
+
+impl<'a> Comment<'a> {
+    /// The text between the delimiters.
+    pub fn text(&self) -> &'a str {
+        match self {
+            Comment::Line(text) => text.strip_suffix('\r').unwrap_or(text),
+            Comment::Block(text) => text,
+        }
+    }
+}
+
+impl<'a> Comments<'a> {
+    /// `leading` must consist of whitespace and comments only.
+    pub(crate) fn new(leading: &'a str, trailing: Option<Comment<'a>>) -> Self {
+        Self { leading, trailing }
+    }
+
+    pub fn leading(&self) -> LeadingComments<'a> {
+        LeadingComments { rest: self.leading }
+    }
+
+    pub fn trailing(&self) -> Option<&Comment<'a>> {
+        self.trailing.as_ref()
+    }
+
+    /// Whether an empty line separates this from the previous item or statement.
+    #[cfg(feature = "std")]
+    pub(crate) fn has_blank_line(&self) -> bool {
+        self.leading.rsplit_once('\n')
+            .is_some_and(|(before, _)| before.split('\n').skip(1).any(|line| line.trim().is_empty()))
+    }
+}
+
+impl<'a> LeadingComments<'a> {
+    /// `trivia` must consist of whitespace and comments only.
+    pub(crate) fn new(trivia: &'a str) -> Self {
+        Self { rest: trivia }
+    }
+}
+
+impl<'a> Iterator for LeadingComments<'a> {
+    type Item = Comment<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (rest, comment) = parse::parse_line_comment(self.rest.trim_start()).ok()?;
+        self.rest = rest;
+        Some(comment)
+    }
+}
+
+/// Splits off a comment that starts on the current line.
+pub(crate) fn trailing_comment(input: &str) -> (&str, Option<Comment<'_>>) {
+    match preceded(space0, parse::parse_line_comment)(input) {
+        Ok((rest, comment)) => (rest, Some(comment)),
+        Err(_) => (input, None),
+    }
+}
+
 #[cfg(test)]
 mod test_parse_line_comment {
     use crate::parser::comment::{Comment, parse::parse_line_comment};
@@ -106,3 +183,21 @@ mod test_empty_comments_and_whitespace {
         );
     }
 }
+
+#[cfg(test)]
+mod test_comments {
+    use super::{trailing_comment, Comment, Comments};
+
+    #[test]
+    fn leading() {
+        let comments = Comments::new("\n// .globl foo\r\n\n\t/* block */\n", None);
+        assert!(comments.leading().map(|comment| comment.text()).eq([" .globl foo", " block "]));
+        assert_eq!(comments.trailing(), None);
+    }
+
+    #[test]
+    fn trailing() {
+        assert_eq!(trailing_comment(" \t// %bb.1\nret;"), ("\nret;", Some(Comment::Line(" %bb.1"))));
+        assert_eq!(trailing_comment("\n// next line"), ("\n// next line", None));
+    }
+}
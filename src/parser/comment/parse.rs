@@ -1,19 +1,21 @@
 use nom::{
     branch::alt,
-    bytes::complete::{tag, take_until, take_while},
+    bytes::complete::{take_until, take_while},
     character::complete::{char, multispace1},
     multi::many1_count,
     sequence::{delimited, preceded},
-    IResult, Parser,
+    Parser,
 };
 
+use crate::parser::error::{parse::{expect, token}, Expected, PResult};
+
 use super::Comment;
 
-pub(crate) fn many1_comments_or_whitespace(input: &str) -> IResult<&str, usize> {
+pub(crate) fn many1_comments_or_whitespace(input: &str) -> PResult<'_, usize> {
     many1_count(comment_or_whitespace)(input)
 }
 
-pub(crate) fn comment_or_whitespace(input: &str) -> IResult<&str, &str> {
+pub(crate) fn comment_or_whitespace(input: &str) -> PResult<'_, &str> {
     alt((
         multispace1,
         parse_line_comment
@@ -24,7 +26,7 @@ pub(crate) fn comment_or_whitespace(input: &str) -> IResult<&str, &str> {
     ))(input)
 }
 
-pub(crate) fn parse_line_comment(input: &str) -> IResult<&str, Comment> {
+pub(crate) fn parse_line_comment(input: &str) -> PResult<'_, Comment<'_>> {
     preceded(
         char('/'),
         alt((
@@ -35,8 +37,8 @@ pub(crate) fn parse_line_comment(input: &str) -> IResult<&str, Comment> {
             .map(Comment::Line),
             delimited(
                 char('*'),
-                take_until("*/"),
-                tag("*/")
+                expect(Expected::Token("*/"), take_until("*/")),
+                token("*/")
             )
             .map(Comment::Block)
     )))(input)
@@ -0,0 +1,248 @@
+use std::collections::BTreeMap;
+
+use super::{
+    visit::{walk_function, walk_statement},
+    BodyLine, Diagnostic, Function, PtxFile, Span, Statement, Visitor,
+};
+
+/// How many unknown statements are sampled per function.
+const SAMPLES: usize = 5;
+
+/// How much of a module's function bodies parsed into typed statements.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Coverage<'a> {
+    functions: Vec<FunctionCoverage<'a>>,
+    categories: BTreeMap<Category<'a>, Counts>,
+}
+
+/// The coverage of a single function body.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FunctionCoverage<'a> {
+    name: &'a str,
+    counts: Counts,
+    samples: Vec<Span>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Counts {
+    pub fully_typed: usize,
+    pub partially_typed: usize,
+    pub unknown: usize,
+}
+
+/// How completely a statement was understood.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Typing {
+    /// Every part of the statement was recognized.
+    Full,
+    /// The statement's shape was recognized, but its opcode, a modifier,
+    /// an operand or a directive was not.
+    Partial,
+    /// The statement was kept as raw text or failed to parse.
+    Unknown,
+}
+
+/// What statements are grouped by in a [`Coverage`] report.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Category<'a> {
+    /// An opcode without its modifiers, e.g. `ld`.
+    Opcode(&'a str),
+    /// A directive without its leading `.`, e.g. `reg`.
+    Directive(&'a str),
+    Label,
+    Unknown,
+}
+
+impl<'a> Coverage<'a> {
+    pub fn new(ptx: &PtxFile<'a>) -> Self {
+        let mut coverage = Self::default();
+        Collector(&mut coverage).visit_file(ptx);
+        coverage
+    }
+
+    fn add(&mut self, category: Category<'a>, typing: Typing, span: Span) {
+        let Some(function) = self.functions.last_mut() else { return };
+        function.counts.add(typing);
+        self.categories.entry(category).or_default().add(typing);
+        if typing == Typing::Unknown && function.samples.len() < SAMPLES {
+            function.samples.push(span);
+        }
+    }
+
+    pub fn functions(&self) -> &[FunctionCoverage<'a>] {
+        &self.functions
+    }
+
+    /// Counts per opcode, directive, label and unknown statement, in sorted order.
+    pub fn categories(&self) -> impl Iterator<Item = (Category<'a>, Counts)> + '_ {
+        self.categories.iter().map(|(category, counts)| (*category, *counts))
+    }
+
+    pub fn total(&self) -> Counts {
+        self.functions.iter().fold(Counts::default(), |total, function| total + function.counts)
+    }
+}
+
+struct Collector<'r, 'a>(&'r mut Coverage<'a>);
+
+impl<'a> Visitor<'a> for Collector<'_, 'a> {
+    fn visit_function(&mut self, function: &Function<'a>) {
+        self.0.functions.push(FunctionCoverage {
+            name: function.name(),
+            counts: Counts::default(),
+            samples: Vec::new(),
+        });
+        walk_function(self, function)
+    }
+
+    fn visit_statement(&mut self, statement: &Statement<'a>) {
+        if let BodyLine::FunctionCall(_) = statement.line() {
+            return walk_statement(self, statement)
+        }
+        let (category, typing) = classify(statement.line());
+        self.0.add(category, typing, statement.span());
+    }
+
+    fn visit_diagnostic(&mut self, _diagnostic: &Diagnostic<'a>, span: Span) {
+        self.0.add(Category::Unknown, Typing::Unknown, span);
+    }
+}
+
+/// Classifies a statement other than a call sequence.
+pub fn classify<'a>(line: &BodyLine<'a>) -> (Category<'a>, Typing) {
+    match line {
+        BodyLine::Register(_) => (Category::Directive("reg"), Typing::Full),
+        BodyLine::Directive(directive) => (
+            Category::Directive(directive.name()),
+            if directive.is_known() { Typing::Full } else { Typing::Partial },
+        ),
+        BodyLine::Instruction(instruction) => (
+            Category::Opcode(instruction.opcode().name()),
+            if instruction.is_fully_typed() { Typing::Full } else { Typing::Partial },
+        ),
+        BodyLine::Operation(operation) => {
+            let opcode = operation.operation().split('.').next().unwrap_or_default();
+            (Category::Opcode(opcode), Typing::Partial)
+        }
+        BodyLine::Label(_) => (Category::Label, Typing::Full),
+        BodyLine::Goto(_) => (Category::Opcode("bra"), Typing::Full),
+        BodyLine::Return { .. } => (Category::Opcode("ret"), Typing::Full),
+        BodyLine::FunctionCall(_) => (Category::Opcode("call"), Typing::Full),
+        BodyLine::Unknown(_) => (Category::Unknown, Typing::Unknown),
+    }
+}
+
+impl<'a> FunctionCoverage<'a> {
+    pub fn name(&self) -> &'a str {
+        self.name
+    }
+
+    pub fn counts(&self) -> Counts {
+        self.counts
+    }
+
+    /// The spans of the first few unknown statements.
+    pub fn samples(&self) -> &[Span] {
+        &self.samples
+    }
+}
+
+impl Counts {
+    pub fn total(&self) -> usize {
+        self.fully_typed + self.partially_typed + self.unknown
+    }
+
+    fn add(&mut self, typing: Typing) {
+        match typing {
+            Typing::Full => self.fully_typed += 1,
+            Typing::Partial => self.partially_typed += 1,
+            Typing::Unknown => self.unknown += 1,
+        }
+    }
+}
+
+impl core::ops::Add for Counts {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self {
+            fully_typed: self.fully_typed + other.fully_typed,
+            partially_typed: self.partially_typed + other.partially_typed,
+            unknown: self.unknown + other.unknown,
+        }
+    }
+}
+
+impl core::fmt::Display for Category<'_> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Category::Opcode(opcode) => f.write_str(opcode),
+            Category::Directive(directive) => write!(f, ".{directive}"),
+            Category::Label => f.write_str("<label>"),
+            Category::Unknown => f.write_str("<unknown>"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_coverage {
+    use crate::parser::{PtxFile, Span};
+
+    use super::{Category, Counts, Coverage};
+
+    #[test]
+    fn counts_statements() {
+        let input = ".version 7.5
+.target sm_30
+.address_size 64
+
+.func foo
+{
+	.reg .b32 %r<3>;
+	.frob 1;
+	add.bogus.u32 %r1, %r2, 1;
+	frobnicate.u32 %r1;
+	mov.u32 %r1, %r2;
+	{ // callseq 0, 0
+	.param .b64 param0;
+	call.uni
+	bar,
+	(
+	param0
+	);
+	} // callseq 0
+	#$!;
+$L__BB0_1:
+	ret;
+}
+";
+        let (ptx, _) = PtxFile::parse_with_diagnostics(input).unwrap();
+        let coverage = Coverage::new(&ptx);
+        let [foo] = coverage.functions() else { panic!() };
+        assert_eq!(foo.name(), "foo");
+        assert_eq!(foo.counts(), Counts { fully_typed: 6, partially_typed: 3, unknown: 1 });
+        let unknown = input.find("#$!;").unwrap();
+        assert_eq!(foo.samples(), [Span::new(unknown, unknown + 4)]);
+        assert_eq!(coverage.total(), foo.counts());
+
+        let categories: Vec<_> = coverage.categories()
+            .map(|(category, counts)| (category.to_string(), counts.total()))
+            .collect();
+        assert_eq!(categories, [
+            ("add".to_string(), 1),
+            ("call".to_string(), 1),
+            ("frobnicate".to_string(), 1),
+            ("mov".to_string(), 1),
+            ("ret".to_string(), 1),
+            (".frob".to_string(), 1),
+            (".param".to_string(), 1),
+            (".reg".to_string(), 1),
+            ("<label>".to_string(), 1),
+            ("<unknown>".to_string(), 1),
+        ]);
+        assert_eq!(
+            coverage.categories().find(|(category, _)| *category == Category::Opcode("add")).unwrap().1,
+            Counts { fully_typed: 0, partially_typed: 1, unknown: 0 },
+        );
+    }
+}
@@ -0,0 +1,253 @@
+use core::fmt;
+
+use super::{
+    function::Function, global::Global, preamble::Preamble, Diagnostic, ParseError,
+    ParseOptions, PtxFile, Span, Statement,
+};
+
+pub(crate) mod parse;
+
+/// A lossless syntax tree of a module.
+///
+/// Every byte of the source, including whitespace and comments, belongs to
+/// exactly one token, so writing out the tokens in order reproduces the
+/// source. The typed AST parsed from the same source is kept alongside and
+/// can be looked up from the nodes.
+#[derive(Debug)]
+pub struct SyntaxTree<'a> {
+    source: &'a str,
+    root: SyntaxNode<'a>,
+    ptx: PtxFile<'a>,
+    statements: Vec<Statement<'a>>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SyntaxNode<'a> {
+    kind: NodeKind,
+    span: Span,
+    children: Vec<SyntaxElement<'a>>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SyntaxElement<'a> {
+    Node(SyntaxNode<'a>),
+    Token(SyntaxToken<'a>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SyntaxToken<'a> {
+    kind: TokenKind,
+    span: Span,
+    text: &'a str,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum NodeKind {
+    Module,
+    Preamble,
+    Version,
+    Target,
+    AddressSize,
+    Global,
+    Function,
+    Signature,
+    Body,
+    Statement,
+    /// Source skipped while recovering from a parse error.
+    Error,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TokenKind {
+    Whitespace,
+    LineComment,
+    BlockComment,
+    String,
+    /// A name starting with `.`, such as a directive or modifier.
+    Directive,
+    /// A name starting with `%`, including dotted special registers like `%tid.x`.
+    Register,
+    Identifier,
+    Number,
+    Punctuation,
+    Unknown,
+}
+
+impl<'a> SyntaxTree<'a> {
+    /// Parses `source` into a syntax tree, recovering from errors like
+    /// [`PtxFile::parse_with_options`]. Skipped source is kept in
+    /// [`NodeKind::Error`] nodes.
+    pub fn parse(
+        source: &'a str,
+        options: ParseOptions,
+    ) -> Result<(Self, Vec<Diagnostic<'a>>), ParseError<'a>> {
+        parse::parse_syntax_tree(source, options)
+    }
+
+    pub fn source(&self) -> &'a str {
+        self.source
+    }
+
+    pub fn root(&self) -> &SyntaxNode<'a> {
+        &self.root
+    }
+
+    /// The typed AST of the module.
+    pub fn ast(&self) -> &PtxFile<'a> {
+        &self.ptx
+    }
+
+    pub fn preamble(&self, node: &SyntaxNode<'a>) -> Option<&Preamble<'a>> {
+        (node.kind == NodeKind::Preamble).then_some(&self.ptx.preamble)
+    }
+
+    pub fn function(&self, node: &SyntaxNode<'a>) -> Option<&Function<'a>> {
+        (node.kind == NodeKind::Function)
+            .then(|| self.ptx.functions.iter().find(|function| function.span() == node.span))?
+    }
+
+    pub fn global(&self, node: &SyntaxNode<'a>) -> Option<&Global<'a>> {
+        (node.kind == NodeKind::Global)
+            .then(|| self.ptx.globals.iter().find(|global| global.span() == node.span))?
+    }
+
+    /// The statement of a [`NodeKind::Statement`] node, including statements
+    /// nested in call sequences.
+    pub fn statement(&self, node: &SyntaxNode<'a>) -> Option<&Statement<'a>> {
+        if node.kind != NodeKind::Statement {
+            return None
+        }
+        let index = self.statements
+            .binary_search_by_key(&node.span.start, |statement| statement.span().start)
+            .ok()?;
+        Some(&self.statements[index])
+    }
+}
+
+impl<'a> SyntaxNode<'a> {
+    pub fn kind(&self) -> NodeKind {
+        self.kind
+    }
+
+    pub fn span(&self) -> Span {
+        self.span
+    }
+
+    pub fn children(&self) -> &[SyntaxElement<'a>] {
+        &self.children
+    }
+
+    pub fn child_nodes(&self) -> impl Iterator<Item = &SyntaxNode<'a>> {
+        self.children.iter().filter_map(|child| match child {
+            SyntaxElement::Node(node) => Some(node),
+            SyntaxElement::Token(_) => None,
+        })
+    }
+
+    /// All tokens under this node, in source order.
+    pub fn tokens(&self) -> impl Iterator<Item = &SyntaxToken<'a>> {
+        let mut stack = vec![self.children.iter()];
+        core::iter::from_fn(move || loop {
+            match stack.last_mut()?.next() {
+                Some(SyntaxElement::Token(token)) => return Some(token),
+                Some(SyntaxElement::Node(node)) => stack.push(node.children.iter()),
+                None => {
+                    stack.pop();
+                }
+            }
+        })
+    }
+
+    /// The innermost node whose span contains `offset`.
+    pub fn covering(&self, offset: usize) -> Option<&SyntaxNode<'a>> {
+        if !self.span.contains(offset) {
+            return None
+        }
+        Some(self.child_nodes().find_map(|node| node.covering(offset)).unwrap_or(self))
+    }
+}
+
+impl<'a> SyntaxToken<'a> {
+    pub fn kind(&self) -> TokenKind {
+        self.kind
+    }
+
+    pub fn span(&self) -> Span {
+        self.span
+    }
+
+    pub fn text(&self) -> &'a str {
+        self.text
+    }
+
+    /// Whether the token is whitespace or a comment.
+    pub fn is_trivia(&self) -> bool {
+        matches!(self.kind, TokenKind::Whitespace | TokenKind::LineComment | TokenKind::BlockComment)
+    }
+}
+
+impl fmt::Display for SyntaxNode<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.tokens().try_for_each(|token| f.write_str(token.text))
+    }
+}
+
+impl fmt::Display for SyntaxTree<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.root.fmt(f)
+    }
+}
+
+#[cfg(test)]
+mod test_syntax_tree {
+    use crate::parser::{BodyLine, ParseOptions};
+
+    use super::{NodeKind, SyntaxNode, SyntaxTree, TokenKind};
+
+    #[test]
+    fn errors_round_trip() {
+        let input = "// header\r\n.version 7.5\n.target sm_30\n.address_size 64\n\n\
+            .bogus;\n.func foo /* inline */ (\n\t.param .b64 x\n)\n{\n\tunterminated\n}\n\
+            .global \"/string ; }\" /* unterminated";
+        let (tree, diagnostics) = SyntaxTree::parse(input, ParseOptions::default()).unwrap();
+        assert!(!diagnostics.is_empty());
+        assert_eq!(tree.to_string(), input);
+        fn errors(node: &SyntaxNode) -> usize {
+            usize::from(node.kind() == NodeKind::Error) + node.child_nodes().map(errors).sum::<usize>()
+        }
+        assert_eq!(errors(tree.root()), diagnostics.len());
+    }
+
+    #[test]
+    fn typed_view() {
+        let input = ".version 7.5\n.target sm_30\n.address_size 64\n\n\
+            // .globl foo\n.visible .entry foo()\n{\n\tmov.u32 %r1, %tid.x; // thread\n\tret;\n}\n";
+        let (tree, _) = SyntaxTree::parse(input, ParseOptions::default()).unwrap();
+        let root = tree.root();
+        let kinds: Vec<_> = root.child_nodes().map(|node| node.kind()).collect();
+        assert_eq!(kinds, [NodeKind::Preamble, NodeKind::Function]);
+
+        let function = root.child_nodes().nth(1).unwrap();
+        assert_eq!(tree.function(function).unwrap().name(), "foo");
+        let body = function.child_nodes().find(|node| node.kind() == NodeKind::Body).unwrap();
+        let statement = body.child_nodes().next().unwrap();
+        assert!(matches!(tree.statement(statement).unwrap().line(), BodyLine::Instruction(_)));
+        assert_eq!(statement.to_string(), "mov.u32 %r1, %tid.x;");
+
+        let tokens: Vec<_> = statement.tokens()
+            .filter(|token| !token.is_trivia())
+            .map(|token| (token.kind(), token.text()))
+            .collect();
+        assert_eq!(tokens, [
+            (TokenKind::Identifier, "mov"),
+            (TokenKind::Directive, ".u32"),
+            (TokenKind::Register, "%r1"),
+            (TokenKind::Punctuation, ","),
+            (TokenKind::Register, "%tid.x"),
+            (TokenKind::Punctuation, ";"),
+        ]);
+        let comment = input.find("// thread").unwrap();
+        assert_eq!(root.covering(comment).unwrap().kind(), NodeKind::Body);
+        assert_eq!(root.covering(input.find("%tid").unwrap()), Some(statement));
+    }
+}
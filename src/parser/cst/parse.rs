@@ -0,0 +1,160 @@
+use crate::parser::{
+    function::body::FunctionBody, skip_comment_or_string, BodyLine, Diagnostic, FunctionOrGlobal, ItemKind,
+    ParseError, ParseOptions, PtxFile, PtxParser, Span, Statement,
+};
+
+use super::{NodeKind, SyntaxElement, SyntaxNode, SyntaxToken, SyntaxTree, TokenKind};
+
+pub(crate) fn parse_syntax_tree(
+    source: &str,
+    options: ParseOptions,
+) -> Result<(SyntaxTree<'_>, Vec<Diagnostic<'_>>), ParseError<'_>> {
+    let mut ptx = PtxParser::with_options(source, options)?;
+    let preamble = ptx.preamble();
+    let mut items = vec![node(source, NodeKind::Preamble, preamble.span(), vec![
+        leaf(source, NodeKind::Version, preamble.version().span()),
+        leaf(source, NodeKind::Target, preamble.target().span()),
+        leaf(source, NodeKind::AddressSize, preamble.address_size().span()),
+    ])];
+    let mut functions = Vec::new();
+    let mut globals = Vec::new();
+    let mut statements = Vec::new();
+    let mut order = Vec::new();
+    let mut diagnostics = Vec::new();
+    while let Some(item) = ptx.next_recovering() {
+        match item {
+            Ok(FunctionOrGlobal::Function(function)) => {
+                let mut children = vec![leaf(source, NodeKind::Signature, function.signature().span())];
+                if let Some(body) = function.body() {
+                    children.push(body_node(source, body, &mut statements, &mut diagnostics));
+                }
+                items.push(node(source, NodeKind::Function, function.span(), children));
+                functions.push(function);
+                order.push(ItemKind::Function);
+            }
+            Ok(FunctionOrGlobal::Global(global)) => {
+                items.push(leaf(source, NodeKind::Global, global.span()));
+                globals.push(global);
+                order.push(ItemKind::Global);
+            }
+            Err(diagnostic) => {
+                items.push(leaf(source, NodeKind::Error, Span::of(source, diagnostic.skipped())));
+                diagnostics.push(diagnostic);
+            }
+        }
+    }
+    let root = node(source, NodeKind::Module, Span::new(0, source.len()), items);
+    let ptx = PtxFile { source, preamble: ptx.preamble, functions, globals, order };
+    Ok((SyntaxTree { source, root, ptx, statements }, diagnostics))
+}
+
+/// Builds the node of a function body or call sequence, collecting its
+/// statements in source order.
+fn body_node<'a>(
+    source: &'a str,
+    mut body: FunctionBody<'a>,
+    statements: &mut Vec<Statement<'a>>,
+    diagnostics: &mut Vec<Diagnostic<'a>>,
+) -> SyntaxNode<'a> {
+    let span = body.span();
+    let mut children = Vec::new();
+    while let Some(statement) = body.next_recovering() {
+        match statement {
+            Ok(statement) => {
+                let index = statements.len();
+                let statement_span = statement.span();
+                statements.push(statement);
+                let nested = match statements[index].line() {
+                    BodyLine::FunctionCall(call) => {
+                        vec![body_node(source, call.statements(), statements, diagnostics)]
+                    }
+                    _ => Vec::new(),
+                };
+                children.push(node(source, NodeKind::Statement, statement_span, nested));
+            }
+            Err(diagnostic) => {
+                children.push(leaf(source, NodeKind::Error, Span::of(source, diagnostic.skipped())));
+                diagnostics.push(diagnostic);
+            }
+        }
+    }
+    node(source, NodeKind::Body, span, children)
+}
+
+fn leaf(source: &str, kind: NodeKind, span: Span) -> SyntaxNode<'_> {
+    node(source, kind, span, Vec::new())
+}
+
+/// Builds a node from its child nodes, which must be in source order and
+/// within `span`. The source between them becomes tokens of this node.
+fn node<'a>(source: &'a str, kind: NodeKind, span: Span, nodes: Vec<SyntaxNode<'a>>) -> SyntaxNode<'a> {
+    let mut children = Vec::new();
+    let mut offset = span.start;
+    for node in nodes {
+        children.extend(tokens(source, Span::new(offset, node.span.start)).map(SyntaxElement::Token));
+        offset = node.span.end;
+        children.push(SyntaxElement::Node(node));
+    }
+    children.extend(tokens(source, Span::new(offset, span.end)).map(SyntaxElement::Token));
+    SyntaxNode { kind, span, children }
+}
+
+/// Splits `span` of `source` into tokens that cover it exactly.
+fn tokens(source: &str, span: Span) -> impl Iterator<Item = SyntaxToken<'_>> {
+    let mut offset = span.start;
+    core::iter::from_fn(move || {
+        if offset >= span.end {
+            return None
+        }
+        let (kind, len) = lex(&source[offset..span.end]);
+        let token = SyntaxToken {
+            kind,
+            span: Span::new(offset, offset + len),
+            text: &source[offset..offset + len],
+        };
+        offset += len;
+        Some(token)
+    })
+}
+
+fn is_name(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_' || c == '$'
+}
+
+/// The kind and byte length of the token at the start of `input`.
+fn lex(input: &str) -> (TokenKind, usize) {
+    let bytes = input.as_bytes();
+    let name_len = |start: usize| input[start..].find(|c: char| !is_name(c)).map_or(input.len(), |len| start + len);
+    if matches!(bytes, [b'/', b'/' | b'*', ..] | [b'"', ..]) {
+        let kind = match bytes {
+            [b'/', b'/', ..] => TokenKind::LineComment,
+            [b'/', ..] => TokenKind::BlockComment,
+            _ => TokenKind::String,
+        };
+        // Unterminated comments and strings run to the end of the input.
+        let end = skip_comment_or_string(bytes, 0).flatten().unwrap_or(input.len());
+        return (kind, end)
+    }
+    let c = input.chars().next().unwrap_or_default();
+    match c {
+        _ if c.is_whitespace() => (
+            TokenKind::Whitespace,
+            input.find(|c: char| !c.is_whitespace()).unwrap_or(input.len()),
+        ),
+        '.' if input[1..].starts_with(is_name) => (TokenKind::Directive, name_len(1)),
+        '%' if input[1..].starts_with(is_name) => {
+            let mut end = name_len(1);
+            while input[end..].starts_with('.') && input[end + 1..].starts_with(is_name) {
+                end = name_len(end + 1);
+            }
+            (TokenKind::Register, end)
+        }
+        _ if c.is_ascii_digit() => (
+            TokenKind::Number,
+            input.find(|c: char| !is_name(c) && c != '.').unwrap_or(input.len()),
+        ),
+        _ if is_name(c) => (TokenKind::Identifier, name_len(0)),
+        _ if c.is_ascii_punctuation() => (TokenKind::Punctuation, 1),
+        _ => (TokenKind::Unknown, c.len_utf8()),
+    }
+}
@@ -0,0 +1,139 @@
+//! Graphviz DOT output of control flow and call graphs.
+
+use super::{CallGraph, CallKind, Cfg, EdgeKind, PtxFile};
+
+/// A function's control flow graph, with a node per basic block labeled
+/// by its statements.
+///
+/// Conditional edges are labeled `taken`, jump table edges are dashed and
+/// the entry block is drawn with a double border.
+pub fn cfg_to_dot(name: &str, cfg: &Cfg) -> String {
+    let mut out = format!("digraph \"{}\" {{\n", escape(name));
+    out.push_str("    node [shape=box, fontname=monospace];\n");
+    for (id, _) in cfg.blocks() {
+        let mut label = format!("B{}\\l", id.index());
+        for statement in cfg.statements(id) {
+            label.push_str(&escape(&statement.to_string()));
+            label.push_str("\\l");
+        }
+        let entry = if id == cfg.entry() { ", peripheries=2" } else { "" };
+        out.push_str(&format!("    b{} [label=\"{label}\"{entry}];\n", id.index()));
+    }
+    for edge in cfg.edges() {
+        let attributes = match edge.kind {
+            EdgeKind::FallThrough | EdgeKind::Jump => "",
+            EdgeKind::Conditional => " [label=\"taken\"]",
+            EdgeKind::JumpTable => " [style=dashed]",
+        };
+        out.push_str(&format!("    b{} -> b{}{attributes};\n", edge.from.index(), edge.to.index()));
+    }
+    out.push_str("}\n");
+    out
+}
+
+/// The module's call graph, with an edge from each function to each
+/// function it may call.
+///
+/// Entries are drawn with a double border, functions that are only
+/// declared are dashed and so are the edges of indirect calls.
+pub fn call_graph_to_dot(ptx: &PtxFile) -> String {
+    let graph = CallGraph::new(ptx);
+    let mut out = String::from("digraph calls {\n");
+    out.push_str("    node [shape=box, fontname=monospace];\n");
+    for name in graph.functions() {
+        let defined = ptx.functions().iter().any(|function| function.name() == *name && !function.is_declaration());
+        let style = match (graph.entries().contains(name), defined) {
+            (true, _) => " [peripheries=2]",
+            (false, true) => "",
+            (false, false) => " [style=dashed]",
+        };
+        out.push_str(&format!("    \"{}\"{style};\n", escape(name)));
+    }
+
+    let mut edges = Vec::new();
+    for call in graph.calls() {
+        if !edges.contains(&(call.caller, call.callee, call.kind)) {
+            edges.push((call.caller, call.callee, call.kind));
+        }
+    }
+    for (caller, callee, kind) in edges {
+        let style = match kind {
+            CallKind::Direct => "",
+            CallKind::Indirect => " [style=dashed]",
+        };
+        out.push_str(&format!("    \"{}\" -> \"{}\"{style};\n", escape(caller), escape(callee)));
+    }
+    out.push_str("}\n");
+    out
+}
+
+/// Escapes a DOT string and ends each line with `\l`, which left-justifies it.
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '"' | '\\' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            '\n' => escaped.push_str("\\l"),
+            '\t' => escaped.push_str("    "),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod test_dot {
+    use crate::{
+        parser::{Cfg, PtxFile},
+        ptx_files::a,
+    };
+
+    use super::{call_graph_to_dot, cfg_to_dot};
+
+    fn function<'a>(ptx: &PtxFile<'a>, name: &str) -> Cfg<'a> {
+        let function = ptx.functions().iter().find(|function| function.name() == name).unwrap();
+        Cfg::new(function.body().unwrap())
+    }
+
+    #[test]
+    fn control_flow() {
+        let ptx = PtxFile::try_from(a::_PTX).unwrap();
+        for name in ["run_length", "thread_id"] {
+            let cfg = function(&ptx, name);
+            let dot = cfg_to_dot(name, &cfg);
+            assert!(dot.starts_with(&format!("digraph \"{name}\" {{\n")));
+            assert!(dot.ends_with("}\n"));
+            assert_eq!(dot.matches(" [label=\"B").count(), cfg.len());
+            assert_eq!(dot.matches(" -> ").count(), cfg.edges().len());
+            assert!(dot.contains("b0 [label=\"B0\\l"));
+        }
+        let dot = cfg_to_dot("run_length", &function(&ptx, "run_length"));
+        assert!(dot.contains("ld.param.u32"));
+        assert!(dot.contains("[label=\"taken\"]"));
+    }
+
+    #[test]
+    fn escapes() {
+        let input = ".version 7.5\n.target sm_30\n.address_size 64\n.visible .entry k()\n{\n\
+            .pragma \"nounroll\";\nret;\n}\n";
+        let ptx = PtxFile::try_from(input).unwrap();
+        let dot = cfg_to_dot("k", &function(&ptx, "k"));
+        assert!(dot.contains(".pragma \\\"nounroll\\\";\\l"));
+    }
+
+    #[test]
+    fn calls() {
+        let ptx = PtxFile::try_from(a::_PTX).unwrap();
+        let dot = call_graph_to_dot(&ptx);
+        assert!(dot.contains("    \"run_length\" [peripheries=2];\n"));
+        assert!(dot.contains("    \"rust_begin_unwind\";\n"));
+        assert!(dot.contains("    \"rust_begin_unwind\" -> \"_ZN4core9panicking5panic17h691abfa2aca02139E\";\n"));
+        // `run_length` calls `next_capped_u64` twice but gets one edge for it.
+        let edges: Vec<_> = dot.lines().filter(|line| line.starts_with("    \"run_length\" -> ")).collect();
+        assert_eq!(edges.len(), 2);
+        assert!(!dot.contains("\"thread_id\" -> "));
+    }
+}
@@ -0,0 +1,287 @@
+use core::fmt;
+
+use nom::{
+    error::{ContextError, ErrorKind},
+    Offset,
+};
+
+pub(crate) mod parse;
+
+/// An error produced while parsing PTX source.
+///
+/// Positions are given both as a byte offset into the source and as a
+/// 1-based line and column, where the column counts `char`s.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError<'a> {
+    offset: usize,
+    line: usize,
+    column: usize,
+    line_text: &'a str,
+    context: Option<Context<'a>>,
+    expected: Option<Expected>,
+}
+
+/// A parse error that the parser recovered from by skipping input.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic<'a> {
+    error: ParseError<'a>,
+    skipped: &'a str,
+}
+
+/// The construct that was being parsed when an error occurred,
+/// e.g. "parameter list of function `foo`".
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Context<'a> {
+    construct: &'static str,
+    owner: Option<(&'static str, &'a str)>,
+}
+
+/// What the parser expected to find at the error position.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Expected {
+    Char(char),
+    Token(&'static str),
+    Closing(char),
+    Description(&'static str),
+}
+
+/// The error type threaded through the internal `nom` parsers.
+///
+/// It is converted into a [`ParseError`] once the full source is known.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Error<'a> {
+    pub(crate) input: &'a str,
+    pub(crate) kind: ErrorKind,
+    pub(crate) expected: Option<Expected>,
+    pub(crate) context: Option<Context<'a>>,
+}
+
+pub(crate) type PResult<'a, O> = nom::IResult<&'a str, O, Error<'a>>;
+
+impl<'a> ParseError<'a> {
+    pub(crate) fn new(source: &'a str, error: Error<'a>) -> Self {
+        let offset = source.offset(error.input).min(source.len());
+        let line_start = source[..offset].rfind('\n').map_or(0, |i| i + 1);
+        let line_end = source[offset..].find('\n').map_or(source.len(), |i| offset + i);
+        Self {
+            offset,
+            line: source[..offset].matches('\n').count() + 1,
+            column: source[line_start..offset].chars().count() + 1,
+            line_text: source[line_start..line_end].trim_end_matches('\r'),
+            context: error.context,
+            expected: error.expected,
+        }
+    }
+
+    pub(crate) fn from_nom(source: &'a str, error: nom::Err<Error<'a>>) -> Self {
+        match error {
+            nom::Err::Error(error) | nom::Err::Failure(error) => Self::new(source, error),
+            nom::Err::Incomplete(_) => Self::new(source, Error::new(&source[source.len()..], ErrorKind::Eof)),
+        }
+    }
+
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    pub fn column(&self) -> usize {
+        self.column
+    }
+
+    pub fn context(&self) -> Option<&Context<'a>> {
+        self.context.as_ref()
+    }
+
+    pub fn expected(&self) -> Option<&Expected> {
+        self.expected.as_ref()
+    }
+}
+
+impl<'a> Diagnostic<'a> {
+    pub(crate) fn new(error: ParseError<'a>, skipped: &'a str) -> Self {
+        Self { error, skipped }
+    }
+
+    pub fn error(&self) -> &ParseError<'a> {
+        &self.error
+    }
+
+    /// The source text that was dropped to get past the error.
+    pub fn skipped(&self) -> &'a str {
+        self.skipped
+    }
+
+    pub fn into_error(self) -> ParseError<'a> {
+        self.error
+    }
+}
+
+impl<'a> Context<'a> {
+    pub(crate) fn new(construct: &'static str) -> Self {
+        Self { construct, owner: None }
+    }
+
+    pub fn construct(&self) -> &'static str {
+        self.construct
+    }
+
+    /// The kind and name of the item the construct belongs to,
+    /// e.g. `("function", "foo")`.
+    pub fn owner(&self) -> Option<(&'static str, &'a str)> {
+        self.owner
+    }
+}
+
+impl<'a> Error<'a> {
+    pub(crate) fn new(input: &'a str, kind: ErrorKind) -> Self {
+        Self { input, kind, expected: None, context: None }
+    }
+
+    pub(crate) fn expected(input: &'a str, expected: Expected) -> Self {
+        Self { expected: Some(expected), ..Self::new(input, ErrorKind::Verify) }
+    }
+
+    /// Records the item that owns the construct being parsed,
+    /// unless an inner parser already did.
+    pub(crate) fn of(mut self, kind: &'static str, name: &'a str) -> Self {
+        if let Some(context) = self.context.as_mut() {
+            context.owner.get_or_insert((kind, name));
+        }
+        self
+    }
+}
+
+impl<'a> nom::error::ParseError<&'a str> for Error<'a> {
+    fn from_error_kind(input: &'a str, kind: ErrorKind) -> Self {
+        Self::new(input, kind)
+    }
+
+    fn append(_: &'a str, _: ErrorKind, other: Self) -> Self {
+        other
+    }
+
+    fn from_char(input: &'a str, c: char) -> Self {
+        Self { expected: Some(Expected::Char(c)), ..Self::new(input, ErrorKind::Char) }
+    }
+
+    /// Keeps whichever alternative got further into the input.
+    fn or(self, other: Self) -> Self {
+        if self.input.len() < other.input.len() {
+            self
+        } else {
+            other
+        }
+    }
+}
+
+impl<'a> ContextError<&'a str> for Error<'a> {
+    /// Keeps the innermost context, which names the most specific construct.
+    fn add_context(_: &'a str, construct: &'static str, mut other: Self) -> Self {
+        other.context.get_or_insert(Context::new(construct));
+        other
+    }
+}
+
+impl fmt::Display for Expected {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Expected::Char(c) => write!(f, "`{c}`"),
+            Expected::Token(token) => write!(f, "`{token}`"),
+            Expected::Closing(c) => write!(f, "matching `{c}`"),
+            Expected::Description(description) => f.write_str(description),
+        }
+    }
+}
+
+impl fmt::Display for Context<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.construct)?;
+        if let Some((kind, name)) = self.owner {
+            write!(f, " of {kind} `{name}`")?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for ParseError<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.expected {
+            Some(expected) => write!(f, "expected {expected}")?,
+            None => f.write_str("unexpected input")?,
+        }
+        if let Some(context) = self.context {
+            write!(f, " while parsing {context}")?;
+        }
+        writeln!(f, " at {}:{}", self.line, self.column)?;
+
+        let mut width = 1;
+        let mut line = self.line;
+        while line >= 10 {
+            width += 1;
+            line /= 10;
+        }
+        writeln!(f, "{:width$} |", "")?;
+        writeln!(f, "{:width$} | {}", self.line, self.line_text)?;
+        write!(f, "{:width$} | ", "")?;
+        for c in self.line_text.chars().take(self.column - 1) {
+            f.write_str(if c == '\t' { "\t" } else { " " })?;
+        }
+        f.write_str("^")
+    }
+}
+
+impl fmt::Display for Diagnostic<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.error.fmt(f)
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ParseError<'_> {}
+
+#[cfg(test)]
+mod test_parse_error {
+    use super::{Error, Expected, ParseError};
+
+    #[test]
+    fn line_and_column() {
+        let source = ".version 7.5\n\t.target sm_30\n.address_size 64";
+        let error = ParseError::new(source, Error::expected(&source[22..], Expected::Token("sm_30")));
+        assert_eq!(error.offset(), 22);
+        assert_eq!(error.line(), 2);
+        assert_eq!(error.column(), 10);
+    }
+
+    #[test]
+    fn non_ascii_column() {
+        let source = "// héllo\n// wörld ;";
+        let offset = source.find(';').unwrap();
+        let error = ParseError::new(source, Error::expected(&source[offset..], Expected::Char(';')));
+        assert_eq!(error.line(), 2);
+        assert_eq!(error.column(), 10);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn display_snippet() {
+        let source = ".func foo(\n\t.param .b64 x\n";
+        let offset = source.find('x').unwrap() + 1;
+        let error = Error::expected(&source[offset..], Expected::Closing(')'));
+        let error = Error {
+            context: Some(super::Context::new("parameter list")),
+            ..error
+        }
+        .of("function", "foo");
+        assert_eq!(
+            ParseError::new(source, error).to_string(),
+            "expected matching `)` while parsing parameter list of function `foo` at 2:15
+  |
+2 | \t.param .b64 x
+  | \t             ^"
+        );
+    }
+}
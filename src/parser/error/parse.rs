@@ -0,0 +1,27 @@
+use nom::{bytes::complete::tag, Parser};
+
+use super::{Error, Expected, PResult};
+
+/// Like `tag`, but reports the missing token on failure.
+pub(crate) fn token<'a>(token: &'static str) -> impl FnMut(&'a str) -> PResult<'a, &'a str> {
+    move |input: &'a str| {
+        tag(token)(input)
+        .map_err(|_: nom::Err<Error>| nom::Err::Error(Error::expected(input, Expected::Token(token))))
+    }
+}
+
+/// Reports `expected` if `parser` fails without consuming any input
+/// or without saying what it expected.
+pub(crate) fn expect<'a, O>(
+    expected: Expected,
+    mut parser: impl Parser<&'a str, O, Error<'a>>,
+) -> impl FnMut(&'a str) -> PResult<'a, O> {
+    move |input: &'a str| {
+        parser.parse(input).map_err(|error| error.map(|mut error| {
+            if error.expected.is_none() || error.input.len() == input.len() {
+                error.expected = Some(expected);
+            }
+            error
+        }))
+    }
+}
@@ -0,0 +1,349 @@
+//! Canonical formatting of PTX source.
+//!
+//! Unlike the `Display` impls, which print the AST as-is, the formatter
+//! lays out a module for reading: opcodes and operands are aligned into
+//! columns per body, parameter lists get one parameter per line, and runs
+//! of whitespace inside declarations are collapsed. Only whitespace and,
+//! optionally, comments change.
+
+use super::{
+    function::body::FunctionBody, split_top_level, BodyLine, Comments, Function, FunctionCall, Global,
+    Item, ParseError, ParseOptions, PtxFile,
+};
+
+/// Opcode columns wider than this are not padded to, so that one long
+/// mnemonic does not push the operands of a whole body to the right.
+const MAX_MNEMONIC_WIDTH: usize = 24;
+
+/// Configures [`format`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FormatOptions {
+    /// Drops comments, including those inside a declaration or statement,
+    /// instead of keeping them with their item or statement.
+    pub strip_comments: bool,
+}
+
+/// Formats a module.
+///
+/// Fails on the first parse error rather than formatting around it, since
+/// source the parser skipped could not be laid out.
+pub fn format(source: &str, options: FormatOptions) -> Result<String, ParseError<'_>> {
+    let (ptx, diagnostics) = PtxFile::parse_with_options(source, ParseOptions::default())?;
+    if let Some(diagnostic) = diagnostics.into_iter().next() {
+        return Err(diagnostic.into_error())
+    }
+    let mut formatter = Formatter { out: String::new(), options };
+    formatter.module(&ptx)?;
+    Ok(formatter.out)
+}
+
+struct Formatter {
+    out: String,
+    options: FormatOptions,
+}
+
+/// A statement split into its columns.
+enum Layout<'l, 'a> {
+    /// An opcode with its modifiers and operands, like `ld.param.u64` and `%rd1, [x]`.
+    Aligned(String, String),
+    Plain(String),
+    Label(String),
+    Block(&'l FunctionCall<'a>),
+}
+
+impl Formatter {
+    fn module<'a>(&mut self, ptx: &PtxFile<'a>) -> Result<(), ParseError<'a>> {
+        if !self.options.strip_comments && ptx.header_comments().next().is_some() {
+            ptx.header_comments().for_each(|comment| self.line(0, &comment.to_string()));
+            self.out.push('\n');
+        }
+        self.out.push_str(&ptx.preamble().to_string());
+        for item in ptx.items() {
+            self.out.push('\n');
+            match item {
+                Item::Function(function) => self.function(function)?,
+                Item::Global(global) => self.global(global),
+            }
+        }
+        Ok(())
+    }
+
+    fn global(&mut self, global: &Global) {
+        self.leading(global.comments(), 0);
+        self.out.push_str(&format!(".global {};", self.squeeze(global.raw_string())));
+        self.trailing(global.comments());
+        self.out.push('\n');
+    }
+
+    fn function<'a>(&mut self, function: &Function<'a>) -> Result<(), ParseError<'a>> {
+        let signature = function.signature();
+        self.leading(function.comments(), 0);
+        if signature.is_visible() {
+            self.out.push_str(".visible ");
+        }
+        self.out.push_str(if signature.is_entry() { ".entry" } else { ".func" });
+        if let Some(return_value) = signature.return_value() {
+            self.out.push_str(&format!(" ({})", self.squeeze(return_value.raw_string())));
+        }
+        self.out.push(' ');
+        self.out.push_str(signature.name());
+        if let Some(parameters) = signature.parameters() {
+            let parameters: Vec<_> = split_top_level(parameters.raw_string())
+                .map(|parameter| self.squeeze(parameter))
+                .filter(|parameter| !parameter.is_empty())
+                .collect();
+            match parameters.is_empty() {
+                true => self.out.push_str("()"),
+                false => self.out.push_str(&format!("(\n\t{}\n)", parameters.join(",\n\t"))),
+            }
+        }
+        match function.body() {
+            Some(body) => {
+                self.out.push_str("\n{\n");
+                self.body(body, 1)?;
+                self.out.push('}');
+            }
+            None => self.out.push(';'),
+        }
+        self.trailing(function.comments());
+        self.out.push('\n');
+        Ok(())
+    }
+
+    fn body<'a>(&mut self, body: FunctionBody<'a>, depth: usize) -> Result<(), ParseError<'a>> {
+        let statements = body.collect::<Result<Vec<_>, _>>()?;
+        let layouts: Vec<_> = statements.iter().map(|statement| self.strip(layout(statement.line()))).collect();
+        let width = layouts.iter()
+            .filter_map(|layout| match layout {
+                Layout::Aligned(mnemonic, _) => Some(mnemonic.len()),
+                _ => None,
+            })
+            .filter(|width| *width <= MAX_MNEMONIC_WIDTH)
+            .max()
+            .unwrap_or_default();
+        for (index, (statement, layout)) in statements.iter().zip(layouts).enumerate() {
+            if index > 0 && statement.comments().has_blank_line() {
+                self.out.push('\n');
+            }
+            self.leading(statement.comments(), depth);
+            match layout {
+                Layout::Aligned(mnemonic, operands) if operands.is_empty() => {
+                    self.indent(depth);
+                    self.out.push_str(&format!("{mnemonic};"));
+                }
+                Layout::Aligned(mnemonic, operands) => {
+                    self.indent(depth);
+                    self.out.push_str(&format!("{mnemonic:<width$} {operands};"));
+                }
+                Layout::Plain(text) => {
+                    self.indent(depth);
+                    self.out.push_str(&text);
+                }
+                Layout::Label(label) => self.out.push_str(&label),
+                Layout::Block(call) => {
+                    self.indent(depth);
+                    self.out.push_str("{\n");
+                    self.body(call.statements(), depth + 1)?;
+                    self.indent(depth);
+                    self.out.push('}');
+                }
+            }
+            self.trailing(statement.comments());
+            self.out.push('\n');
+        }
+        Ok(())
+    }
+
+    /// Collapses whitespace, and drops comments if they are stripped.
+    fn squeeze(&self, text: &str) -> String {
+        match self.options.strip_comments {
+            true => squeeze(&without_comments(text)),
+            false => squeeze(text),
+        }
+    }
+
+    /// Drops the comments inside a statement if they are stripped.
+    fn strip<'l, 'a>(&self, layout: Layout<'l, 'a>) -> Layout<'l, 'a> {
+        if !self.options.strip_comments {
+            return layout
+        }
+        match layout {
+            Layout::Aligned(mnemonic, operands) => Layout::Aligned(self.squeeze(&mnemonic), self.squeeze(&operands)),
+            Layout::Plain(text) => Layout::Plain(self.squeeze(&text)),
+            Layout::Label(label) => Layout::Label(self.squeeze(&label)),
+            Layout::Block(call) => Layout::Block(call),
+        }
+    }
+
+    fn leading(&mut self, comments: &Comments, depth: usize) {
+        if !self.options.strip_comments {
+            comments.leading().for_each(|comment| self.line(depth, &comment.to_string()));
+        }
+    }
+
+    fn trailing(&mut self, comments: &Comments) {
+        match comments.trailing() {
+            Some(comment) if !self.options.strip_comments => self.out.push_str(&format!(" {comment}")),
+            _ => {}
+        }
+    }
+
+    fn line(&mut self, depth: usize, text: &str) {
+        self.indent(depth);
+        self.out.push_str(text);
+        self.out.push('\n');
+    }
+
+    fn indent(&mut self, depth: usize) {
+        (0..depth).for_each(|_| self.out.push('\t'));
+    }
+}
+
+fn layout<'l, 'a>(line: &'l BodyLine<'a>) -> Layout<'l, 'a> {
+    match line {
+        BodyLine::Instruction(instruction) => {
+            let mut mnemonic = String::new();
+            if let Some(predicate) = instruction.predicate() {
+                mnemonic.push_str(&format!("{predicate} "));
+            }
+            mnemonic.push_str(instruction.opcode().name());
+            instruction.modifiers().for_each(|modifier| mnemonic.push_str(&format!(".{modifier}")));
+            let operands: Vec<_> = instruction.operands().map(|operand| operand.to_string()).collect();
+            Layout::Aligned(mnemonic, operands.join(", "))
+        }
+        BodyLine::Goto(goto) => {
+            let predicate = goto.predicate().map(|predicate| format!("{predicate} ")).unwrap_or_default();
+            let bra = if goto.is_uniform() { "bra.uni" } else { "bra" };
+            Layout::Aligned(format!("{predicate}{bra}"), goto.label().to_string())
+        }
+        BodyLine::Operation(operation) => {
+            Layout::Aligned(operation.operation().to_string(), squeeze(operation.arguments()))
+        }
+        BodyLine::Register(register) => Layout::Plain(format!(".reg {};", squeeze(register.raw_string()))),
+        BodyLine::Directive(directive) => match squeeze(directive.arguments()) {
+            arguments if arguments.is_empty() => Layout::Plain(format!(".{};", directive.name())),
+            arguments => Layout::Plain(format!(".{} {arguments};", directive.name())),
+        },
+        BodyLine::Label(label) => Layout::Label(format!("{label}:")),
+        BodyLine::Return { uniform } => {
+            Layout::Aligned(if *uniform { "ret.uni" } else { "ret" }.to_string(), String::new())
+        }
+        BodyLine::FunctionCall(call) => Layout::Block(call),
+        BodyLine::Unknown(raw_string) => Layout::Plain(format!("{};", squeeze(raw_string))),
+    }
+}
+
+/// Collapses runs of whitespace outside string literals into one space.
+fn squeeze(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut in_string = false;
+    let mut space = false;
+    for c in text.trim().chars() {
+        if c.is_whitespace() && !in_string {
+            space = true;
+            continue
+        }
+        if core::mem::take(&mut space) {
+            out.push(' ');
+        }
+        in_string ^= c == '"';
+        out.push(c);
+    }
+    out
+}
+
+/// Replaces the comments outside string literals with spaces.
+fn without_comments(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut in_string = false;
+    let mut rest = text;
+    while let Some(c) = rest.chars().next() {
+        if !in_string && rest.starts_with("/*") {
+            rest = rest[2..].find("*/").map_or("", |end| &rest[end + 4..]);
+            out.push(' ');
+            continue
+        }
+        if !in_string && rest.starts_with("//") {
+            rest = rest.find('\n').map_or("", |end| &rest[end..]);
+            continue
+        }
+        in_string ^= c == '"';
+        out.push(c);
+        rest = &rest[c.len_utf8()..];
+    }
+    out
+}
+
+#[cfg(test)]
+mod test_format {
+    use super::{format, FormatOptions};
+
+    fn without_whitespace(text: &str) -> String {
+        text.split_whitespace().collect()
+    }
+
+    #[test]
+    fn layout() {
+        let input = "// header\n.version 7.5\n.target sm_30\n.address_size 64\n\n\n\
+            .global .align 4   .u32 x;\n.visible .entry foo(.param .u64 a,   .param .u32 b)\n{\n\
+            .reg .b32   %r<4>;\n\n\n  @%p1 bra $L1; // skip\n    ld.param.u64 %rd1,[a];\n\
+            $L1:\n{\n.param .b32 y;\ncall.uni (y), bar, (%r1);\n}\n ret;\n}\n\
+            .func bar();";
+        assert_eq!(format(input, FormatOptions::default()).unwrap(), "// header
+
+.version 7.5
+.target sm_30
+.address_size 64
+
+.global .align 4 .u32 x;
+
+.visible .entry foo(
+\t.param .u64 a,
+\t.param .u32 b
+)
+{
+\t.reg .b32 %r<4>;
+
+\t@%p1 bra     $L1; // skip
+\tld.param.u64 %rd1, [a];
+$L1:
+\t{
+\t\t.param .b32 y;
+\t\tcall.uni (y), bar, (%r1);
+\t}
+\tret;
+}
+
+.func bar();
+");
+    }
+
+    #[test]
+    fn keeps_modifiers() {
+        let input = ".version 7.5\n.target sm_30\n.address_size 64\n\n\
+            .visible .entry foo()\n{\n  ld.shared::cluster.u32 %r1, [%rd1];\n  bra.uni   $L1;\n$L1:\n  ret.uni;\n}\n";
+        let formatted = format(input, FormatOptions::default()).unwrap();
+        assert!(formatted.ends_with("{\n\tld.shared::cluster.u32 %r1, [%rd1];\n\tbra.uni                $L1;\n$L1:\n\tret.uni;\n}\n"));
+        assert_eq!(without_whitespace(&formatted), without_whitespace(input));
+    }
+
+    #[test]
+    fn strips_comments() {
+        let input = ".version 7.5\n.target sm_30\n.address_size 64\n\
+            /* g */ .global /* x */ .u32 counter; /* z */\n\
+            .visible .entry foo(/* p */ .param .u64 x // first\n)\n{\n\
+            .reg /* r */ .b32 %r<2>;\n\tmov.u32 %r1, /* imm */ 1; // after\n\
+            frob.x %r1 /* w */;\n$L1: /* l */\n\tret;\n}\n";
+        let stripped = format(input, FormatOptions { strip_comments: true }).unwrap();
+        assert!(!stripped.contains("/*") && !stripped.contains("//"));
+        assert!(stripped.contains(".global .u32 counter;\n"));
+        assert!(stripped.contains("foo(\n\t.param .u64 x\n)"));
+        assert!(stripped.contains("\t.reg .b32 %r<2>;\n\tmov.u32 %r1, 1;\n\tfrob.x  %r1;\n$L1:\n"));
+    }
+
+    #[test]
+    fn rejects_errors() {
+        let input = ".version 7.5\n.target sm_30\n.address_size 64\n.bogus;\n";
+        assert!(format(input, FormatOptions::default()).is_err());
+    }
+}
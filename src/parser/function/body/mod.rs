@@ -1,209 +1,543 @@
 use nom::{
     branch::alt,
-    bytes::complete::{tag, take_until1, take_while1},
+    bytes::complete::{take_until1, take_while, take_while1},
     character::complete::{char, multispace1, space0, space1},
-    combinator::opt,
-    sequence::{delimited, preceded, terminated, Tuple},
-    IResult, Parser,
+    combinator::{consumed, eof, map, opt, verify},
+    error::context,
+    sequence::{delimited, pair, preceded, terminated, Tuple},
+    Parser,
 };
 
 use crate::parser::{
     is_special,
-    parse_braced_balanced, comment::{parse::{many1_comments_or_whitespace, parse_line_comment}, Comment}
+    parse_braced_balanced, comment::{parse::many1_comments_or_whitespace, trailing_comment, Comments},
+    error::{parse::{expect, token}, Diagnostic, Error, Expected, PResult, ParseError},
+    instruction::{parse::{check_instruction, is_identifier, parse_instruction}, Instruction},
+    recover,
+    span::Span,
+    ParseOptions,
 };
 
-#[derive(Debug, PartialEq)]
-pub(super) struct FunctionBody<'a> {
+/// The statements of a function body, parsed lazily.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FunctionBody<'a> {
+    pub(super) span: Span,
+    pub(super) source: &'a str,
+    pub(super) function: &'a str,
     pub(super) body: Option<&'a str>,
+    pub(super) options: ParseOptions,
 }
 
 impl<'a> Iterator for FunctionBody<'a> {
-    type Item = IResult<&'a str, BodyLine<'a>>;
+    type Item = Result<Statement<'a>, ParseError<'a>>;
 
+    /// Yields the next statement. After an error, parsing resumes past the
+    /// next `;` or `}`.
     fn next(&mut self) -> Option<Self::Item> {
+        self.next_recovering()
+            .map(|line| line.map_err(Diagnostic::into_error))
+    }
+}
+
+impl<'a> FunctionBody<'a> {
+    /// The span of the body, including its braces.
+    pub fn span(&self) -> Span {
+        self.span
+    }
+
+    pub(crate) fn source(&self) -> &'a str {
+        self.source
+    }
+
+    pub(crate) fn next_recovering(&mut self) -> Option<Result<Statement<'a>, Diagnostic<'a>>> {
         let body = self.body?;
-        Some(match preceded(
-            opt(many1_comments_or_whitespace),
-            parse_body_line,
-        )(body) {
-                Ok((body, value)) => {
-                    self.body = Some(body);
-                    Ok((body, value))
+        let line = opt(many1_comments_or_whitespace)(body)
+            .map_or(body, |(line, _)| line);
+        if line.is_empty() {
+            self.body = None;
+            return None
+        }
+        let leading = &body[..body.len() - line.len()];
+        Some(match context("statement", consumed(parse_body_line(self.source, self.function, self.options)))(line) {
+            Ok((body, (text, line))) => {
+                let (body, trailing) = trailing_comment(body);
+                self.body = Some(body);
+                Ok(Statement {
+                    span: Span::of(self.source, text),
+                    line,
+                    comments: Comments::new(leading, trailing),
+                })
             },
-                err => {
-                    let body = self.body?.trim();
-                    if body.is_empty() {
-                        self.body = None;
-                        return None
-                    } else {
-                        self.body = Some(body);
-                        err
-                    }
+            Err(err) => {
+                let body = recover(line, &[]);
+                self.body = Some(body);
+                Err(Diagnostic::new(
+                    ParseError::from_nom(
+                        self.source,
+                        err.map(|err| err.of("function", self.function)),
+                    ),
+                    &line[..line.len() - body.len()],
+                ))
             },
         })
     }
 }
 
+/// A statement of a function body together with the span it covers,
+/// including its terminating `;` or `:`.
+///
+/// Serializable only, like the rest of the borrowed AST; an
+/// [`owned::Statement`](crate::parser::owned::Statement) deserializes.
 #[derive(Debug, PartialEq)]
-pub(crate) struct Register<'a> {
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Statement<'a> {
+    span: Span,
+    line: BodyLine<'a>,
+    comments: Comments<'a>,
+}
+
+impl<'a> Statement<'a> {
+    pub fn span(&self) -> Span {
+        self.span
+    }
+
+    pub fn line(&self) -> &BodyLine<'a> {
+        &self.line
+    }
+
+    pub fn into_line(self) -> BodyLine<'a> {
+        self.line
+    }
+
+    pub fn comments(&self) -> &Comments<'a> {
+        &self.comments
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Register<'a> {
+    span: Span,
     raw_string: &'a str,
 }
 
-fn parse_register(input: &str) -> IResult<&str, Register> {
-    preceded(
-        tag(".reg").and(space1),
-        take_while1(|_| true)
-        .map(|raw_string| Register { raw_string })
-    )(input)
+impl<'a> Register<'a> {
+    pub fn span(&self) -> Span {
+        self.span
+    }
+
+    /// The declaration after `.reg`.
+    pub fn raw_string(&self) -> &'a str {
+        self.raw_string
+    }
+}
+
+fn parse_register<'a>(source: &'a str) -> impl FnMut(&'a str) -> PResult<'a, Register<'a>> {
+    map(
+        consumed(preceded(
+            token(".reg").and(space1),
+            take_while1(|_| true),
+        )),
+        move |(text, raw_string)| Register { span: Span::of(source, text), raw_string }
+    )
 }
 
 #[derive(Debug, PartialEq)]
-pub(crate) struct Operation<'a> {
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Operation<'a> {
+    span: Span,
     operation: &'a str,
     arguments: &'a str,
 }
 
 #[derive(Debug, PartialEq)]
-pub(crate) struct Goto<'a> {
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Goto<'a> {
+    span: Span,
     predicate: Option<Predicate<'a>>,
+    uniform: bool,
     label: &'a str,
 }
 
-fn parse_unknown_line(input: &str) -> IResult<&str, &str> {
+impl<'a> Operation<'a> {
+    pub fn span(&self) -> Span {
+        self.span
+    }
+
+    /// The opcode with its modifiers, e.g. `ld.param.u64`.
+    pub fn operation(&self) -> &'a str {
+        self.operation
+    }
+
+    pub fn arguments(&self) -> &'a str {
+        self.arguments
+    }
+}
+
+impl<'a> Goto<'a> {
+    pub fn span(&self) -> Span {
+        self.span
+    }
+
+    pub fn predicate(&self) -> Option<&Predicate<'a>> {
+        self.predicate.as_ref()
+    }
+
+    /// Whether the branch is `bra.uni`, i.e. known not to diverge.
+    pub fn is_uniform(&self) -> bool {
+        self.uniform
+    }
+
+    /// The target label as written, like `$L__BB0_2` or `LBB0_2`.
+    pub fn label(&self) -> &'a str {
+        self.label
+    }
+}
+
+fn parse_unknown_line(input: &str) -> PResult<'_, &str> {
     take_while1(|_| true)(input)
 }
 
-fn parse_operation(input: &str) -> IResult<&str, Operation> {
-    let (input, (operation, arguments)) = (
-        take_while1(|c: char|
-            !c.is_whitespace() && (c == '.' || !is_special(c))
-        ),
-        preceded(
-            space1,
-            take_while1(|_| true)
-        ),
-    )
-    .parse(input)?;
-    Ok((
-        input,
-        Operation {
-            operation,
-            arguments,
-        },
-    ))
+fn parse_operation<'a>(source: &'a str) -> impl FnMut(&'a str) -> PResult<'a, Operation<'a>> {
+    move |input| {
+        let (rest, (operation, arguments)) = (
+            take_while1(|c: char|
+                !c.is_whitespace() && (c == '.' || !is_special(c))
+            ),
+            preceded(
+                space1,
+                take_while1(|_| true)
+            ),
+        )
+        .parse(input)?;
+        Ok((
+            rest,
+            Operation {
+                span: Span::of(source, &input[..input.len() - rest.len()]),
+                operation,
+                arguments,
+            },
+        ))
+    }
 }
 
-fn parse_goto(input: &str) -> IResult<&str, Goto> {
-    let (label, predicate) = alt((
-        delimited(
-            char('@'),
-            opt(char('!'))
-                .and(take_while1(|c: char| !c.is_whitespace()))
+fn parse_goto<'a>(source: &'a str) -> impl FnMut(&'a str) -> PResult<'a, Goto<'a>> {
+    move |input| {
+        let (label, (predicate, uniform)) = pair(
+            opt(terminated(
+                preceded(
+                    char('@'),
+                    opt(char('!')).and(take_while1(|c: char| !c.is_whitespace())),
+                )
                 .map(|(negation, raw_string)| {
-                    Some(if negation.is_none() {
+                    if negation.is_none() {
                         Predicate::True(raw_string)
                     } else {
                         Predicate::False(raw_string)
-                    })
+                    }
                 }),
-            space1.and(tag("bra")).and(space1).and(char('$')),
-        ),
-        tag("bra.uni").and(space1).and(char('$'))
-        .map(|_| None),
-    ))
-    (input)?;
-    Ok((input, Goto { predicate, label }))
+                space1,
+            )),
+            terminated(
+                preceded(token("bra"), opt(token(".uni")).map(|uni| uni.is_some())),
+                space1,
+            ),
+        )
+        (input)?;
+        let (_, label) = terminated(
+            verify(
+                take_while1(|c: char| !c.is_whitespace()),
+                |label: &str| label.starts_with('$') || is_identifier(label),
+            ),
+            space0.and(eof),
+        )
+        (label)?;
+        Ok((input, Goto { span: Span::of(source, input), predicate, uniform, label }))
+    }
+}
+
+/// A directive inside a function body other than `.reg`, e.g. `.local .align 8 .b8 __local_depot0[8]`.
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Directive<'a> {
+    span: Span,
+    name: &'a str,
+    arguments: &'a str,
+}
+
+/// Directives that may appear in a function body, besides `.reg`.
+const BODY_DIRECTIVES: &[&str] = &[
+    "local", "shared", "param", "const", "pragma", "loc",
+    "maxnreg", "maxntid", "reqntid", "minnctapersm", "maxnctapersm",
+];
+
+impl<'a> Directive<'a> {
+    pub fn span(&self) -> Span {
+        self.span
+    }
+
+    /// The directive without its leading `.`, e.g. `local`.
+    pub fn name(&self) -> &'a str {
+        self.name
+    }
+
+    pub fn arguments(&self) -> &'a str {
+        self.arguments
+    }
+
+    pub fn is_known(&self) -> bool {
+        BODY_DIRECTIVES.contains(&self.name)
+    }
+}
+
+fn parse_directive<'a>(source: &'a str) -> impl FnMut(&'a str) -> PResult<'a, Directive<'a>> {
+    map(
+        consumed(preceded(
+            char('.'),
+            take_while1(|c: char| c.is_ascii_alphanumeric() || c == '_')
+                .and(preceded(space0, take_while(|_| true))),
+        )),
+        move |(text, (name, arguments))| Directive { span: Span::of(source, text), name, arguments }
+    )
 }
 
 #[derive(Debug, PartialEq)]
-pub(crate) struct FunctionCall<'a> {
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct FunctionCall<'a> {
+    span: Span,
     setup: &'a str,
     function: &'a str,
     arguments: &'a str,
-    comment: Comment<'a>,
+    statements: FunctionBody<'a>,
 }
 
-fn parse_function_call(input: &str) -> IResult<&str, FunctionCall> {
-    let (input, (body, comment)) = (
-        parse_braced_balanced,
-        preceded(space0, parse_line_comment)
-    )
-    .parse(input)?;
-
-    (
-        take_until1("call.uni"),
-        delimited(
-            tag("call.uni").and(multispace1),
-            take_while1(|c: char| c != ','),
-            char(','),
-        ),
-    )
-        .parse(body)
-        .map(|(arguments, (setup, function))| {
-            (
-                input,
-                FunctionCall {
-                    setup,
-                    function,
-                    arguments,
-                    comment,
-                },
-            )
-        })
+impl<'a> FunctionCall<'a> {
+    pub fn span(&self) -> Span {
+        self.span
+    }
+
+    /// The declarations and moves before `call.uni`.
+    pub fn setup(&self) -> &'a str {
+        self.setup
+    }
+
+    /// The name of the called function.
+    pub fn function(&self) -> &'a str {
+        self.function
+    }
+
+    /// Everything after the called function's name.
+    pub fn arguments(&self) -> &'a str {
+        self.arguments
+    }
+
+    /// The statements of the call sequence, including the `call` itself.
+    pub fn statements(&self) -> FunctionBody<'a> {
+        self.statements.clone()
+    }
 }
 
-fn parse_body_line(input: &str) -> IResult<&str, BodyLine> {
-    let body_line = alt((
-        delimited(
-            char('$'),
-            take_while1(|c: char| !c.is_whitespace() && c != ':'),
-            char(':'),
-        )
-        .map(BodyLine::Label),
-        parse_function_call
-        .map(BodyLine::FunctionCall),
-        terminated(
-            alt((
-                take_while1(|c: char| c != ';'),
-            )),
-            char(';')
+fn parse_function_call<'a>(
+    source: &'a str,
+    caller: &'a str,
+    options: ParseOptions,
+) -> impl FnMut(&'a str) -> PResult<'a, FunctionCall<'a>> {
+    move |input| {
+        let (rest, (block, body)) = consumed(parse_braced_balanced)(input)?;
+
+        let (arguments, (setup, function)) = (
+            expect(Expected::Token("call.uni"), take_until1("call.uni")),
+            delimited(
+                token("call.uni").and(multispace1),
+                take_while1(|c: char| c != ','),
+                char(','),
+            ),
         )
-        .map(BodyLine::Unknown)
-    ))
-    (input)?;
-    Ok(match body_line {
-        (input, BodyLine::Unknown(raw_string)) => {
-            let (_, body_line) = alt((
-                tag("ret").map(|_| BodyLine::Return),
-                parse_goto.map(BodyLine::Goto),
-                parse_register.map(BodyLine::Register),
-                parse_operation.map(BodyLine::Operation),
-                parse_unknown_line.map(BodyLine::Unknown),
-            ))
-            .parse(raw_string)?;
-            (input, body_line)
+            .parse(body)?;
+        let call = FunctionCall {
+            span: Span::of(source, block),
+            setup,
+            function,
+            arguments,
+            statements: FunctionBody {
+                span: Span::of(source, block),
+                source,
+                function: caller,
+                body: Some(body),
+                options,
+            },
+        };
+        if options.strict {
+            // Fail outright so the block is not reparsed as a single unknown statement.
+            check_statements(source, caller, options, body).map_err(|error| match error {
+                nom::Err::Error(error) => nom::Err::Failure(error),
+                error => error,
+            })?;
         }
-        label_or_braced => label_or_braced,
-    })
+        Ok((rest, call))
+    }
+}
+
+/// Fails on the first statement of `body` that does not parse.
+fn check_statements<'a>(
+    source: &'a str,
+    function: &'a str,
+    options: ParseOptions,
+    mut body: &'a str,
+) -> Result<(), nom::Err<Error<'a>>> {
+    loop {
+        let line = opt(many1_comments_or_whitespace)(body)
+            .map_or(body, |(line, _)| line);
+        if line.is_empty() {
+            return Ok(())
+        }
+        (body, _) = parse_body_line(source, function, options)(line)?;
+    }
+}
+
+/// Rejects statements that were not fully understood.
+fn check_strict<'a>(body_line: &BodyLine<'a>) -> Result<(), nom::Err<Error<'a>>> {
+    let unknown = |input, description| Err(nom::Err::Error(Error::expected(input, Expected::Description(description))));
+    match body_line {
+        BodyLine::Operation(operation) => unknown(operation.operation, "a known opcode"),
+        BodyLine::Directive(directive) if !directive.is_known() => unknown(directive.name, "a known directive"),
+        BodyLine::Instruction(instruction) => check_instruction(instruction),
+        BodyLine::Unknown(raw_string) => unknown(raw_string, "a statement"),
+        _ => Ok(()),
+    }
+}
+
+fn parse_body_line<'a>(
+    source: &'a str,
+    function: &'a str,
+    options: ParseOptions,
+) -> impl FnMut(&'a str) -> PResult<'a, BodyLine<'a>> {
+    move |input| {
+        let body_line = alt((
+            terminated(
+                verify(
+                    take_while1(|c: char| !c.is_whitespace() && c != ':'),
+                    |label: &str| label.starts_with('$') || is_identifier(label),
+                ),
+                char(':'),
+            )
+            .map(BodyLine::Label),
+            parse_function_call(source, function, options)
+            .map(BodyLine::FunctionCall),
+            terminated(
+                alt((
+                    take_while1(|c: char| c != ';'),
+                )),
+                char(';')
+            )
+            .map(BodyLine::Unknown)
+        ))
+        (input)?;
+        let (input, body_line) = match body_line {
+            (input, BodyLine::Unknown(raw_string)) => {
+                let (_, body_line) = alt((
+                    preceded(token("ret"), terminated(opt(token(".uni")), eof))
+                        .map(|uni| BodyLine::Return { uniform: uni.is_some() }),
+                    parse_goto(source).map(BodyLine::Goto),
+                    parse_register(source).map(BodyLine::Register),
+                    parse_directive(source).map(BodyLine::Directive),
+                    parse_instruction(source).map(BodyLine::Instruction),
+                    parse_operation(source).map(BodyLine::Operation),
+                    parse_unknown_line.map(BodyLine::Unknown),
+                ))
+                .parse(raw_string)?;
+                (input, body_line)
+            }
+            label_or_braced => label_or_braced,
+        };
+        if options.strict {
+            check_strict(&body_line)?;
+        }
+        Ok((input, body_line))
+    }
 }
 
 #[derive(Debug, PartialEq)]
-pub(crate) enum BodyLine<'a> {
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum BodyLine<'a> {
     Register(Register<'a>),
+    Directive(Directive<'a>),
+    /// A statement whose opcode is known.
+    Instruction(Instruction<'a>),
+    /// A statement with an unknown opcode.
     Operation(Operation<'a>),
+    /// A label as written, like `$L__BB0_2` or `LBB0_2`.
     Label(&'a str),
     Goto(Goto<'a>),
-    Return,
+    /// `ret`, or `ret.uni` if `uniform`.
+    Return { uniform: bool },
     FunctionCall(FunctionCall<'a>),
     Unknown(&'a str),
 }
 
-#[derive(Debug, PartialEq)]
-pub(crate) enum Predicate<'a> {
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum Predicate<'a> {
     True(&'a str),
     False(&'a str),
 }
 
+#[cfg(feature = "std")]
+#[cfg(test)]
+mod test_spans {
+    use crate::parser::{BodyLine, FunctionOrGlobal, PtxParser};
+
+    #[test]
+    fn spans_cover_source_text() {
+        let input = ".version 7.5
+.target sm_30
+.address_size 64
+
+.global .u32 counter;
+
+.visible .entry kernel(
+	.param .u64 kernel_param_0
+)
+{
+	.reg .b32 	%r<2>;
+	mov.u32 	%r1, %tid.x;
+	@%p1 bra 	$L__BB0_2;
+$L__BB0_2:
+	ret;
+}
+";
+        let mut ptx = PtxParser::try_from(input).unwrap();
+        assert_eq!(ptx.preamble().span().text(input), ".version 7.5\n.target sm_30\n.address_size 64");
+        assert_eq!(ptx.preamble().target().span().text(input), ".target sm_30");
+
+        let Some(Ok(FunctionOrGlobal::Global(global))) = ptx.next() else { panic!() };
+        assert_eq!(global.span().text(input), ".global .u32 counter;");
+
+        let Some(Ok(FunctionOrGlobal::Function(function))) = ptx.next() else { panic!() };
+        assert_eq!(function.span().start, input.find(".visible").unwrap());
+        assert_eq!(function.span().end, input.len() - 1);
+        let signature = function.signature();
+        assert_eq!(signature.span().text(input), ".visible .entry kernel(\n\t.param .u64 kernel_param_0\n)");
+        assert_eq!(signature.parameters().unwrap().span().text(input), "(\n\t.param .u64 kernel_param_0\n)");
+
+        let statements: Vec<_> = function.body().unwrap().map(Result::unwrap).collect();
+        let texts: Vec<_> = statements.iter().map(|statement| statement.span().text(input)).collect();
+        assert_eq!(texts, [
+            ".reg .b32 \t%r<2>;",
+            "mov.u32 \t%r1, %tid.x;",
+            "@%p1 bra \t$L__BB0_2;",
+            "$L__BB0_2:",
+            "ret;",
+        ]);
+        let BodyLine::Instruction(instruction) = statements[1].line() else { panic!() };
+        assert_eq!(instruction.span().text(input), "mov.u32 \t%r1, %tid.x");
+        let BodyLine::Goto(goto) = statements[2].line() else { panic!() };
+        assert_eq!(goto.span().text(input), "@%p1 bra \t$L__BB0_2");
+        assert_eq!(goto.label(), "$L__BB0_2");
+        assert!(ptx.next().is_none());
+    }
+}
+
+#[cfg(feature = "std")]
 #[cfg(test)]
 mod test_iterator {
     use crate::{
@@ -218,7 +552,7 @@ mod test_iterator {
         ptx
         .into_iter()
             .filter_map(|line| line.ok())
-        .filter_map(|(_, function)| {
+        .filter_map(|function| {
             if let FunctionOrGlobal::Function(function) = function {
                 Some(function)
             } else {
@@ -228,10 +562,8 @@ mod test_iterator {
         .for_each(|function| {
             dbg!(&function);
             if let Some(body) = function.body {
-                for line in body {
-                    if let Ok(line) = line {
-                        dbg!(line.1);
-                    }
+                for line in body.flatten() {
+                    dbg!(line);
                 }
             }
         });
@@ -242,7 +574,7 @@ mod test_iterator {
         ptx
         .into_iter()
             .filter_map(|line| line.ok())
-        .filter_map(|(_, function)| {
+        .filter_map(|function| {
             if let FunctionOrGlobal::Function(function) = function {
                 Some(function)
             } else {
@@ -252,9 +584,8 @@ mod test_iterator {
         .for_each(|function| {
             if let Some(body) = function.body {
                 body.filter_map(Result::ok)
-                    .map(|(_, line)| line)
-                    .for_each(|line| {
-                        if let BodyLine::Unknown(raw_string) = line {
+                    .for_each(|statement| {
+                        if let BodyLine::Unknown(raw_string) = statement.line() {
                             dbg!("Unknown line", raw_string);
                         }
                     })
@@ -276,7 +607,7 @@ mod test_iterator {
         ptx
         .into_iter()
         .filter_map(|line| line.ok())
-        .filter_map(|(_, function)| {
+        .filter_map(|function| {
             if let FunctionOrGlobal::Function(function) = function {
                 Some(function)
             } else {
@@ -287,8 +618,7 @@ mod test_iterator {
         .for_each(|body| {
             body
             .filter_map(Result::ok)
-            .map(|(_, line)| line)
-            .filter_map(|line| line.operation())
+            .filter_map(|statement| statement.into_line().operation())
             .for_each(|operation| {
                 dbg!(operation);
             })
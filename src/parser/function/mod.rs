@@ -1,14 +1,59 @@
+use super::{comment::Comments, span::Span};
+
 pub(super) mod body;
 pub(crate) mod parse;
 
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Function<'a> {
+    span: Span,
     signature: FunctionSignature<'a>,
     body: Option<body::FunctionBody<'a>>,
+    comments: Comments<'a>,
+}
+
+impl<'a> Function<'a> {
+    pub fn span(&self) -> Span {
+        self.span
+    }
+
+    pub fn comments(&self) -> &Comments<'a> {
+        &self.comments
+    }
+
+    pub(crate) fn with_comments(self, comments: Comments<'a>) -> Self {
+        Self { comments, ..self }
+    }
+
+    pub fn signature(&self) -> &FunctionSignature<'a> {
+        &self.signature
+    }
+
+    pub fn name(&self) -> &'a str {
+        self.signature.name
+    }
+
+    /// Whether the function is a kernel (`.entry`) rather than a device function.
+    pub fn is_entry(&self) -> bool {
+        self.signature.entry
+    }
+
+    /// Returns a fresh iterator over the body's statements,
+    /// or `None` for a declaration without a body.
+    pub fn body(&self) -> Option<body::FunctionBody<'a>> {
+        self.body.clone()
+    }
+
+    /// Whether this is a forward declaration like `.func foo(...);`.
+    pub fn is_declaration(&self) -> bool {
+        self.body.is_none()
+    }
 }
 
 #[derive(Debug, PartialEq)]
-pub(super) struct FunctionSignature<'a> {
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct FunctionSignature<'a> {
+    span: Span,
     visible: bool,
     entry: bool,
     return_value: Option<ReturnValue<'a>>,
@@ -16,32 +61,83 @@ pub(super) struct FunctionSignature<'a> {
     parameters: Option<Parameters<'a>>,
 }
 
+impl<'a> FunctionSignature<'a> {
+    pub fn span(&self) -> Span {
+        self.span
+    }
+
+    pub fn is_visible(&self) -> bool {
+        self.visible
+    }
+
+    pub fn is_entry(&self) -> bool {
+        self.entry
+    }
+
+    pub fn return_value(&self) -> Option<&ReturnValue<'a>> {
+        self.return_value.as_ref()
+    }
+
+    pub fn name(&self) -> &'a str {
+        self.name
+    }
+
+    pub fn parameters(&self) -> Option<&Parameters<'a>> {
+        self.parameters.as_ref()
+    }
+}
+
 #[derive(Debug, PartialEq)]
-pub(super) struct ReturnValue<'a> {
+pub struct ReturnValue<'a> {
+    span: Span,
     raw_string: &'a str,
 }
 
 #[derive(Debug, PartialEq)]
-pub(super) struct Parameters<'a> {
+pub struct Parameters<'a> {
+    span: Span,
     raw_string: &'a str,
 }
 
+impl<'a> ReturnValue<'a> {
+    /// The span of the return value, including its parentheses.
+    pub fn span(&self) -> Span {
+        self.span
+    }
+
+    pub fn raw_string(&self) -> &'a str {
+        self.raw_string
+    }
+}
+
+impl<'a> Parameters<'a> {
+    /// The span of the parameter list, including its parentheses.
+    pub fn span(&self) -> Span {
+        self.span
+    }
+
+    pub fn raw_string(&self) -> &'a str {
+        self.raw_string
+    }
+}
+
 #[cfg(test)]
 mod test_parse_function_signature {
 
-    use crate::parser::function::parse::parse_function_signature;
+    use crate::parser::{function::parse::parse_function_signature, span::Span};
 
     use super::{FunctionSignature, ReturnValue, Parameters};
 
     #[test]
     fn visible_entry_name() {
         let input = ".visible .entry _Z6kernelPiS_i";
-        let signature = parse_function_signature(input);
+        let signature = parse_function_signature(input)(input);
         assert_eq!(
             signature,
             Ok((
                 "",
                 FunctionSignature {
+                    span: Span::new(0, input.len()),
                     visible: true,
                     entry: true,
                     return_value: None,
@@ -55,12 +151,13 @@ mod test_parse_function_signature {
     #[test]
     fn func_no_return_no_parameters() {
         let input = ".func _Z6kernelPiS_i";
-        let signature = parse_function_signature(input);
+        let signature = parse_function_signature(input)(input);
         assert_eq!(
             signature,
             Ok((
                 "",
                 FunctionSignature {
+                    span: Span::new(0, input.len()),
                     visible: false,
                     entry: false,
                     return_value: None,
@@ -75,17 +172,18 @@ mod test_parse_function_signature {
     fn func_no_return_trivial_parameters() {
         let input =
 ".func _ZN4core9panicking(hi)";
-        let signature = parse_function_signature(input);
+        let signature = parse_function_signature(input)(input);
         assert_eq!(
             signature,
             Ok((
                 "",
                 FunctionSignature {
+                    span: Span::new(0, input.len()),
                     visible: false,
                     entry: false,
                     return_value: None,
                     name: "_ZN4core9panicking",
-                    parameters: Some(Parameters { raw_string: "hi" }),
+                    parameters: Some(Parameters { span: Span::new(24, 28), raw_string: "hi" }),
                 }
             ))
         )
@@ -100,17 +198,18 @@ mod test_parse_function_signature {
 	.param .b64 _ZN4core9panicking_param_1,
 	.param .b64 _ZN4core9panicking_param_2
 )";
-        let signature = parse_function_signature(input);
+        let signature = parse_function_signature(input)(input);
         assert_eq!(
             signature,
             Ok((
                 "",
                 FunctionSignature {
+                    span: Span::new(0, input.len()),
                     visible: false,
                     entry: false,
                     return_value: None,
                     name: "_ZN4core9panicking",
-                    parameters: Some(Parameters { raw_string: "
+                    parameters: Some(Parameters { span: Span::new(25, input.len()), raw_string: "
 	.param .b64 _ZN4core9panicking_param_0,
 	.param .b64 _ZN4core9panicking_param_1,
 	.param .b64 _ZN4core9panicking_param_2
@@ -127,17 +226,18 @@ mod test_parse_function_signature {
 	.param .b64 _foo_param_0,
 	.param .b64 _foo_param_1
 )";
-        let signature = parse_function_signature(input);
+        let signature = parse_function_signature(input)(input);
         assert_eq!(
             signature,
             Ok((
                 "",
                 FunctionSignature {
+                    span: Span::new(0, input.len()),
                     visible: false,
                     entry: false,
-                    return_value: Some(ReturnValue { raw_string: ".param .b64 func_retval0" }),
+                    return_value: Some(ReturnValue { span: Span::new(7, 33), raw_string: ".param .b64 func_retval0" }),
                     name: "_foo",
-                    parameters: Some(Parameters { raw_string: "
+                    parameters: Some(Parameters { span: Span::new(38, input.len()), raw_string: "
 	.param .b64 _foo_param_0,
 	.param .b64 _foo_param_1
 "})
@@ -150,14 +250,16 @@ mod test_parse_function_signature {
 #[cfg(test)]
 mod test_parse_function_body {
 
-    use crate::parser::function::{
-        parse::parse_function_body, body::FunctionBody,
+    use crate::parser::{
+        function::{parse::parse_function_body, body::FunctionBody},
+        span::Span,
+        ParseOptions,
     };
 
     #[test]
     fn empty() {
         let input = ";";
-        let body = parse_function_body(input);
+        let body = parse_function_body(input, "foo", ParseOptions::default())(input);
         assert!(
             body.is_err()
         )
@@ -166,12 +268,18 @@ mod test_parse_function_body {
     #[test]
     fn non_empty() {
         let input = "{.reg .b32 %r<3>}";
-        let body = parse_function_body(input);
+        let body = parse_function_body(input, "foo", ParseOptions::default())(input);
         assert_eq!(
             body,
             Ok((
                 "",
-                FunctionBody { body: Some(".reg .b32 %r<3>") }
+                FunctionBody {
+                    span: Span::new(0, 17),
+                    source: input,
+                    function: "foo",
+                    body: Some(".reg .b32 %r<3>"),
+                    options: ParseOptions::default(),
+                }
             ))
         )
     }
@@ -179,19 +287,25 @@ mod test_parse_function_body {
 
 #[cfg(test)]
 mod test_parse_function {
-    use crate::parser::function::{parse::parse_function, Function, FunctionSignature, body::FunctionBody};
+    use crate::parser::{
+        function::{parse::parse_function, Function, FunctionSignature, body::FunctionBody},
+        span::Span,
+        ParseOptions,
+    };
 
 
     #[test]
     fn no_return_no_parameters_no_body() {
         let input = ".func _Z6kernelPiS_i;";
-        let function = parse_function(input);
+        let function = parse_function(input, ParseOptions::default())(input);
         assert_eq!(
             function,
             Ok((
                 "",
                 Function {
+                    span: Span::new(0, input.len()),
                     signature: FunctionSignature {
+                        span: Span::new(0, 20),
                         visible: false,
                         entry: false,
                         return_value: None,
@@ -199,6 +313,7 @@ mod test_parse_function {
                         parameters: None,
                     },
                     body: None,
+                    comments: Default::default(),
                 }
             ))
         )
@@ -207,22 +322,62 @@ mod test_parse_function {
     #[test]
     fn no_return_no_parameters_with_body() {
         let input = ".func _Z6kernelPiS_i { \n foo \n bar }";
-        let function = parse_function(input);
+        let function = parse_function(input, ParseOptions::default())(input);
         assert_eq!(
             function,
             Ok((
                 "",
                 Function {
+                    span: Span::new(0, input.len()),
+                    signature: FunctionSignature {
+                        span: Span::new(0, 20),
+                        visible: false,
+                        entry: false,
+                        return_value: None,
+                        name: "_Z6kernelPiS_i",
+                        parameters: None,
+                    },
+                    body: Some(FunctionBody {
+                        span: Span::new(21, input.len()),
+                        source: input,
+                        function: "_Z6kernelPiS_i",
+                        body: Some(" \n foo \n bar "),
+                        options: ParseOptions::default(),
+                    }),
+                    comments: Default::default(),
+                }
+            ))
+        )
+    }
+
+    #[test]
+    fn body_with_brace_in_comment() {
+        let input = ".func _Z6kernelPiS_i { // } \n ret; }\n.global foo;";
+        let function = parse_function(input, ParseOptions::default())(input);
+        assert_eq!(
+            function,
+            Ok((
+                "\n.global foo;",
+                Function {
+                    span: Span::new(0, 36),
                     signature: FunctionSignature {
+                        span: Span::new(0, 20),
                         visible: false,
                         entry: false,
                         return_value: None,
                         name: "_Z6kernelPiS_i",
                         parameters: None,
                     },
-                    body: Some(FunctionBody { body: Some(" \n foo \n bar ") }),
+                    body: Some(FunctionBody {
+                        span: Span::new(21, 36),
+                        source: input,
+                        function: "_Z6kernelPiS_i",
+                        body: Some(" // } \n ret; "),
+                        options: ParseOptions::default(),
+                    }),
+                    comments: Default::default(),
                 }
             ))
         )
     }
-}
\ No newline at end of file
+}
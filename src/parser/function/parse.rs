@@ -1,91 +1,136 @@
 use nom::{
-    IResult,
-    bytes::complete::tag,
     Parser,
     sequence::preceded,
     character::complete::{space0, space1, multispace0},
-    combinator::{opt, value, map},
+    combinator::{consumed, opt, value, map},
     branch::alt,
     character::complete::char,
+    error::context,
 };
 
-use crate::parser::{comment::parse::many1_comments_or_whitespace, parse_braced_balanced, parse_parenthesized_naive, parse_name};
+use crate::parser::{
+    comment::Comments,
+    comment::parse::many1_comments_or_whitespace,
+    error::{parse::{expect, token}, Expected, PResult},
+    parse_braced_balanced, parse_parenthesized_balanced, parse_name,
+    span::Span,
+    ParseOptions,
+};
 
 use super::{Function, body::FunctionBody, FunctionSignature, ReturnValue, Parameters};
 
-pub(crate) fn parse_function(input: &str) -> IResult<&str, Function> {
-    let (input, signature) = 
-    parse_function_signature(input)?;
-    let (input, body) = preceded(
-        opt(many1_comments_or_whitespace),
-        alt((
-            map(
-                char(';'),
-                |_| None
-            ),
-            parse_function_body
-            .map(Some)
+pub(crate) fn parse_function<'a>(
+    source: &'a str,
+    options: ParseOptions,
+) -> impl FnMut(&'a str) -> PResult<'a, Function<'a>> {
+    move |input| {
+        let start = input;
+        let (input, signature) = 
+        parse_function_signature(source)(input)?;
+        let name = signature.name;
+        let (input, body) = preceded(
+            opt(many1_comments_or_whitespace),
+            alt((
+                map(
+                    char(';'),
+                    |_| None
+                ),
+                parse_function_body(source, name, options)
+                .map(Some)
+            ))
+        )(input)
+        .map_err(|error| error.map(|error| error.of("function", name)))?;
+        Ok((
+            input,
+            Function {
+                span: Span::of(source, &start[..start.len() - input.len()]),
+                signature,
+                body,
+                comments: Comments::default(),
+            }
         ))
-    )(input)?;
-    Ok((
-        input,
-        Function {
-            signature,
-            body,
-        }
-    ))
+    }
 }
 
-pub(super) fn parse_function_body(input: &str) -> IResult<&str, FunctionBody> {
-    parse_braced_balanced
-        .map(|raw_string| FunctionBody { body: Some(raw_string) })
-    .parse(input)
+pub(super) fn parse_function_body<'a>(
+    source: &'a str,
+    function: &'a str,
+    options: ParseOptions,
+) -> impl FnMut(&'a str) -> PResult<'a, FunctionBody<'a>> {
+    map(
+        context("function body", consumed(parse_braced_balanced)),
+        move |(text, raw_string)| FunctionBody {
+            span: Span::of(source, text),
+            source,
+            function,
+            body: Some(raw_string),
+            options,
+        }
+    )
 }
 
-pub(super) fn parse_function_signature(input: &str) -> IResult<&str, FunctionSignature> {
-    let (input, (visible, entry)) = alt((
-        value(
-            (true, true),
-            tag(".visible")
-            .and(space1)
-            .and(tag(".entry"))
-        ),
-        value(
-            (false, false),
-            tag(".func")
-        )
-    ))
-    (input)?;
-    
-    let (input, return_value) = preceded(
-        space1,
-        opt(
-            parse_parenthesized_naive
-            .map(|raw_string| ReturnValue { raw_string })
+pub(super) fn parse_function_signature<'a>(source: &'a str) -> impl FnMut(&'a str) -> PResult<'a, FunctionSignature<'a>> {
+    move |input| {
+        let start = input;
+        let (input, (visible, entry)) = context(
+            "function signature",
+            expect(
+                Expected::Description("`.func` or `.visible .entry`"),
+                alt((
+                    value(
+                        (true, true),
+                        token(".visible")
+                        .and(space1)
+                        .and(token(".entry"))
+                    ),
+                    value(
+                        (false, false),
+                        token(".func")
+                    )
+                )),
+            ),
         )
-    )(input)?;
+        (input)?;
+        
+        let (input, return_value) = context(
+            "return value",
+            preceded(
+                space1,
+                opt(
+                    consumed(parse_parenthesized_balanced)
+                    .map(|(text, raw_string)| ReturnValue { span: Span::of(source, text), raw_string })
+                )
+            ),
+        )(input)?;
 
-    let (input, name) = preceded(
-        space0,
-        parse_name
-    )(input)?;
+        let (input, name) = context(
+            "function signature",
+            preceded(
+                space0,
+                parse_name
+            ),
+        )(input)?;
 
-    let (input, parameters) = preceded(
-        multispace0,
-        opt(
-            parse_parenthesized_naive
-            .map(|raw_string| Parameters { raw_string })
-        )
-    )(input)?;
+        let (input, parameters) = context(
+            "parameter list",
+            opt(preceded(
+                multispace0,
+                consumed(parse_parenthesized_balanced)
+                .map(|(text, raw_string)| Parameters { span: Span::of(source, text), raw_string })
+            )),
+        )(input)
+        .map_err(|error| error.map(|error| error.of("function", name)))?;
 
-    Ok((
-        input,
-        FunctionSignature {
-            visible,
-            entry,
-            return_value,
-            name,
-            parameters,
-        }
-    ))
+        Ok((
+            input,
+            FunctionSignature {
+                span: Span::of(source, &start[..start.len() - input.len()]),
+                visible,
+                entry,
+                return_value,
+                name,
+                parameters,
+            }
+        ))
+    }
 }
@@ -1,18 +1,99 @@
+use super::{comment::Comments, span::Span, split_top_level};
+
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Global<'a> {
+    span: Span,
     raw_string: &'a str,
+    comments: Comments<'a>,
+}
+
+impl<'a> Global<'a> {
+    pub fn span(&self) -> Span {
+        self.span
+    }
+
+    pub fn comments(&self) -> &Comments<'a> {
+        &self.comments
+    }
+
+    pub(crate) fn with_comments(self, comments: Comments<'a>) -> Self {
+        Self { comments, ..self }
+    }
+
+    /// The declaration between `.global` and `;`.
+    pub fn raw_string(&self) -> &'a str {
+        self.raw_string
+    }
+
+    /// The name of the first variable, the last word before any array
+    /// dimensions or initializer.
+    pub fn name(&self) -> &'a str {
+        declared_name(split_top_level(self.raw_string).next().unwrap_or_default())
+    }
+
+    /// The names of all variables, like `a` and `b` in `.global .u32 a, b;`.
+    pub fn names(&self) -> impl Iterator<Item = &'a str> {
+        split_top_level(self.raw_string).map(declared_name)
+    }
+
+    /// The value after `=`, like `{1, 2}`.
+    pub fn initializer(&self) -> Option<&'a str> {
+        self.raw_string.split_once('=').map(|(_, value)| value.trim())
+    }
+}
+
+/// The name in a declaration like `.align 4 .u32 table[2] = {1, 2}`.
+pub(crate) fn declared_name(declaration: &str) -> &str {
+    let declaration = declaration.split(['=', '[']).next().unwrap_or_default();
+    declaration.split_whitespace().last().unwrap_or_default()
 }
 
 pub(crate) mod parse;
 
 #[cfg(test)]
 mod test_parse_global {
-    use crate::parser::global::{Global, parse::parse_global};
+    use crate::parser::{global::{Global, parse::parse_global}, span::Span};
 
     #[test]
     fn trivial_exaample() {
         let input = ".global hello;";
-        let expected = Ok(("", Global { raw_string: "hello" }));
-        assert_eq!(parse_global(input), expected)
+        let expected = Ok(("", Global { span: Span::new(0, 14), raw_string: "hello", comments: Default::default() }));
+        assert_eq!(parse_global(input)(input), expected)
+    }
+
+    #[test]
+    fn name() {
+        for (input, name) in [
+            (".global .u32 x;", "x"),
+            (".global .align 8 .b8 buffer[16];", "buffer"),
+            (".global .align 4 .u32 table[2] = {1, 2};", "table"),
+            (".global .f32 y=1.5;", "y"),
+            (".global .u32 a, b;", "a"),
+        ] {
+            assert_eq!(parse_global(input)(input).unwrap().1.name(), name);
+        }
+    }
+
+    #[test]
+    fn names() {
+        for (input, names) in [
+            (".global .u32 x;", &["x"][..]),
+            (".global .u32 a, b;", &["a", "b"]),
+            (".global .align 4 .u32 t[2] = {1, 2}, u, v[1]={3};", &["t", "u", "v"]),
+        ] {
+            assert!(parse_global(input)(input).unwrap().1.names().eq(names.iter().copied()));
+        }
+    }
+
+    #[test]
+    fn initializer() {
+        for (input, initializer) in [
+            (".global .u32 x;", None),
+            (".global .align 4 .u32 table[2] = {1, 2};", Some("{1, 2}")),
+            (".global .f32 y=1.5;", Some("1.5")),
+        ] {
+            assert_eq!(parse_global(input)(input).unwrap().1.initializer(), initializer);
+        }
     }
 }
@@ -1,17 +1,30 @@
 use nom::{
-    bytes::complete::{tag, take_while1},
+    bytes::complete::take_while1,
     character::complete::{char, space1},
+    combinator::{consumed, map},
+    error::context,
     sequence::delimited,
-    IResult, Parser,
+    Parser,
+};
+
+use crate::parser::{
+    comment::Comments,
+    error::{parse::{expect, token}, Expected, PResult},
+    span::Span,
 };
 
 use super::Global;
 
-pub(crate) fn parse_global(input: &str) -> IResult<&str, Global> {
-    delimited(
-        tag(".global").and(space1),
-        take_while1(|c: char| c != ';')
-        .map(|raw_string| Global { raw_string }),
-        char(';'),
-    )(input)
+pub(crate) fn parse_global<'a>(source: &'a str) -> impl FnMut(&'a str) -> PResult<'a, Global<'a>> {
+    map(
+        context(
+            "global variable",
+            consumed(delimited(
+                token(".global").and(space1),
+                expect(Expected::Description("a declaration"), take_while1(|c: char| c != ';')),
+                char(';'),
+            )),
+        ),
+        move |(text, raw_string)| Global { span: Span::of(source, text), raw_string, comments: Comments::default() },
+    )
 }
@@ -0,0 +1,529 @@
+use super::{function::body::Predicate, span::Span};
+
+pub(crate) mod parse;
+
+/// A PTX instruction whose opcode is known, e.g. `@%p1 ld.param.u64 %rd1, [x];`.
+///
+/// Modifiers and operands are kept as source text and parsed on demand,
+/// so no allocation is needed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Instruction<'a> {
+    span: Span,
+    predicate: Option<Predicate<'a>>,
+    opcode: Opcode,
+    modifiers: &'a str,
+    operands: &'a str,
+}
+
+/// A modifier following the opcode, without its leading `.`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum Modifier<'a> {
+    Type(Type),
+    /// A state space and its qualifier, like `shared` and `cluster` in
+    /// `shared::cluster`.
+    StateSpace(StateSpace, Option<&'a str>),
+    Vector(u8),
+    Known(&'a str),
+    Unknown(&'a str),
+}
+
+/// An instruction operand.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum Operand<'a> {
+    /// A register, including special registers such as `%tid.x`.
+    Register(&'a str),
+    /// A negated predicate register, e.g. `!%p1`.
+    Negated(&'a str),
+    /// The two destinations of `setp`, e.g. `%p1|%p2`.
+    PredicatePair(&'a str, &'a str),
+    Immediate(&'a str),
+    /// A variable, parameter, function or label name.
+    Symbol(&'a str),
+    Address(Address<'a>),
+    /// A braced vector of operands, e.g. `{%rd1, %rd2}`.
+    Vector(OperandList<'a>),
+    /// A parenthesized list of operands, as in `call (retval0), f, (param0);`.
+    List(OperandList<'a>),
+    Unknown(&'a str),
+}
+
+/// A memory operand, e.g. `[%rd1+8]`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Address<'a> {
+    base: &'a str,
+    offset: Option<&'a str>,
+}
+
+/// The comma-separated operands inside a vector or list operand.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct OperandList<'a> {
+    raw_string: &'a str,
+}
+
+/// Iterates over the modifiers of an opcode, e.g. `param`, `u64` in `ld.param.u64`.
+#[derive(Debug, Clone)]
+pub struct Modifiers<'a> {
+    rest: &'a str,
+}
+
+/// Iterates over comma-separated operands.
+#[derive(Debug, Clone)]
+pub struct Operands<'a> {
+    rest: &'a str,
+}
+
+/// Fundamental and packed types.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Type {
+    S8, S16, S32, S64,
+    U8, U16, U32, U64,
+    B8, B16, B32, B64, B128,
+    F16, F16x2, Bf16, Bf16x2, Tf32, F32, F64,
+    E4m3, E5m2, E4m3x2, E5m2x2,
+    Pred,
+}
+
+/// State spaces, as used by memory instructions and variable declarations.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum StateSpace {
+    Reg,
+    Sreg,
+    Const,
+    Global,
+    Local,
+    Param,
+    Shared,
+    Tex,
+}
+
+macro_rules! opcodes {
+    ($($variant:ident => $name:literal,)*) => {
+        /// The known PTX opcodes.
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+        #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+        pub enum Opcode {
+            $($variant,)*
+        }
+
+        impl Opcode {
+            pub const ALL: &'static [Opcode] = &[$(Opcode::$variant,)*];
+
+            pub fn from_name(name: &str) -> Option<Self> {
+                match name {
+                    $($name => Some(Opcode::$variant),)*
+                    _ => None,
+                }
+            }
+
+            pub fn name(&self) -> &'static str {
+                match self {
+                    $(Opcode::$variant => $name,)*
+                }
+            }
+        }
+    };
+}
+
+opcodes! {
+    Abs => "abs",
+    Activemask => "activemask",
+    Add => "add",
+    Addc => "addc",
+    Alloca => "alloca",
+    And => "and",
+    Applypriority => "applypriority",
+    Atom => "atom",
+    Bar => "bar",
+    Barrier => "barrier",
+    Bfe => "bfe",
+    Bfi => "bfi",
+    Bfind => "bfind",
+    Bmsk => "bmsk",
+    Bra => "bra",
+    Brev => "brev",
+    Brkpt => "brkpt",
+    Brx => "brx",
+    Call => "call",
+    Clz => "clz",
+    Cnot => "cnot",
+    Copysign => "copysign",
+    Cos => "cos",
+    Cp => "cp",
+    Createpolicy => "createpolicy",
+    Cvt => "cvt",
+    Cvta => "cvta",
+    Discard => "discard",
+    Div => "div",
+    Dp2a => "dp2a",
+    Dp4a => "dp4a",
+    Elect => "elect",
+    Ex2 => "ex2",
+    Exit => "exit",
+    Fence => "fence",
+    Fma => "fma",
+    Fns => "fns",
+    Getctarank => "getctarank",
+    Griddepcontrol => "griddepcontrol",
+    Isspacep => "isspacep",
+    Istypep => "istypep",
+    Ld => "ld",
+    Ldmatrix => "ldmatrix",
+    Ldu => "ldu",
+    Lg2 => "lg2",
+    Lop3 => "lop3",
+    Mad => "mad",
+    Mad24 => "mad24",
+    Madc => "madc",
+    Mapa => "mapa",
+    Match => "match",
+    Max => "max",
+    Mbarrier => "mbarrier",
+    Membar => "membar",
+    Min => "min",
+    Mma => "mma",
+    Mov => "mov",
+    Movmatrix => "movmatrix",
+    Mul => "mul",
+    Mul24 => "mul24",
+    Multimem => "multimem",
+    Nanosleep => "nanosleep",
+    Neg => "neg",
+    Not => "not",
+    Or => "or",
+    Pmevent => "pmevent",
+    Popc => "popc",
+    Prefetch => "prefetch",
+    Prefetchu => "prefetchu",
+    Prmt => "prmt",
+    Rcp => "rcp",
+    Red => "red",
+    Redux => "redux",
+    Rem => "rem",
+    Ret => "ret",
+    Rsqrt => "rsqrt",
+    Sad => "sad",
+    Selp => "selp",
+    Set => "set",
+    Setmaxnreg => "setmaxnreg",
+    Setp => "setp",
+    Shf => "shf",
+    Shfl => "shfl",
+    Shl => "shl",
+    Shr => "shr",
+    Sin => "sin",
+    Slct => "slct",
+    Sqrt => "sqrt",
+    St => "st",
+    Stackrestore => "stackrestore",
+    Stacksave => "stacksave",
+    Stmatrix => "stmatrix",
+    Sub => "sub",
+    Subc => "subc",
+    Suld => "suld",
+    Suq => "suq",
+    Sured => "sured",
+    Sust => "sust",
+    Szext => "szext",
+    Tanh => "tanh",
+    Tensormap => "tensormap",
+    Testp => "testp",
+    Tex => "tex",
+    Tld4 => "tld4",
+    Trap => "trap",
+    Txq => "txq",
+    Vabsdiff => "vabsdiff",
+    Vabsdiff2 => "vabsdiff2",
+    Vabsdiff4 => "vabsdiff4",
+    Vadd => "vadd",
+    Vadd2 => "vadd2",
+    Vadd4 => "vadd4",
+    Vavrg2 => "vavrg2",
+    Vavrg4 => "vavrg4",
+    Vmad => "vmad",
+    Vmax => "vmax",
+    Vmax2 => "vmax2",
+    Vmax4 => "vmax4",
+    Vmin => "vmin",
+    Vmin2 => "vmin2",
+    Vmin4 => "vmin4",
+    Vote => "vote",
+    Vset => "vset",
+    Vset2 => "vset2",
+    Vset4 => "vset4",
+    Vshl => "vshl",
+    Vshr => "vshr",
+    Vsub => "vsub",
+    Vsub2 => "vsub2",
+    Vsub4 => "vsub4",
+    Wgmma => "wgmma",
+    Wmma => "wmma",
+    Xor => "xor",
+}
+
+/// Modifiers that are neither types, state spaces nor vector widths.
+const KNOWN_MODIFIERS: &[&str] = &[
+    // rounding
+    "rn", "rz", "rm", "rp", "rna", "rs", "rni", "rzi", "rmi", "rpi",
+    // floating point
+    "ftz", "sat", "approx", "full", "relu", "nan", "xorsign", "abs", "oob", "satfinite",
+    // integer arithmetic
+    "lo", "hi", "wide", "cc", "shiftamt", "clamp", "wrap", "l", "r",
+    // comparisons
+    "eq", "ne", "lt", "le", "gt", "ge", "ls", "hs",
+    "equ", "neu", "ltu", "leu", "gtu", "geu", "num",
+    // boolean and atomic operations
+    "and", "or", "xor", "not", "popc", "add", "inc", "dec", "min", "max", "exch", "cas",
+    // control flow
+    "uni", "to",
+    // memory consistency and scope
+    "volatile", "relaxed", "acquire", "release", "acq_rel", "sc", "weak", "mmio",
+    "cta", "cluster", "gpu", "sys", "proxy", "async", "alias",
+    // caching
+    "ca", "cg", "cs", "lu", "cv", "wb", "wt", "nc",
+    // warp-level operations
+    "all", "any", "ballot", "sync", "aligned", "up", "down", "bfly", "idx",
+    "arrive", "red",
+    // matrix operations
+    "row", "col", "trans", "x1", "x2", "x4", "load", "store", "a", "b", "c", "d",
+    // textures and surfaces
+    "1d", "2d", "3d", "a1d", "a2d", "cube", "acube", "2dms", "a2dms", "trap", "zero",
+    // byte permutes
+    "f4e", "b4e", "rc8", "ecl", "ecr", "rc16",
+    // bulk and asynchronous copies
+    "bulk", "tensor", "tile", "im2col", "commit_group", "wait_group", "wait_all",
+    "mbarrier", "complete_tx", "expect_tx", "bytes", "test_wait", "try_wait", "parity",
+    "init", "inval", "pending_count", "noinc",
+];
+
+impl<'a> Instruction<'a> {
+    pub fn span(&self) -> Span {
+        self.span
+    }
+
+    pub fn predicate(&self) -> Option<&Predicate<'a>> {
+        self.predicate.as_ref()
+    }
+
+    pub fn opcode(&self) -> Opcode {
+        self.opcode
+    }
+
+    pub fn modifiers(&self) -> Modifiers<'a> {
+        Modifiers { rest: self.modifiers }
+    }
+
+    pub fn operands(&self) -> Operands<'a> {
+        Operands::new(self.operands)
+    }
+
+    /// The modifiers as written, e.g. `.param.u64`.
+    pub fn raw_modifiers(&self) -> &'a str {
+        self.modifiers
+    }
+
+    pub fn raw_operands(&self) -> &'a str {
+        self.operands
+    }
+
+    /// Whether every modifier and operand was recognized.
+    pub fn is_fully_typed(&self) -> bool {
+        self.modifiers().all(|modifier| !matches!(modifier, Modifier::Unknown(_)))
+            && self.operands().all(|operand| operand.is_fully_typed())
+    }
+}
+
+impl<'a> Modifier<'a> {
+    pub fn new(modifier: &'a str) -> Self {
+        if let Some(ty) = Type::from_name(modifier) {
+            return Modifier::Type(ty)
+        }
+        let (space, qualifier) = modifier.split_once("::").unwrap_or((modifier, ""));
+        if let Some(space) = StateSpace::from_name(space) {
+            return Modifier::StateSpace(space, Some(qualifier).filter(|qualifier| !qualifier.is_empty()))
+        }
+        match modifier {
+            "v2" => Modifier::Vector(2),
+            "v4" => Modifier::Vector(4),
+            "v8" => Modifier::Vector(8),
+            _ if KNOWN_MODIFIERS.contains(&modifier) => Modifier::Known(modifier),
+            _ if matches!(space, "L1" | "L2") && !qualifier.is_empty() => Modifier::Known(modifier),
+            _ if is_matrix_shape(modifier) => Modifier::Known(modifier),
+            _ => Modifier::Unknown(modifier),
+        }
+    }
+}
+
+/// Matches shapes such as `m16n8k16` and `m8n8`.
+fn is_matrix_shape(modifier: &str) -> bool {
+    let mut parts = modifier.split(|c: char| c.is_ascii_digit()).filter(|part| !part.is_empty());
+    modifier.starts_with('m')
+        && modifier.ends_with(|c: char| c.is_ascii_digit())
+        && parts.all(|part| matches!(part, "m" | "n" | "k"))
+}
+
+impl<'a> Operand<'a> {
+    /// Parses a single operand.
+    pub fn new(operand: &'a str) -> Self {
+        parse::parse_operand(operand)
+    }
+
+    pub fn is_fully_typed(&self) -> bool {
+        match self {
+            Operand::Unknown(_) => false,
+            Operand::Vector(list) | Operand::List(list) => list.operands().all(|operand| operand.is_fully_typed()),
+            _ => true,
+        }
+    }
+}
+
+impl<'a> Address<'a> {
+    /// The register or symbol being offset.
+    pub fn base(&self) -> &'a str {
+        self.base
+    }
+
+    /// The offset, including its sign if negative, e.g. `8` or `-4`.
+    pub fn offset(&self) -> Option<&'a str> {
+        self.offset
+    }
+}
+
+impl<'a> OperandList<'a> {
+    pub fn operands(&self) -> Operands<'a> {
+        Operands::new(self.raw_string)
+    }
+
+    pub fn raw_string(&self) -> &'a str {
+        self.raw_string
+    }
+}
+
+impl<'a> Operands<'a> {
+    pub(crate) fn new(raw_string: &'a str) -> Self {
+        Self { rest: raw_string.trim() }
+    }
+}
+
+impl<'a> Iterator for Modifiers<'a> {
+    type Item = Modifier<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let rest = self.rest.strip_prefix('.')?;
+        let end = rest.find('.').unwrap_or(rest.len());
+        let (modifier, rest) = rest.split_at(end);
+        self.rest = rest;
+        Some(Modifier::new(modifier))
+    }
+}
+
+impl<'a> Iterator for Operands<'a> {
+    type Item = Operand<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.rest.is_empty() {
+            return None
+        }
+        let mut depth = 0usize;
+        let end = self.rest
+            .char_indices()
+            .find(|&(_, c)| {
+                match c {
+                    '[' | '{' | '(' => depth += 1,
+                    ']' | '}' | ')' => depth = depth.saturating_sub(1),
+                    ',' if depth == 0 => return true,
+                    _ => (),
+                }
+                false
+            })
+            .map_or(self.rest.len(), |(i, _)| i);
+        let operand = self.rest[..end].trim();
+        self.rest = self.rest[end..].strip_prefix(',').unwrap_or("").trim_start();
+        Some(Operand::new(operand))
+    }
+}
+
+macro_rules! names {
+    ($ty:ident { $($variant:ident => $name:literal,)* }) => {
+        impl $ty {
+            pub fn from_name(name: &str) -> Option<Self> {
+                match name {
+                    $($name => Some($ty::$variant),)*
+                    _ => None,
+                }
+            }
+
+            pub fn name(&self) -> &'static str {
+                match self {
+                    $($ty::$variant => $name,)*
+                }
+            }
+        }
+    };
+}
+
+names!(Type {
+    S8 => "s8", S16 => "s16", S32 => "s32", S64 => "s64",
+    U8 => "u8", U16 => "u16", U32 => "u32", U64 => "u64",
+    B8 => "b8", B16 => "b16", B32 => "b32", B64 => "b64", B128 => "b128",
+    F16 => "f16", F16x2 => "f16x2", Bf16 => "bf16", Bf16x2 => "bf16x2", Tf32 => "tf32",
+    F32 => "f32", F64 => "f64",
+    E4m3 => "e4m3", E5m2 => "e5m2", E4m3x2 => "e4m3x2", E5m2x2 => "e5m2x2",
+    Pred => "pred",
+});
+
+names!(StateSpace {
+    Reg => "reg",
+    Sreg => "sreg",
+    Const => "const",
+    Global => "global",
+    Local => "local",
+    Param => "param",
+    Shared => "shared",
+    Tex => "tex",
+});
+
+impl Type {
+    /// The size of a value of this type in bits. Predicates count as one bit.
+    pub fn bits(&self) -> u32 {
+        match self {
+            Type::Pred => 1,
+            Type::S8 | Type::U8 | Type::B8 | Type::E4m3 | Type::E5m2 => 8,
+            Type::S16 | Type::U16 | Type::B16 | Type::F16 | Type::Bf16
+            | Type::E4m3x2 | Type::E5m2x2 => 16,
+            Type::S32 | Type::U32 | Type::B32 | Type::F32 | Type::F16x2
+            | Type::Bf16x2 | Type::Tf32 => 32,
+            Type::S64 | Type::U64 | Type::B64 | Type::F64 => 64,
+            Type::B128 => 128,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_modifiers {
+    use super::{Modifier, Opcode, StateSpace, Type};
+
+    #[test]
+    fn opcode_names_round_trip() {
+        for opcode in Opcode::ALL {
+            assert_eq!(Opcode::from_name(opcode.name()), Some(*opcode));
+        }
+        assert_eq!(Opcode::from_name("frobnicate"), None);
+    }
+
+    #[test]
+    fn classify() {
+        assert_eq!(Modifier::new("u64"), Modifier::Type(Type::U64));
+        assert_eq!(Modifier::new("param"), Modifier::StateSpace(StateSpace::Param, None));
+        assert_eq!(Modifier::new("shared::cta"), Modifier::StateSpace(StateSpace::Shared, Some("cta")));
+        assert_eq!(Modifier::new("v2"), Modifier::Vector(2));
+        assert_eq!(Modifier::new("wide"), Modifier::Known("wide"));
+        assert_eq!(Modifier::new("L2::evict_last"), Modifier::Known("L2::evict_last"));
+        assert_eq!(Modifier::new("m16n8k16"), Modifier::Known("m16n8k16"));
+        assert_eq!(Modifier::new("bogus"), Modifier::Unknown("bogus"));
+    }
+}
@@ -0,0 +1,240 @@
+use nom::{
+    bytes::complete::{take_while, take_while1},
+    character::complete::{char, multispace0, multispace1},
+    combinator::{opt, rest, verify},
+    sequence::{preceded, terminated},
+    Parser,
+};
+
+use crate::parser::{
+    error::{parse::expect, Error, Expected, PResult},
+    function::body::Predicate,
+    span::Span,
+};
+
+use super::{Address, Instruction, Modifier, Opcode, Operand, OperandList};
+
+fn parse_predicate(input: &str) -> PResult<'_, Predicate<'_>> {
+    preceded(
+        char('@'),
+        opt(char('!')).and(take_while1(|c: char| !c.is_whitespace())),
+    )
+    .map(|(negation, register)| match negation {
+        None => Predicate::True(register),
+        Some(_) => Predicate::False(register),
+    })
+    .parse(input)
+}
+
+/// Parses a statement whose opcode is known, without its terminating `;`.
+pub(crate) fn parse_instruction<'a>(source: &'a str) -> impl FnMut(&'a str) -> PResult<'a, Instruction<'a>> {
+    move |input| {
+        let (start, predicate) = opt(terminated(parse_predicate, multispace1))(input)?;
+        let (remaining, opcode) = expect(
+            Expected::Description("a known opcode"),
+            take_while1(|c: char| c.is_ascii_alphanumeric() || c == '_')
+                .map(Opcode::from_name),
+        )(start)?;
+        let Some(opcode) = opcode else {
+            return Err(nom::Err::Error(Error::expected(start, Expected::Description("a known opcode"))))
+        };
+        let (remaining, modifiers) = verify(
+            take_while(|c: char| !c.is_whitespace()),
+            |modifiers: &str| modifiers.is_empty() || modifiers.starts_with('.'),
+        )(remaining)?;
+        let (remaining, operands) = preceded(multispace0, rest)(remaining)?;
+        Ok((
+            remaining,
+            Instruction {
+                span: Span::of(source, input.trim_end()),
+                predicate,
+                opcode,
+                modifiers,
+                operands: operands.trim_end(),
+            },
+        ))
+    }
+}
+
+/// Fails on the first unknown modifier or operand of `instruction`.
+pub(crate) fn check_instruction<'a>(instruction: &Instruction<'a>) -> Result<(), nom::Err<Error<'a>>> {
+    let mut modifiers = instruction.modifiers;
+    while let Some(rest) = modifiers.strip_prefix('.') {
+        let end = rest.find('.').unwrap_or(rest.len());
+        if let Modifier::Unknown(modifier) = Modifier::new(&rest[..end]) {
+            return Err(nom::Err::Error(Error::expected(modifier, Expected::Description("a known modifier"))))
+        }
+        modifiers = &rest[end..];
+    }
+    check_operands(instruction.operands())
+}
+
+fn check_operands<'a>(mut operands: super::Operands<'a>) -> Result<(), nom::Err<Error<'a>>> {
+    operands.try_for_each(|operand| match operand {
+        Operand::Unknown(operand) => {
+            Err(nom::Err::Error(Error::expected(operand, Expected::Description("an operand"))))
+        }
+        Operand::Vector(list) | Operand::List(list) => check_operands(list.operands()),
+        _ => Ok(()),
+    })
+}
+
+fn is_identifier_start(c: char) -> bool {
+    c.is_ascii_alphabetic() || c == '_' || c == '$'
+}
+
+pub(crate) fn is_identifier(name: &str) -> bool {
+    name.starts_with(is_identifier_start)
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '$')
+}
+
+fn is_register(register: &str) -> bool {
+    register.strip_prefix('%').is_some_and(|name| {
+        !name.is_empty()
+            && name.split('.').all(|part| {
+                !part.is_empty() && part.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '$')
+            })
+    })
+}
+
+pub(crate) fn is_immediate(immediate: &str) -> bool {
+    let digits = immediate.strip_prefix('-').unwrap_or(immediate);
+    digits.starts_with(|c: char| c.is_ascii_digit())
+        && digits.chars().all(|c| c.is_ascii_alphanumeric() || c == '.')
+}
+
+fn inner(operand: &str, open: char, close: char) -> Option<&str> {
+    operand.strip_prefix(open)?.strip_suffix(close)
+}
+
+pub(crate) fn parse_operand(operand: &str) -> Operand<'_> {
+    if is_register(operand) {
+        Operand::Register(operand)
+    } else if let Some(register) = operand.strip_prefix('!').filter(|register| is_register(register)) {
+        Operand::Negated(register)
+    } else if let Some((first, second)) = operand.split_once('|').filter(|(first, second)| {
+        is_register(first.trim_end()) && is_register(second.trim_start())
+    }) {
+        Operand::PredicatePair(first.trim_end(), second.trim_start())
+    } else if is_immediate(operand) {
+        Operand::Immediate(operand)
+    } else if is_identifier(operand) {
+        Operand::Symbol(operand)
+    } else if let Some(address) = inner(operand, '[', ']').and_then(parse_address) {
+        Operand::Address(address)
+    } else if let Some(raw_string) = inner(operand, '{', '}') {
+        Operand::Vector(OperandList { raw_string })
+    } else if let Some(raw_string) = inner(operand, '(', ')') {
+        Operand::List(OperandList { raw_string })
+    } else {
+        Operand::Unknown(operand)
+    }
+}
+
+fn parse_address(address: &str) -> Option<Address<'_>> {
+    let address = address.trim();
+    let (base, offset) = match address.split_once('+') {
+        Some((base, offset)) => (base.trim_end(), Some(offset.trim_start())),
+        None => (address, None),
+    };
+    let valid_base = is_register(base) || is_identifier(base) || is_immediate(base);
+    let valid_offset = match offset {
+        Some(offset) => is_immediate(offset),
+        None => true,
+    };
+    (valid_base && valid_offset).then_some(Address { base, offset })
+}
+
+#[cfg(test)]
+mod test_parse_instruction {
+    use crate::parser::{function::body::Predicate, span::Span};
+
+    use super::{check_instruction, parse_instruction, parse_operand, Address, Modifier, Opcode, Operand};
+    use crate::parser::instruction::{StateSpace, Type};
+
+    #[test]
+    fn load() {
+        let input = "ld.param.v2.b64 \t{%rd11, %rd12}, [retval0+0]";
+        let (rest, instruction) = parse_instruction(input)(input).unwrap();
+        assert_eq!(rest, "");
+        assert_eq!(instruction.span(), Span::new(0, input.len()));
+        assert_eq!(instruction.opcode(), Opcode::Ld);
+        assert!(instruction.modifiers().eq([
+            Modifier::StateSpace(StateSpace::Param, None),
+            Modifier::Vector(2),
+            Modifier::Type(Type::B64),
+        ]));
+        let mut operands = instruction.operands();
+        let Some(Operand::Vector(vector)) = operands.next() else { panic!() };
+        assert!(vector.operands().eq([Operand::Register("%rd11"), Operand::Register("%rd12")]));
+        assert_eq!(
+            operands.next(),
+            Some(Operand::Address(Address { base: "retval0", offset: Some("0") })),
+        );
+        assert_eq!(operands.next(), None);
+        assert!(instruction.is_fully_typed());
+    }
+
+    #[test]
+    fn predicated() {
+        let input = "@!%p1 st.global.f32 [%rd4], %f3";
+        let (_, instruction) = parse_instruction(input)(input).unwrap();
+        assert_eq!(instruction.predicate(), Some(&Predicate::False("%p1")));
+        assert_eq!(instruction.opcode(), Opcode::St);
+    }
+
+    #[test]
+    fn call() {
+        let input = "call.uni (retval0), \nfoo, \n(\nparam0, \nparam1\n)";
+        let (_, instruction) = parse_instruction(input)(input).unwrap();
+        assert_eq!(instruction.opcode(), Opcode::Call);
+        let mut operands = instruction.operands();
+        assert!(matches!(operands.next(), Some(Operand::List(_))));
+        assert_eq!(operands.next(), Some(Operand::Symbol("foo")));
+        let Some(Operand::List(arguments)) = operands.next() else { panic!() };
+        assert!(arguments.operands().eq([Operand::Symbol("param0"), Operand::Symbol("param1")]));
+    }
+
+    #[test]
+    fn no_operands() {
+        let input = "bar.sync";
+        let (_, instruction) = parse_instruction(input)(input).unwrap();
+        assert_eq!(instruction.opcode(), Opcode::Bar);
+        assert_eq!(instruction.operands().next(), None);
+    }
+
+    #[test]
+    fn unknown_opcode() {
+        let input = "frobnicate.u32 %r1";
+        assert!(parse_instruction(input)(input).is_err());
+    }
+
+    #[test]
+    fn operands() {
+        assert_eq!(parse_operand("%tid.x"), Operand::Register("%tid.x"));
+        assert_eq!(parse_operand("-1"), Operand::Immediate("-1"));
+        assert_eq!(parse_operand("0f3F800000"), Operand::Immediate("0f3F800000"));
+        assert_eq!(parse_operand("$L__BB0_2"), Operand::Symbol("$L__BB0_2"));
+        assert_eq!(parse_operand("%p1|%p2"), Operand::PredicatePair("%p1", "%p2"));
+        assert_eq!(
+            parse_operand("[%rd1+-4]"),
+            Operand::Address(Address { base: "%rd1", offset: Some("-4") }),
+        );
+        assert_eq!(parse_operand("[%rd1]"), Operand::Address(Address { base: "%rd1", offset: None }));
+        assert_eq!(parse_operand("a b"), Operand::Unknown("a b"));
+    }
+
+    #[test]
+    fn check() {
+        let input = "add.bogus.u32 %r1, %r2, ?";
+        let (_, instruction) = parse_instruction(input)(input).unwrap();
+        assert!(!instruction.is_fully_typed());
+        let Err(nom::Err::Error(error)) = check_instruction(&instruction) else { panic!() };
+        assert_eq!(error.input, "bogus");
+
+        let input = "add.u32 %r1, %r2, ?";
+        let (_, instruction) = parse_instruction(input)(input).unwrap();
+        let Err(nom::Err::Error(error)) = check_instruction(&instruction) else { panic!() };
+        assert_eq!(error.input, "?");
+    }
+}
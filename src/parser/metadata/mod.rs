@@ -0,0 +1,182 @@
+use super::{comment::parse::many1_comments_or_whitespace, LeadingComments, PtxParser};
+
+/// Information about a module that is not part of its PTX semantics,
+/// recovered from the header comments and naming conventions.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ModuleMetadata<'a> {
+    source: &'a str,
+    producer: Producer,
+    compiler_version: Option<&'a str>,
+    build_id: Option<&'a str>,
+}
+
+/// The tool that most likely generated a module.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Producer {
+    /// The LLVM NVPTX back-end driven by rustc.
+    Rustc,
+    /// The LLVM NVPTX back-end driven by clang.
+    Clang,
+    /// The LLVM NVPTX back-end with an unknown front-end.
+    Llvm,
+    /// nvcc or another NVVM-based compiler.
+    Nvvm,
+    /// No known generator header.
+    HandWritten,
+}
+
+/// Iterates over the names in `// .globl name` comments.
+#[derive(Debug, Clone)]
+pub struct GloblHints<'a> {
+    lines: core::str::Lines<'a>,
+}
+
+impl<'a> ModuleMetadata<'a> {
+    pub fn new(source: &'a str) -> Self {
+        let header = many1_comments_or_whitespace(source)
+            .map_or("", |(rest, _)| &source[..source.len() - rest.len()]);
+        let mut metadata = Self {
+            source,
+            producer: Producer::HandWritten,
+            compiler_version: None,
+            build_id: None,
+        };
+        for comment in LeadingComments::new(header) {
+            let text = comment.text().trim();
+            if text.starts_with("Generated by LLVM NVPTX Back-End") {
+                metadata.producer = llvm_front_end(source);
+            } else if text.starts_with("Generated by NVIDIA NVVM Compiler") {
+                metadata.producer = Producer::Nvvm;
+            } else if let Some(build_id) = text.strip_prefix("Compiler Build ID:") {
+                metadata.build_id = Some(build_id.trim());
+            } else if let Some(release) = text.strip_prefix("Cuda compilation tools, release") {
+                // e.g. "11.8, V11.8.89"
+                let version = release.rsplit(", V").next().unwrap_or(release);
+                metadata.compiler_version = Some(version.trim());
+            } else if let Some(nvvm) = text.strip_prefix("Based on NVVM") {
+                metadata.compiler_version.get_or_insert(nvvm.trim());
+            }
+        }
+        metadata
+    }
+
+    pub fn producer(&self) -> Producer {
+        self.producer
+    }
+
+    /// The compiler version from the header, e.g. `11.8.89` for nvcc.
+    pub fn compiler_version(&self) -> Option<&'a str> {
+        self.compiler_version
+    }
+
+    /// The nvcc build id, e.g. `CL-31833905`.
+    pub fn build_id(&self) -> Option<&'a str> {
+        self.build_id
+    }
+
+    /// The symbols that the compiler marked as `.globl` in comments.
+    pub fn globl_hints(&self) -> GloblHints<'a> {
+        GloblHints { lines: self.source.lines() }
+    }
+}
+
+impl<'a> PtxParser<'a> {
+    pub fn metadata(&self) -> ModuleMetadata<'a> {
+        ModuleMetadata::new(self.source)
+    }
+}
+
+impl<'a> Iterator for GloblHints<'a> {
+    type Item = &'a str;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.lines.find_map(|line| {
+            let name = line.trim().strip_prefix("//")?.trim_start().strip_prefix(".globl")?;
+            name.starts_with(char::is_whitespace).then(|| name.trim())
+        })
+    }
+}
+
+/// Tells rustc and clang output apart by their symbol mangling.
+fn llvm_front_end(source: &str) -> Producer {
+    let mut producer = Producer::Llvm;
+    for symbol in source.split(|c: char| !(c.is_ascii_alphanumeric() || c == '_' || c == '$')) {
+        if is_rust_symbol(symbol) {
+            return Producer::Rustc
+        }
+        if symbol.starts_with("_Z") {
+            producer = Producer::Clang;
+        }
+    }
+    producer
+}
+
+/// Matches `rust_begin_unwind` and legacy mangled names ending in `17h<hash>E`.
+fn is_rust_symbol(symbol: &str) -> bool {
+    if symbol == "rust_begin_unwind" {
+        return true
+    }
+    let Some(hash) = symbol.strip_prefix("_ZN").and_then(|name| name.strip_suffix('E')) else {
+        return false
+    };
+    hash.len() > 19
+        && hash.is_char_boundary(hash.len() - 19)
+        && hash[hash.len() - 19..].strip_prefix("17h")
+            .is_some_and(|hash| hash.chars().all(|c| c.is_ascii_hexdigit()))
+}
+
+#[cfg(test)]
+mod test_metadata {
+    use crate::ptx_files::{a, kernel, _EXAMPLE_FILE};
+
+    use super::{ModuleMetadata, Producer};
+
+    #[test]
+    fn rustc() {
+        let metadata = ModuleMetadata::new(a::_PTX);
+        assert_eq!(metadata.producer(), Producer::Rustc);
+        assert_eq!(metadata.compiler_version(), None);
+    }
+
+    #[test]
+    fn llvm() {
+        let metadata = ModuleMetadata::new(kernel::_PTX);
+        assert_eq!(metadata.producer(), Producer::Llvm);
+        assert!(metadata.globl_hints().eq(["square_kernel"]));
+    }
+
+    #[test]
+    fn clang() {
+        let source = "//\n// Generated by LLVM NVPTX Back-End\n//\n\n.version 7.0\n.target sm_52\n\
+            .address_size 64\n\n\t// .globl\t_Z6squarePfi\n.visible .entry _Z6squarePfi()\n{\n\tret;\n}\n";
+        let metadata = ModuleMetadata::new(source);
+        assert_eq!(metadata.producer(), Producer::Clang);
+        assert!(metadata.globl_hints().eq(["_Z6squarePfi"]));
+    }
+
+    #[test]
+    fn nvvm() {
+        let source = "//
+// Generated by NVIDIA NVVM Compiler
+//
+// Compiler Build ID: CL-31833905
+// Cuda compilation tools, release 11.8, V11.8.89
+// Based on NVVM 7.0.1
+//
+
+.version 7.8
+.target sm_52
+.address_size 64
+";
+        let metadata = ModuleMetadata::new(source);
+        assert_eq!(metadata.producer(), Producer::Nvvm);
+        assert_eq!(metadata.compiler_version(), Some("11.8.89"));
+        assert_eq!(metadata.build_id(), Some("CL-31833905"));
+        assert_eq!(metadata.globl_hints().next(), None);
+    }
+
+    #[test]
+    fn hand_written() {
+        assert_eq!(ModuleMetadata::new(_EXAMPLE_FILE).producer(), Producer::HandWritten);
+    }
+}
@@ -1,33 +1,212 @@
-use nom::{bytes::complete::take_while1, character::complete::char, sequence::delimited, IResult};
+use nom::{
+    bytes::complete::take_while1,
+    character::complete::char,
+    combinator::opt,
+    sequence::{delimited, preceded},
+};
 
+#[cfg(feature = "std")]
+pub(crate) mod builder;
+#[cfg(feature = "std")]
+pub(crate) mod call_graph;
+#[cfg(feature = "std")]
+pub(crate) mod cfg;
 pub(crate) mod comment;
+#[cfg(feature = "std")]
+pub(crate) mod coverage;
+#[cfg(feature = "std")]
+pub(crate) mod cst;
+#[cfg(feature = "std")]
+pub(crate) mod dot;
+pub(crate) mod error;
+#[cfg(feature = "std")]
+pub(crate) mod format;
 pub(crate) mod function;
 pub(crate) mod global;
+pub(crate) mod instruction;
+pub(crate) mod metadata;
+#[cfg(feature = "std")]
+pub(crate) mod module;
+#[cfg(feature = "std")]
+pub mod owned;
 pub(crate) mod preamble;
+pub(crate) mod printer;
 pub(crate) mod ptx_file;
+#[cfg(feature = "serde")]
+pub(crate) mod serialize;
+pub(crate) mod span;
+#[cfg(feature = "std")]
+pub(crate) mod symbols;
+pub mod visit;
+#[cfg(feature = "std")]
+pub(crate) mod xref;
+
+use error::{Error, PResult};
 
-use preamble::Preamble;
-use function::Function;
-use global::Global;
+#[cfg(feature = "std")]
+pub use builder::{
+    Axis, BlockBuilder, BuildError, Compare, FunctionBuilder, Label, ModuleBuilder, Param, Reg,
+    Special, Value,
+};
+#[cfg(feature = "std")]
+pub use call_graph::{Call, CallGraph, CallKind};
+#[cfg(feature = "std")]
+pub use cfg::{
+    Analysis, BasicBlock, BlockId, Cfg, Definition, DefinitionId, Direction, DominatorTree, Edge, EdgeKind, Lattice,
+    Liveness, Loop, LoopId, Loops, Point, Pressure, ReachingDefinitions, RegisterPressure, Solution, Terminator,
+};
+#[cfg(feature = "std")]
+pub use coverage::{classify, Category, Counts, Coverage, FunctionCoverage, Typing};
+#[cfg(feature = "std")]
+pub use cst::{NodeKind, SyntaxElement, SyntaxNode, SyntaxToken, SyntaxTree, TokenKind};
+pub use comment::{Comment, Comments, LeadingComments};
+pub use error::{Context, Diagnostic, Expected, ParseError};
+#[cfg(feature = "std")]
+pub use dot::{call_graph_to_dot, cfg_to_dot};
+#[cfg(feature = "std")]
+pub use format::{format, FormatOptions};
+pub use function::body::{BodyLine, Directive, FunctionBody, FunctionCall, Goto, Operation, Predicate, Register, Statement};
+pub use function::{Function, FunctionSignature, Parameters, ReturnValue};
+pub use global::Global;
+pub use instruction::{Address, Instruction, Modifier, Modifiers, Opcode, Operand, OperandList, Operands, StateSpace, Type};
+pub use metadata::{GloblHints, ModuleMetadata, Producer};
+#[cfg(feature = "std")]
+pub use module::Module;
+pub use preamble::{AddressSize, Preamble, Target, Version};
+pub use ptx_file::FunctionOrGlobal;
+pub use span::{LineColumn, Span};
+#[cfg(feature = "std")]
+pub use symbols::{
+    Reference, Resolution, Scope, ScopeId, ScopeKind, Symbol, SymbolId, SymbolKind, SymbolTable, UseKind,
+};
+pub use visit::Visitor;
+#[cfg(feature = "std")]
+pub use xref::Xref;
+#[cfg(feature = "std")]
+pub use visit::VisitorMut;
+#[cfg(feature = "std")]
+pub use span::SourceMap;
 
 #[derive(Debug)]
 pub struct PtxParser<'a> {
+    source: &'a str,
     preamble: Preamble<'a>,
     body: Option<&'a str>,
+    options: ParseOptions,
+}
+
+/// Configures how strictly PTX source is parsed.
+///
+/// More options may be added, so start from [`ParseOptions::default`] or
+/// [`ParseOptions::strict`] rather than a struct literal.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct ParseOptions {
+    /// Rejects statements with an unknown directive, opcode, modifier or
+    /// operand instead of keeping them as `BodyLine::Operation` or
+    /// `BodyLine::Unknown`.
+    pub strict: bool,
+}
+
+impl ParseOptions {
+    /// The default options with [`strict`](Self::strict) parsing on.
+    pub fn strict() -> Self {
+        Self { strict: true, ..Self::default() }
+    }
 }
 
 impl<'a> PtxParser<'a> {
+    /// Parses the preamble of `source`; the rest is parsed lazily.
+    pub fn with_options(source: &'a str, options: ParseOptions) -> Result<Self, ParseError<'a>> {
+        let (body, preamble) = preceded(
+            opt(comment::parse::many1_comments_or_whitespace),
+            preamble::parse::parse_preamble(source),
+        )(source)
+        .map_err(|err| ParseError::from_nom(source, err))?;
+        Ok(PtxParser { source, preamble, body: Some(body), options })
+    }
+
     pub fn preamble(&self) -> &Preamble<'a> {
         &self.preamble
     }
+
+    /// The comments before `.version`, such as `// Generated by LLVM NVPTX Back-End`.
+    pub fn header_comments(&self) -> LeadingComments<'a> {
+        LeadingComments::new(self.header())
+    }
+
+    fn header(&self) -> &'a str {
+        &self.source[..self.preamble.span().start]
+    }
+
+    pub fn options(&self) -> ParseOptions {
+        self.options
+    }
 }
 
+/// A parsed module, which borrows from its source.
+///
+/// With the `serde` feature it implements `Serialize` but not
+/// `Deserialize`; load the JSON as an [`owned::PtxFile`] instead.
 #[cfg(feature = "std")]
 #[derive(Debug)]
 pub struct PtxFile<'a> {
+    source: &'a str,
     preamble: Preamble<'a>,
     functions: Vec<Function<'a>>,
     globals: Vec<Global<'a>>,
+    order: Vec<ItemKind>,
+}
+
+/// Which list the next module item of a [`PtxFile`] comes from.
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ItemKind {
+    Function,
+    Global,
+}
+
+/// A module item borrowed from a [`PtxFile`].
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum Item<'r, 'a> {
+    Function(&'r Function<'a>),
+    Global(&'r Global<'a>),
+}
+
+#[cfg(feature = "std")]
+impl<'a> PtxFile<'a> {
+    pub fn preamble(&self) -> &Preamble<'a> {
+        &self.preamble
+    }
+
+    /// The comments before `.version`.
+    pub fn header_comments(&self) -> LeadingComments<'a> {
+        LeadingComments::new(&self.source[..self.preamble.span().start])
+    }
+
+    pub(crate) fn source(&self) -> &'a str {
+        self.source
+    }
+
+    pub fn functions(&self) -> &[Function<'a>] {
+        &self.functions
+    }
+
+    pub fn globals(&self) -> &[Global<'a>] {
+        &self.globals
+    }
+
+    /// The functions and globals in source order.
+    pub fn items(&self) -> impl Iterator<Item = Item<'_, 'a>> {
+        let mut functions = self.functions.iter();
+        let mut globals = self.globals.iter();
+        self.order.iter().filter_map(move |kind| match kind {
+            ItemKind::Function => functions.next().map(Item::Function),
+            ItemKind::Global => globals.next().map(Item::Global),
+        })
+    }
 }
 
 fn is_special(c: char) -> bool {
@@ -35,19 +214,18 @@ fn is_special(c: char) -> bool {
     .contains(&c)
 }
 
-fn parse_name(input: &str) -> IResult<&str, &str> {
-    take_while1(|c: char| !c.is_whitespace() && !is_special(c))(input)
+fn parse_name(input: &str) -> PResult<'_, &str> {
+    error::parse::expect(
+        Expected::Description("a name"),
+        take_while1(|c: char| !c.is_whitespace() && !is_special(c)),
+    )(input)
 }
 
-fn parse_parenthesized_naive(input: &str) -> IResult<&str, &str> {
-    delimited(
-        char('('),
-        take_while1(|c: char| c != ')'),
-        char(')')
-    )(input)
+fn parse_parenthesized_balanced(input: &str) -> PResult<'_, &str> {
+    parse_balanced(b'(', b')')(input)
 }
 
-fn _parse_braced_naive(input: &str) -> IResult<&str, &str> {
+fn _parse_braced_naive(input: &str) -> PResult<'_, &str> {
     delimited(
         char('{'),
         take_while1(|c: char| c != '}'),
@@ -55,70 +233,186 @@ fn _parse_braced_naive(input: &str) -> IResult<&str, &str> {
     )(input)
 }
 
-fn parse_braced_balanced(input: &str) -> IResult<&str, &str> {
-    let mut chars = input.chars().enumerate();
-    let (mut depth, mut end) = match chars.next() {
-        Some((_, '{')) => (1, None),
-        _ => return Err(nom::Err::Error(
-            nom::error::Error::new(input, nom::error::ErrorKind::Char)
-        ))
-    };
+fn parse_braced_balanced(input: &str) -> PResult<'_, &str> {
+    parse_balanced(b'{', b'}')(input)
+}
+
+/// Matches `open`, then everything up to the `close` that balances it.
+///
+/// Delimiters inside `//` and `/* */` comments and inside `"..."` strings are
+/// not counted. The scan is done over bytes, so the returned slices stay on
+/// `char` boundaries even when comments contain non-ASCII text.
+/// A missing closing delimiter is a failure rather than a recoverable error.
+fn parse_balanced(open: u8, close: u8) -> impl Fn(&str) -> PResult<&str> {
+    move |input: &str| {
+        let bytes = input.as_bytes();
+        if bytes.first() != Some(&open) {
+            return Err(nom::Err::Error(
+                Error::expected(input, Expected::Char(open.into()))
+            ))
+        }
+        match find_balanced(bytes, open, close) {
+            Some(end) => Ok((&input[end + 1..], &input[1..end])),
+            None => Err(nom::Err::Failure(
+                Error::expected(input, Expected::Closing(close.into()))
+            )),
+        }
+    }
+}
 
-    for (i, c) in chars {
-        match c {
-            '{' => depth += 1,
-            '}' => {
+/// Returns the index of the `close` balancing the `open` at `bytes[0]`.
+fn find_balanced(bytes: &[u8], open: u8, close: u8) -> Option<usize> {
+    let mut depth = 1;
+    let mut i = 1;
+    while i < bytes.len() {
+        if let Some(next) = skip_comment_or_string(bytes, i)? {
+            i = next;
+            continue
+        }
+        match bytes[i] {
+            b if b == open => depth += 1,
+            b if b == close => {
                 depth -= 1;
                 if depth == 0 {
-                    end = Some(i);
-                    break;
+                    return Some(i)
                 }
             }
             _ => (),
         }
+        i += 1;
     }
-    if let Some(end) = end {
-        Ok((&input[end + 1..], &input[1..end]))
-    } else {
-        Err(nom::Err::Error(nom::error::Error::new(
-            input,
-            nom::error::ErrorKind::Eof,
-        )))
+    None
+}
+
+/// If a comment or string starts at `bytes[i]`, returns `Some(Some(j))` with
+/// `j` just past it, or `None` if it is never closed. Otherwise returns
+/// `Some(None)`. Line comments end before their newline.
+fn skip_comment_or_string(bytes: &[u8], i: usize) -> Option<Option<usize>> {
+    Some(match (bytes[i], bytes.get(i + 1)) {
+        (b'/', Some(b'/')) => Some(
+            bytes[i..].iter()
+            .position(|&b| b == b'\n')
+            .map_or(bytes.len(), |newline| i + newline)
+        ),
+        (b'/', Some(b'*')) => Some(
+            bytes[i + 2..].windows(2)
+            .position(|w| w == b"*/")
+            .map(|end| i + 2 + end + 2)?
+        ),
+        (b'"', _) => {
+            let mut j = i + 1;
+            loop {
+                match bytes.get(j)? {
+                    b'\\' => j += 2,
+                    b'"' => break Some(j + 1),
+                    _ => j += 1,
+                }
+            }
+        }
+        _ => None,
+    })
+}
+
+/// Directives that may start a module-level item.
+pub(crate) const MODULE_DIRECTIVES: &[&str] = &[
+    ".version", ".target", ".address_size",
+    ".visible", ".extern", ".weak", ".entry", ".func",
+    ".global", ".const", ".shared", ".local",
+    ".file", ".section",
+];
+
+/// Skips the item at the start of `input` after a parse error.
+///
+/// Returns the input just past the first `;` or `}` outside any braces,
+/// or just past a whole `{ ... }` block, or at the first line that starts
+/// with one of `directives`, whichever comes first. Comments and strings
+/// are skipped over. At least one byte is always consumed.
+pub(crate) fn recover<'a>(input: &'a str, directives: &[&str]) -> &'a str {
+    let bytes = input.as_bytes();
+    let mut i = 0;
+    while i < bytes.len() {
+        let next = match skip_comment_or_string(bytes, i) {
+            Some(Some(next)) => next,
+            None => bytes.len(),
+            Some(None) => match bytes[i] {
+                b';' | b'}' => return &input[i + 1..],
+                b'{' => return match find_balanced(&bytes[i..], b'{', b'}') {
+                    Some(end) => &input[i + end + 1..],
+                    None => &input[bytes.len()..],
+                },
+                b'\n' => {
+                    let line = input[i + 1..].trim_start();
+                    if directives.iter().any(|directive| line.starts_with(directive)) {
+                        return line
+                    }
+                    i + 1
+                }
+                _ => i + 1,
+            },
+        };
+        i = next;
     }
+    &input[bytes.len()..]
+}
+
+/// Splits `text` on commas outside brackets.
+pub(crate) fn split_top_level(text: &str) -> impl Iterator<Item = &str> {
+    let mut rest = Some(text);
+    core::iter::from_fn(move || {
+        let text = rest?;
+        let mut depth = 0usize;
+        for (index, c) in text.char_indices() {
+            match c {
+                '(' | '[' | '{' => depth += 1,
+                ')' | ']' | '}' => depth = depth.saturating_sub(1),
+                ',' if depth == 0 => {
+                    rest = Some(&text[index + 1..]);
+                    return Some(&text[..index])
+                }
+                _ => {}
+            }
+        }
+        rest = None;
+        Some(text)
+    })
 }
 
 #[cfg(test)]
 mod test_parse_parenthesized {
 
-    use super::parse_parenthesized_naive;
+    use super::parse_parenthesized_balanced;
 
     #[test]
     fn no_newline() {
         let input = "(hello)";
         let expected = Ok(("", "hello"));
-        assert_eq!(parse_parenthesized_naive(input), expected)
+        assert_eq!(parse_parenthesized_balanced(input), expected)
     }
 
     #[test]
     fn newline() {
         let input = "(hello\n)";
         let expected = Ok(("", "hello\n"));
-        assert_eq!(parse_parenthesized_naive(input), expected)
+        assert_eq!(parse_parenthesized_balanced(input), expected)
     }
 
     #[test]
     fn one_left_parenthesis() {
         let input = "(hello";
-        assert!(parse_parenthesized_naive(input).is_err())
+        assert!(parse_parenthesized_balanced(input).is_err())
     }
 
     #[test]
     fn two_left_one_right() {
         let input = "((hello)";
-        assert_eq!(
-            parse_parenthesized_naive(input),
-            Ok(("", "(hello")),
-        )
+        assert!(parse_parenthesized_balanced(input).is_err())
+    }
+
+    #[test]
+    fn nested_pair() {
+        let input = "(.param .align 16 .b8 (foo)) _bar";
+        let expected = Ok((" _bar", ".param .align 16 .b8 (foo)"));
+        assert_eq!(parse_parenthesized_balanced(input), expected)
     }
 }
 
@@ -195,4 +489,126 @@ mod test_parse_braced_balanced {
         let input = "{hello{world}";
         assert!(parse_braced_balanced(input).is_err())
     }
+
+    #[test]
+    fn brace_in_line_comment() {
+        let input = "{hello // }\n}world";
+        let expected = Ok(("world", "hello // }\n"));
+        assert_eq!(parse_braced_balanced(input), expected)
+    }
+
+    #[test]
+    fn brace_in_block_comment() {
+        let input = "{hello /* { */}world";
+        let expected = Ok(("world", "hello /* { */"));
+        assert_eq!(parse_braced_balanced(input), expected)
+    }
+
+    #[test]
+    fn brace_in_string() {
+        let input = "{.pragma \"}\\\"{\";}";
+        let expected = Ok(("", ".pragma \"}\\\"{\";"));
+        assert_eq!(parse_braced_balanced(input), expected)
+    }
+
+    #[test]
+    fn non_ascii_comment() {
+        let input = "{// héllo wörld\n ret;}tail";
+        let expected = Ok(("tail", "// héllo wörld\n ret;"));
+        assert_eq!(parse_braced_balanced(input), expected)
+    }
+
+    #[test]
+    fn unterminated_block_comment() {
+        let input = "{hello /* }";
+        assert!(parse_braced_balanced(input).is_err())
+    }
+}
+
+#[cfg(test)]
+mod test_recover {
+
+    use super::{recover, MODULE_DIRECTIVES};
+
+    #[test]
+    fn past_semicolon() {
+        assert_eq!(recover("foo bar; baz;", &[]), " baz;")
+    }
+
+    #[test]
+    fn past_block() {
+        assert_eq!(recover(".entry { a; { b; } }\nrest", &[]), "\nrest")
+    }
+
+    #[test]
+    fn past_closing_brace() {
+        assert_eq!(recover("foo } rest", &[]), " rest")
+    }
+
+    #[test]
+    fn at_directive() {
+        assert_eq!(
+            recover(".foo bar\n  .global baz;", MODULE_DIRECTIVES),
+            ".global baz;",
+        )
+    }
+
+    #[test]
+    fn ignores_comments_and_strings() {
+        assert_eq!(recover("a // ;\n /* } */ \";\" b; c", &[]), " c")
+    }
+
+    #[test]
+    fn to_end() {
+        assert_eq!(recover("foo bar", MODULE_DIRECTIVES), "")
+    }
+}
+
+/// The round trips every fixture must survive, in one sweep.
+#[cfg(feature = "std")]
+#[cfg(test)]
+mod test_round_trip {
+    use crate::ptx_files::{a, b, c, d, kernel, _EXAMPLE_FILE};
+
+    #[cfg(feature = "serde")]
+    use super::owned;
+    use super::{format, FormatOptions, ParseOptions, PtxFile, SyntaxTree};
+
+    fn without_whitespace(text: &str) -> String {
+        text.split_whitespace().collect()
+    }
+
+    #[test]
+    fn fixtures() {
+        for input in [a::_PTX, b::_PTX, c::_PTX, d::_PTX, kernel::_PTX, _EXAMPLE_FILE] {
+            // The syntax tree keeps every byte.
+            let (tree, _) = SyntaxTree::parse(input, ParseOptions::default()).unwrap();
+            assert_eq!(tree.to_string(), input);
+
+            let ptx = PtxFile::try_from(input).unwrap();
+            let printed = ptx.to_string();
+
+            // Formatting is idempotent and only changes whitespace.
+            let formatted = format(input, FormatOptions::default()).unwrap();
+            assert_eq!(format(&formatted, FormatOptions::default()).unwrap(), formatted);
+            assert_eq!(without_whitespace(&formatted), without_whitespace(input));
+            let strip = FormatOptions { strip_comments: true };
+            let stripped = format(input, strip).unwrap();
+            assert!(!stripped.contains("//") && !stripped.contains("/*"));
+            assert_eq!(format(&formatted, strip).unwrap(), stripped);
+
+            // The owned AST prints like the borrowed one.
+            let owned = ptx.into_owned();
+            assert_eq!(owned.to_string(), printed);
+
+            // Both ASTs serialize to what loads as the owned one.
+            #[cfg(feature = "serde")]
+            {
+                let borrowed = serde_json::to_string(&PtxFile::try_from(input).unwrap()).unwrap();
+                for json in [borrowed, serde_json::to_string(&owned).unwrap()] {
+                    assert_eq!(serde_json::from_str::<owned::PtxFile>(&json).unwrap(), owned);
+                }
+            }
+        }
+    }
 }
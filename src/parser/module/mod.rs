@@ -0,0 +1,168 @@
+use std::collections::HashMap;
+
+use super::{Function, Global, Item, PtxFile};
+
+/// An index over the symbols of a [`PtxFile`].
+///
+/// A function that is forward declared and later defined is one symbol,
+/// which resolves to its definition.
+#[derive(Debug, Clone)]
+pub struct Module<'r, 'a> {
+    ptx: &'r PtxFile<'a>,
+    symbols: HashMap<&'a str, Item<'r, 'a>>,
+}
+
+impl<'r, 'a> Module<'r, 'a> {
+    pub fn new(ptx: &'r PtxFile<'a>) -> Self {
+        let mut symbols = HashMap::new();
+        for item in ptx.items() {
+            let names: Vec<_> = match item {
+                Item::Function(function) => vec![function.name()],
+                Item::Global(global) => global.names().collect(),
+            };
+            for name in names {
+                match symbols.get(name) {
+                    Some(Item::Function(function)) if !function.is_declaration() => {}
+                    _ => {
+                        symbols.insert(name, item);
+                    }
+                }
+            }
+        }
+        Self { ptx, symbols }
+    }
+
+    pub fn ptx(&self) -> &'r PtxFile<'a> {
+        self.ptx
+    }
+
+    /// Looks up a function or variable by name.
+    pub fn get(&self, name: &str) -> Option<Item<'r, 'a>> {
+        self.symbols.get(name).copied()
+    }
+
+    /// The definition of a function, or its declaration if it is only declared.
+    pub fn function(&self, name: &str) -> Option<&'r Function<'a>> {
+        match self.get(name)? {
+            Item::Function(function) => Some(function),
+            Item::Global(_) => None,
+        }
+    }
+
+    /// A kernel by name.
+    pub fn entry(&self, name: &str) -> Option<&'r Function<'a>> {
+        self.function(name).filter(|function| function.is_entry())
+    }
+
+    pub fn variable(&self, name: &str) -> Option<&'r Global<'a>> {
+        match self.get(name)? {
+            Item::Global(global) => Some(global),
+            Item::Function(_) => None,
+        }
+    }
+
+    /// Whether `name` is a function with a body or a variable.
+    pub fn is_defined(&self, name: &str) -> bool {
+        match self.get(name) {
+            Some(Item::Function(function)) => !function.is_declaration(),
+            Some(Item::Global(_)) => true,
+            None => false,
+        }
+    }
+
+    /// The kernels, in source order.
+    pub fn entries(&self) -> impl Iterator<Item = &'r Function<'a>> + '_ {
+        self.symbols().filter(|function| function.is_entry())
+    }
+
+    /// The `.func` functions, in source order, once each.
+    pub fn device_functions(&self) -> impl Iterator<Item = &'r Function<'a>> + '_ {
+        self.symbols().filter(|function| !function.is_entry())
+    }
+
+    /// The functions with a body, in source order.
+    pub fn definitions(&self) -> impl Iterator<Item = &'r Function<'a>> {
+        self.ptx.functions().iter().filter(|function| !function.is_declaration())
+    }
+
+    /// Every forward declaration, in source order, including those of
+    /// functions defined later.
+    pub fn declarations(&self) -> impl Iterator<Item = &'r Function<'a>> {
+        self.ptx.functions().iter().filter(|function| function.is_declaration())
+    }
+
+    /// The global variables, in source order.
+    pub fn variables(&self) -> impl Iterator<Item = &'r Global<'a>> {
+        self.ptx.globals().iter()
+    }
+
+    /// The functions that the symbols resolve to, in source order.
+    fn symbols(&self) -> impl Iterator<Item = &'r Function<'a>> + '_ {
+        self.ptx.functions().iter().filter(|function| {
+            self.function(function.name()).is_some_and(|symbol| core::ptr::eq(symbol, *function))
+        })
+    }
+}
+
+#[cfg(test)]
+mod test_module {
+    use crate::{
+        parser::{Item, PtxFile},
+        ptx_files::{d, kernel},
+    };
+
+    use super::Module;
+
+    const NEW: &str = "_ZN12libgdx_xs1283rng6Random3new17hf4ea8272c085a319E";
+
+    #[test]
+    fn kernel() {
+        let ptx = PtxFile::try_from(kernel::_PTX).unwrap();
+        let module = Module::new(&ptx);
+        let kernel = module.entry("square_kernel").unwrap();
+        assert!(kernel.signature().parameters().unwrap().raw_string().contains("square_kernel_param_2"));
+        assert_eq!(module.entries().count(), 1);
+        assert_eq!(module.device_functions().count(), 0);
+        assert!(module.get("missing").is_none());
+    }
+
+    #[test]
+    fn declarations_and_definitions() {
+        let ptx = PtxFile::try_from(d::_PTX).unwrap();
+        let module = Module::new(&ptx);
+        let entries: Vec<_> = module.entries().map(|function| function.name()).collect();
+        assert_eq!(entries, ["run_length", "thread_id"]);
+
+        let new = module.function(NEW).unwrap();
+        assert!(!new.is_declaration());
+        assert!(module.is_defined(NEW));
+        assert!(module.declarations().any(|function| function.name() == NEW));
+        assert!(module.definitions().all(|function| !function.is_declaration()));
+        assert!(module.entry(NEW).is_none());
+
+        // Declared and defined, but listed once.
+        let names: Vec<_> = module.device_functions().map(|function| function.name()).collect();
+        assert_eq!(names.iter().filter(|name| **name == NEW).count(), 1);
+        assert!(names.contains(&"rust_begin_unwind"));
+    }
+
+    #[test]
+    fn variables() {
+        let ptx = PtxFile::try_from(d::_PTX).unwrap();
+        let module = Module::new(&ptx);
+        assert_eq!(module.variables().count(), ptx.globals().len());
+        let global = module.variable("str_$_0").unwrap();
+        assert!(global.raw_string().starts_with(".align 1 .b8 str_$_0[57]"));
+        assert!(matches!(module.get("str_$_0"), Some(Item::Global(_))));
+        assert!(module.function("str_$_0").is_none());
+    }
+
+    #[test]
+    fn multiple_declarators() {
+        let input = ".version 7.5\n.target sm_30\n.address_size 64\n.global .u32 a, b;\n";
+        let ptx = PtxFile::try_from(input).unwrap();
+        let module = Module::new(&ptx);
+        let (a, b) = (module.variable("a").unwrap(), module.variable("b").unwrap());
+        assert!(core::ptr::eq(a, b));
+    }
+}
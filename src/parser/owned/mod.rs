@@ -0,0 +1,448 @@
+//! An owned mirror of the AST.
+//!
+//! The types in [`crate::parser`] borrow from the source and parse function
+//! bodies lazily. The types here own their text and hold every statement,
+//! so they can outlive the source, be sent across threads and be edited.
+//! They print like their borrowed counterparts.
+//!
+//! Spans still refer to the source the module was parsed from.
+
+use super::{Opcode, Span, StateSpace, Type};
+
+/// An owned [`PtxFile`](super::PtxFile).
+///
+/// With the `serde` feature, this is what JSON serialized from either AST
+/// deserializes into.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PtxFile {
+    /// The comments before `.version`.
+    pub header_comments: Vec<Comment>,
+    pub preamble: Preamble,
+    pub items: Vec<Item>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Preamble {
+    /// Major and minor version, e.g. `7` and `5`.
+    pub version: (String, String),
+    pub target: String,
+    pub address_size: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Item {
+    Function(Function),
+    Global(Global),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Global {
+    pub span: Span,
+    /// The declaration after `.global`, without the `;`.
+    pub raw_string: String,
+    pub comments: Comments,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Function {
+    pub span: Span,
+    pub signature: FunctionSignature,
+    /// `None` for a declaration without a body.
+    pub body: Option<Vec<Statement>>,
+    pub comments: Comments,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FunctionSignature {
+    pub visible: bool,
+    pub entry: bool,
+    /// The return value declaration, without parentheses.
+    pub return_value: Option<String>,
+    pub name: String,
+    /// The parameter list, without parentheses.
+    pub parameters: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Statement {
+    pub span: Span,
+    pub line: BodyLine,
+    pub comments: Comments,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum BodyLine {
+    /// The declaration after `.reg`.
+    Register(String),
+    Directive(Directive),
+    Instruction(Instruction),
+    Operation(Operation),
+    /// A label as written, like `$L__BB0_2` or `LBB0_2`.
+    Label(String),
+    Goto(Goto),
+    /// `ret`, or `ret.uni` if `uniform`.
+    Return { uniform: bool },
+    FunctionCall(FunctionCall),
+    Unknown(String),
+    /// Source that failed to parse, kept verbatim.
+    Error(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Directive {
+    /// The directive without its leading `.`.
+    pub name: String,
+    pub arguments: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Operation {
+    pub operation: String,
+    pub arguments: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Goto {
+    pub predicate: Option<Predicate>,
+    pub uniform: bool,
+    /// The target label as written, like `$L__BB0_2` or `LBB0_2`.
+    pub label: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FunctionCall {
+    /// The name of the called function.
+    pub function: String,
+    /// The statements of the call sequence, including the `call` itself.
+    pub statements: Vec<Statement>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Instruction {
+    pub predicate: Option<Predicate>,
+    pub opcode: Opcode,
+    pub modifiers: Vec<Modifier>,
+    pub operands: Vec<Operand>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Predicate {
+    True(String),
+    False(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Modifier {
+    Type(Type),
+    StateSpace(StateSpace, Option<String>),
+    Vector(u8),
+    Known(String),
+    Unknown(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Operand {
+    Register(String),
+    Negated(String),
+    PredicatePair(String, String),
+    Immediate(String),
+    Symbol(String),
+    Address { base: String, offset: Option<String> },
+    Vector(Vec<Operand>),
+    List(Vec<Operand>),
+    Unknown(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Comment {
+    Line(String),
+    Block(String),
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Comments {
+    pub leading: Vec<Comment>,
+    pub trailing: Option<Comment>,
+}
+
+impl PtxFile {
+    pub fn functions(&self) -> impl Iterator<Item = &Function> {
+        self.items.iter().filter_map(|item| match item {
+            Item::Function(function) => Some(function),
+            Item::Global(_) => None,
+        })
+    }
+
+    pub fn globals(&self) -> impl Iterator<Item = &Global> {
+        self.items.iter().filter_map(|item| match item {
+            Item::Global(global) => Some(global),
+            Item::Function(_) => None,
+        })
+    }
+}
+
+impl Function {
+    pub fn name(&self) -> &str {
+        &self.signature.name
+    }
+}
+
+impl<'a> super::PtxFile<'a> {
+    /// Copies the module into an owned AST, parsing all function bodies.
+    pub fn into_owned(self) -> PtxFile {
+        PtxFile {
+            header_comments: self.header_comments().map(Comment::from).collect(),
+            preamble: Preamble::from(self.preamble()),
+            items: self.items()
+                .map(|item| match item {
+                    super::Item::Function(function) => Item::Function(function.into()),
+                    super::Item::Global(global) => Item::Global(global.into()),
+                })
+                .collect(),
+        }
+    }
+}
+
+impl<'a> super::Function<'a> {
+    pub fn into_owned(self) -> Function {
+        Function::from(&self)
+    }
+}
+
+impl<'a> super::Global<'a> {
+    pub fn into_owned(self) -> Global {
+        Global::from(&self)
+    }
+}
+
+impl<'a> super::Statement<'a> {
+    pub fn into_owned(self) -> Statement {
+        Statement::from(&self)
+    }
+}
+
+fn statements(mut body: super::FunctionBody) -> Vec<Statement> {
+    let source = body.source();
+    core::iter::from_fn(|| body.next_recovering())
+        .map(|statement| match statement {
+            Ok(statement) => Statement::from(&statement),
+            Err(diagnostic) => Statement {
+                span: Span::of(source, diagnostic.skipped()),
+                line: BodyLine::Error(diagnostic.skipped().to_string()),
+                comments: Comments::default(),
+            },
+        })
+        .collect()
+}
+
+impl From<&super::Preamble<'_>> for Preamble {
+    fn from(preamble: &super::Preamble) -> Self {
+        let version = preamble.version();
+        Self {
+            version: (version.major().to_string(), version.minor().to_string()),
+            target: preamble.target().target().to_string(),
+            address_size: preamble.address_size().size().to_string(),
+        }
+    }
+}
+
+impl From<&super::Global<'_>> for Global {
+    fn from(global: &super::Global) -> Self {
+        Self {
+            span: global.span(),
+            raw_string: global.raw_string().to_string(),
+            comments: global.comments().into(),
+        }
+    }
+}
+
+impl From<&super::Function<'_>> for Function {
+    fn from(function: &super::Function) -> Self {
+        let signature = function.signature();
+        Self {
+            span: function.span(),
+            signature: FunctionSignature {
+                visible: signature.is_visible(),
+                entry: signature.is_entry(),
+                return_value: signature.return_value().map(|value| value.raw_string().to_string()),
+                name: signature.name().to_string(),
+                parameters: signature.parameters().map(|parameters| parameters.raw_string().to_string()),
+            },
+            body: function.body().map(statements),
+            comments: function.comments().into(),
+        }
+    }
+}
+
+impl From<&super::Statement<'_>> for Statement {
+    fn from(statement: &super::Statement) -> Self {
+        Self {
+            span: statement.span(),
+            line: statement.line().into(),
+            comments: statement.comments().into(),
+        }
+    }
+}
+
+impl From<&super::BodyLine<'_>> for BodyLine {
+    fn from(line: &super::BodyLine) -> Self {
+        match line {
+            super::BodyLine::Register(register) => BodyLine::Register(register.raw_string().to_string()),
+            super::BodyLine::Directive(directive) => BodyLine::Directive(Directive {
+                name: directive.name().to_string(),
+                arguments: directive.arguments().to_string(),
+            }),
+            super::BodyLine::Instruction(instruction) => BodyLine::Instruction(instruction.into()),
+            super::BodyLine::Operation(operation) => BodyLine::Operation(Operation {
+                operation: operation.operation().to_string(),
+                arguments: operation.arguments().to_string(),
+            }),
+            super::BodyLine::Label(label) => BodyLine::Label(label.to_string()),
+            super::BodyLine::Goto(goto) => BodyLine::Goto(Goto {
+                predicate: goto.predicate().map(Predicate::from),
+                uniform: goto.is_uniform(),
+                label: goto.label().to_string(),
+            }),
+            super::BodyLine::Return { uniform } => BodyLine::Return { uniform: *uniform },
+            super::BodyLine::FunctionCall(call) => BodyLine::FunctionCall(FunctionCall {
+                function: call.function().to_string(),
+                statements: statements(call.statements()),
+            }),
+            super::BodyLine::Unknown(raw_string) => BodyLine::Unknown(raw_string.to_string()),
+        }
+    }
+}
+
+impl From<&super::Instruction<'_>> for Instruction {
+    fn from(instruction: &super::Instruction) -> Self {
+        Self {
+            predicate: instruction.predicate().map(Predicate::from),
+            opcode: instruction.opcode(),
+            modifiers: instruction.modifiers().map(Modifier::from).collect(),
+            operands: instruction.operands().map(Operand::from).collect(),
+        }
+    }
+}
+
+impl From<&super::Predicate<'_>> for Predicate {
+    fn from(predicate: &super::Predicate) -> Self {
+        match predicate {
+            super::Predicate::True(register) => Predicate::True(register.to_string()),
+            super::Predicate::False(register) => Predicate::False(register.to_string()),
+        }
+    }
+}
+
+impl From<super::Modifier<'_>> for Modifier {
+    fn from(modifier: super::Modifier) -> Self {
+        match modifier {
+            super::Modifier::Type(ty) => Modifier::Type(ty),
+            super::Modifier::StateSpace(space, qualifier) => Modifier::StateSpace(space, qualifier.map(str::to_string)),
+            super::Modifier::Vector(width) => Modifier::Vector(width),
+            super::Modifier::Known(modifier) => Modifier::Known(modifier.to_string()),
+            super::Modifier::Unknown(modifier) => Modifier::Unknown(modifier.to_string()),
+        }
+    }
+}
+
+impl From<super::Operand<'_>> for Operand {
+    fn from(operand: super::Operand) -> Self {
+        match operand {
+            super::Operand::Register(register) => Operand::Register(register.to_string()),
+            super::Operand::Negated(register) => Operand::Negated(register.to_string()),
+            super::Operand::PredicatePair(first, second) => {
+                Operand::PredicatePair(first.to_string(), second.to_string())
+            }
+            super::Operand::Immediate(immediate) => Operand::Immediate(immediate.to_string()),
+            super::Operand::Symbol(symbol) => Operand::Symbol(symbol.to_string()),
+            super::Operand::Address(address) => Operand::Address {
+                base: address.base().to_string(),
+                offset: address.offset().map(str::to_string),
+            },
+            super::Operand::Vector(list) => Operand::Vector(list.operands().map(Operand::from).collect()),
+            super::Operand::List(list) => Operand::List(list.operands().map(Operand::from).collect()),
+            super::Operand::Unknown(operand) => Operand::Unknown(operand.to_string()),
+        }
+    }
+}
+
+impl From<super::Comment<'_>> for Comment {
+    fn from(comment: super::Comment) -> Self {
+        match comment {
+            super::Comment::Line(_) => Comment::Line(comment.text().to_string()),
+            super::Comment::Block(text) => Comment::Block(text.to_string()),
+        }
+    }
+}
+
+impl From<&super::Comments<'_>> for Comments {
+    fn from(comments: &super::Comments) -> Self {
+        Self {
+            leading: comments.leading().map(Comment::from).collect(),
+            trailing: comments.trailing().copied().map(Comment::from),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_owned {
+    use crate::{parser::PtxFile, ptx_files::kernel};
+
+    use super::{BodyLine, Item, Operand, PtxFile as OwnedPtxFile};
+
+    #[test]
+    fn outlives_source() {
+        fn parse() -> OwnedPtxFile {
+            let source = kernel::_PTX.to_string();
+            PtxFile::try_from(source.as_str()).unwrap().into_owned()
+        }
+        fn assert_send_sync<T: Send + Sync + 'static>(_: &T) {}
+
+        let mut ptx = parse();
+        assert_send_sync(&ptx);
+        let Some(Item::Function(function)) = ptx.items.first_mut() else { panic!("expected a function") };
+        function.signature.name = "cube_kernel".to_string();
+        let parameters = function.signature.parameters.as_mut().unwrap();
+        *parameters = parameters.replace("square_kernel", "cube_kernel");
+        for statement in function.body.iter_mut().flatten() {
+            if let BodyLine::Instruction(instruction) = &mut statement.line {
+                for operand in &mut instruction.operands {
+                    if let Operand::Address { base, .. } = operand {
+                        *base = base.replace("square_kernel", "cube_kernel");
+                    }
+                }
+            }
+        }
+        let printed = ptx.to_string();
+        assert!(printed.contains(".visible .entry cube_kernel("));
+        assert!(!printed.contains("square_kernel_param"));
+    }
+
+    #[test]
+    fn uniform_return() {
+        let input = ".version 7.5\n.target sm_30\n.address_size 64\n.visible .entry k()\n{\nret.uni;\n}\n";
+        let ptx = PtxFile::try_from(input).unwrap().into_owned();
+        let Some(Item::Function(function)) = ptx.items.first() else { panic!("expected a function") };
+        let statement = &function.body.as_ref().unwrap()[0];
+        assert_eq!(statement.line, BodyLine::Return { uniform: true });
+        assert_eq!(statement.line.to_string(), "ret.uni;");
+    }
+}
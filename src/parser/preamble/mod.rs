@@ -1,39 +1,100 @@
+use super::span::Span;
+
 pub(crate) mod parse;
 
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Preamble<'a> {
+    span: Span,
     version: Version<'a>,
     target: Target<'a>,
     address_size: AddressSize<'a>,
 }
 
 #[derive(Debug, PartialEq)]
-pub(super) struct Version<'a> {
+pub struct Version<'a> {
+    span: Span,
     major: &'a str,
     minor: &'a str,
 }
 
 #[derive(Debug, PartialEq)]
-pub(super) struct Target<'a> {
+pub struct Target<'a> {
+    span: Span,
     target: &'a str,
 }
 
 #[derive(Debug, PartialEq)]
-pub(super) struct AddressSize<'a> {
+pub struct AddressSize<'a> {
+    span: Span,
     size: &'a str,
 }
 
+impl<'a> Preamble<'a> {
+    pub fn span(&self) -> Span {
+        self.span
+    }
+
+    pub fn version(&self) -> &Version<'a> {
+        &self.version
+    }
+
+    pub fn target(&self) -> &Target<'a> {
+        &self.target
+    }
+
+    pub fn address_size(&self) -> &AddressSize<'a> {
+        &self.address_size
+    }
+}
+
+impl<'a> Version<'a> {
+    pub fn span(&self) -> Span {
+        self.span
+    }
+
+    pub fn major(&self) -> &'a str {
+        self.major
+    }
+
+    pub fn minor(&self) -> &'a str {
+        self.minor
+    }
+}
+
+impl<'a> Target<'a> {
+    pub fn span(&self) -> Span {
+        self.span
+    }
+
+    pub fn target(&self) -> &'a str {
+        self.target
+    }
+}
+
+impl<'a> AddressSize<'a> {
+    pub fn span(&self) -> Span {
+        self.span
+    }
+
+    pub fn size(&self) -> &'a str {
+        self.size
+    }
+}
+
 #[cfg(test)]
 mod test_parse_version {
-    use crate::parser::preamble::{Version, parse::parse_version};
+    use crate::parser::{preamble::{Version, parse::parse_version}, span::Span};
 
     #[test]
     fn no_whitespace() {
+        let input = ".version 1.0";
         assert_eq!(
-            parse_version(".version 1.0"),
+            parse_version(input)(input),
             Ok((
                 "",
                 Version {
+                    span: Span::new(0, 12),
                     major: "1",
                     minor: "0"
                 }
@@ -43,16 +104,19 @@ mod test_parse_version {
 
     #[test]
     fn leading_whitespace() {
-        assert!(parse_version("  .version 1.0").is_err());
+        let input = "  .version 1.0";
+        assert!(parse_version(input)(input).is_err());
     }
 
     #[test]
     fn trailing_whitespace() {
+        let input = ".version 1.0  ";
         assert_eq!(
-            parse_version(".version 1.0  "),
+            parse_version(input)(input),
             Ok((
                 "  ",
                 Version {
+                    span: Span::new(0, 12),
                     major: "1",
                     minor: "0"
                 }
@@ -62,11 +126,13 @@ mod test_parse_version {
 
     #[test]
     fn immediate_comment() {
+        let input = ".version 1.0// This is a comment";
         assert_eq!(
-            parse_version(".version 1.0// This is a comment"),
+            parse_version(input)(input),
             Ok((
                 "// This is a comment",
                 Version {
+                    span: Span::new(0, 12),
                     major: "1",
                     minor: "0"
                 }
@@ -77,89 +143,100 @@ mod test_parse_version {
 
 #[cfg(test)]
 mod test_parse_target {
-    use crate::parser::preamble::{Target, parse::parse_target};
+    use crate::parser::{preamble::{Target, parse::parse_target}, span::Span};
 
     #[test]
     fn no_whitespace() {
+        let input = ".target sm_30";
         assert_eq!(
-            parse_target(".target sm_30"),
-            Ok(("", Target { target: "sm_30" }))
+            parse_target(input)(input),
+            Ok(("", Target { span: Span::new(0, 13), target: "sm_30" }))
         );
     }
 
     #[test]
     fn leading_whitespace() {
-        assert!(parse_target("  .target sm_30").is_err());
+        let input = "  .target sm_30";
+        assert!(parse_target(input)(input).is_err());
     }
 
     #[test]
     fn trailing_whitespace() {
+        let input = ".target sm_30  ";
         assert_eq!(
-            parse_target(".target sm_30  "),
-            Ok(("  ", Target { target: "sm_30" }))
+            parse_target(input)(input),
+            Ok(("  ", Target { span: Span::new(0, 13), target: "sm_30" }))
         );
     }
 
     #[test]
     fn immediate_comment() {
+        let input = ".target sm_30// This is a comment";
         assert_eq!(
-            parse_target(".target sm_30// This is a comment"),
-            Ok(("// This is a comment", Target { target: "sm_30" }))
+            parse_target(input)(input),
+            Ok(("// This is a comment", Target { span: Span::new(0, 13), target: "sm_30" }))
         );
     }
 }
 
 #[cfg(test)]
 mod test_parse_address_size {
-    use crate::parser::preamble::{AddressSize, parse::parse_address_size};
+    use crate::parser::{preamble::{AddressSize, parse::parse_address_size}, span::Span};
 
     #[test]
     fn no_whitespace() {
+        let input = ".address_size 64";
         assert_eq!(
-            parse_address_size(".address_size 64"),
-            Ok(("", AddressSize { size: "64" }))
+            parse_address_size(input)(input),
+            Ok(("", AddressSize { span: Span::new(0, 16), size: "64" }))
         );
     }
 
     #[test]
     fn leading_whitespace() {
-        assert!(parse_address_size("  .address_size 64").is_err());
+        let input = "  .address_size 64";
+        assert!(parse_address_size(input)(input).is_err());
     }
 
     #[test]
     fn trailing_whitespace() {
+        let input = ".address_size 64  ";
         assert_eq!(
-            parse_address_size(".address_size 64  "),
-            Ok(("  ", AddressSize { size: "64" }))
+            parse_address_size(input)(input),
+            Ok(("  ", AddressSize { span: Span::new(0, 16), size: "64" }))
         );
     }
 
     #[test]
     fn immediate_comment() {
+        let input = ".address_size 64// This is a comment";
         assert_eq!(
-            parse_address_size(".address_size 64// This is a comment"),
-            Ok(("// This is a comment", AddressSize { size: "64" }))
+            parse_address_size(input)(input),
+            Ok(("// This is a comment", AddressSize { span: Span::new(0, 16), size: "64" }))
         );
     }
 }
 
 #[cfg(test)]
 mod test_parse_preamble {
-    use crate::parser::preamble::{parse::parse_preamble, Preamble, Version, Target, AddressSize};
+    use crate::parser::{preamble::{parse::parse_preamble, Preamble, Version, Target, AddressSize}, span::Span};
 
     #[test]
     fn no_whitespace() {
+        let input = ".version 1.0\n.target sm_30\n.address_size 64";
         assert_eq!(
-            parse_preamble(".version 1.0\n.target sm_30\n.address_size 64"),
+            parse_preamble(input)(input),
             Ok((
                 "",
                 (Preamble {
+                    span: Span::new(0, 43),
                     version: Version {
+                        span: Span::new(0, 12),
                         major: "1",
                         minor: "0"
                     },
-                    target: Target { target: "sm_30" },
-                    address_size: AddressSize { size: "64" }
+                    target: Target { span: Span::new(13, 26), target: "sm_30" },
+                    address_size: AddressSize { span: Span::new(27, 43), size: "64" }
                 })
             ))
         );
@@ -167,17 +244,20 @@ mod test_parse_preamble {
 
     #[test]
     fn leading_whitespace() {
+        let input = "  .version 1.0\n.target sm_30\n.address_size 64";
         assert_eq!(
-            parse_preamble("  .version 1.0\n.target sm_30\n.address_size 64"),
+            parse_preamble(input)(input),
             Ok((
                 "",
                 (Preamble {
+                    span: Span::new(2, 45),
                     version: Version {
+                        span: Span::new(2, 14),
                         major: "1",
                         minor: "0"
                     },
-                    target: Target { target: "sm_30" },
-                    address_size: AddressSize { size: "64" }
+                    target: Target { span: Span::new(15, 28), target: "sm_30" },
+                    address_size: AddressSize { span: Span::new(29, 45), size: "64" }
                 })
             ))
         );
@@ -185,17 +265,20 @@ mod test_parse_preamble {
 
     #[test]
     fn leading_newline() {
+        let input = " \n .version 1.0\n.target sm_30\n.address_size 64";
         assert_eq!(
-            parse_preamble(" \n .version 1.0\n.target sm_30\n.address_size 64"),
+            parse_preamble(input)(input),
             Ok((
                 "",
                 (Preamble {
+                    span: Span::new(3, 46),
                     version: Version {
+                        span: Span::new(3, 15),
                         major: "1",
                         minor: "0"
                     },
-                    target: Target { target: "sm_30" },
-                    address_size: AddressSize { size: "64" }
+                    target: Target { span: Span::new(16, 29), target: "sm_30" },
+                    address_size: AddressSize { span: Span::new(30, 46), size: "64" }
                 })
             ))
         );
@@ -203,17 +286,20 @@ mod test_parse_preamble {
 
     #[test]
     fn trailing_whitespace() {
+        let input = ".version 1.0\n.target sm_30\n.address_size 64  ";
         assert_eq!(
-            parse_preamble(".version 1.0\n.target sm_30\n.address_size 64  "),
+            parse_preamble(input)(input),
             Ok((
                 "  ",
                 (Preamble {
+                    span: Span::new(0, 43),
                     version: Version {
+                        span: Span::new(0, 12),
                         major: "1",
                         minor: "0"
                     },
-                    target: Target { target: "sm_30" },
-                    address_size: AddressSize { size: "64" }
+                    target: Target { span: Span::new(13, 26), target: "sm_30" },
+                    address_size: AddressSize { span: Span::new(27, 43), size: "64" }
                 })
             ))
         );
@@ -221,17 +307,20 @@ mod test_parse_preamble {
 
     #[test]
     fn immediate_comment() {
+        let input = ".version 1.0\n.target sm_30\n.address_size 64// This is a comment";
         assert_eq!(
-            parse_preamble(".version 1.0\n.target sm_30\n.address_size 64// This is a comment"),
+            parse_preamble(input)(input),
             Ok((
                 "// This is a comment",
                 (Preamble {
+                    span: Span::new(0, 43),
                     version: Version {
+                        span: Span::new(0, 12),
                         major: "1",
                         minor: "0"
                     },
-                    target: Target { target: "sm_30" },
-                    address_size: AddressSize { size: "64" }
+                    target: Target { span: Span::new(13, 26), target: "sm_30" },
+                    address_size: AddressSize { span: Span::new(27, 43), size: "64" }
                 })
             ))
         );
@@ -239,17 +328,20 @@ mod test_parse_preamble {
 
     #[test]
     fn trailing_comment() {
+        let input = ".version 1.0\n.target sm_30\n.address_size 64\n// This is a comment";
         assert_eq!(
-            parse_preamble(".version 1.0\n.target sm_30\n.address_size 64\n// This is a comment"),
+            parse_preamble(input)(input),
             Ok((
                 "\n// This is a comment",
                 (Preamble {
+                    span: Span::new(0, 43),
                     version: Version {
+                        span: Span::new(0, 12),
                         major: "1",
                         minor: "0"
                     },
-                    target: Target { target: "sm_30" },
-                    address_size: AddressSize { size: "64" }
+                    target: Target { span: Span::new(13, 26), target: "sm_30" },
+                    address_size: AddressSize { span: Span::new(27, 43), size: "64" }
                 })
             ))
         );
@@ -1,62 +1,85 @@
 use nom::{
-    bytes::complete::{tag, take_while1},
+    bytes::complete::take_while1,
     character::complete::{char, space1},
-    combinator::opt,
-    sequence::{preceded, Tuple},
-    IResult, Parser,
+    combinator::{consumed, map, opt},
+    error::context,
+    sequence::{pair, preceded, Tuple},
+    Parser,
 };
 
-use crate::parser::{comment::parse::many1_comments_or_whitespace, parse_name};
+use crate::parser::{
+    comment::parse::many1_comments_or_whitespace,
+    error::{parse::{expect, token}, Expected, PResult},
+    parse_name,
+    span::Span,
+};
 
 use super::{Preamble, Target, Version, AddressSize};
 
-pub(crate) fn parse_preamble(input: &str) -> IResult<&str, Preamble> {
-    (
-        preceded(
-            opt(many1_comments_or_whitespace), 
-            parse_version
-        ),
-        preceded(
-            many1_comments_or_whitespace,
-            parse_target
-        ),
-        preceded(
-            many1_comments_or_whitespace,
-            parse_address_size
-        )
-    )
-    .parse(input)
-    .map(|(input, (version, target, address_size))| {
-        (
-            input,
-            Preamble {
-                version,
-                target,
-                address_size,
-            },
-        )
-    })
+pub(crate) fn parse_preamble<'a>(source: &'a str) -> impl FnMut(&'a str) -> PResult<'a, Preamble<'a>> {
+    move |input| {
+        let (input, _) = opt(many1_comments_or_whitespace)(input)?;
+        consumed(|input| (
+            parse_version(source),
+            preceded(
+                many1_comments_or_whitespace,
+                parse_target(source)
+            ),
+            preceded(
+                many1_comments_or_whitespace,
+                parse_address_size(source)
+            )
+        ).parse(input))
+        .parse(input)
+        .map(|(input, (text, (version, target, address_size)))| {
+            (
+                input,
+                Preamble {
+                    span: Span::of(source, text),
+                    version,
+                    target,
+                    address_size,
+                },
+            )
+        })
+    }
 }
 
-pub(super) fn parse_version(input: &str) -> IResult<&str, Version> {
-    (
-        preceded(tag(".version").and(space1), take_while1(char::is_numeric)),
-        preceded(char('.'), take_while1(char::is_numeric)),
-    )
-    .parse(input)
-    .map(|(input, (major, minor))| (input, Version { major, minor }))
+pub(super) fn parse_version<'a>(source: &'a str) -> impl FnMut(&'a str) -> PResult<'a, Version<'a>> {
+    map(context(
+        "version directive",
+        consumed(pair(
+            preceded(
+                token(".version").and(space1),
+                expect(Expected::Description("a major version"), take_while1(char::is_numeric)),
+            ),
+            preceded(
+                char('.'),
+                expect(Expected::Description("a minor version"), take_while1(char::is_numeric)),
+            ),
+        )),
+    ),
+    move |(text, (major, minor))| Version { span: Span::of(source, text), major, minor })
 }
 
-pub(super) fn parse_target(input: &str) -> IResult<&str, Target> {
-    preceded(
-        tag(".target").and(space1),
-        parse_name.map(|target| Target { target }),
-    )(input)
+pub(super) fn parse_target<'a>(source: &'a str) -> impl FnMut(&'a str) -> PResult<'a, Target<'a>> {
+    map(context(
+        "target directive",
+        consumed(preceded(
+            token(".target").and(space1),
+            parse_name,
+        )),
+    ),
+    move |(text, target)| Target { span: Span::of(source, text), target })
 }
 
-pub(super) fn parse_address_size(input: &str) -> IResult<&str, AddressSize> {
-    preceded(
-        tag(".address_size").and(space1),
-        parse_name.map(|size| AddressSize { size }),
-    )(input)
+pub(super) fn parse_address_size<'a>(source: &'a str) -> impl FnMut(&'a str) -> PResult<'a, AddressSize<'a>> {
+    map(context(
+        "address size directive",
+        consumed(preceded(
+            token(".address_size").and(space1),
+            parse_name,
+        )),
+    ),
+    move |(text, size)| AddressSize { span: Span::of(source, text), size })
 }
@@ -0,0 +1,462 @@
+//! `Display` impls that print the AST back to PTX.
+//!
+//! Printing normalizes whitespace: statements go on their own lines,
+//! indented by a tab, with labels in the first column. Raw text the parser
+//! keeps as-is, such as parameter lists, is printed verbatim. Parsing the
+//! output yields the same AST up to spans.
+
+use core::fmt::{self, Display, Formatter, Write};
+
+use super::{
+    function::body::FunctionBody, AddressSize, BodyLine, Comment, Comments, Directive, Function,
+    FunctionSignature, Global, Goto, Instruction, Modifier, Opcode, Operand, OperandList, Operation,
+    Predicate, Preamble, Register, StateSpace, Statement, Target, Type, Version,
+};
+#[cfg(feature = "std")]
+use super::{Item, PtxFile};
+
+#[cfg(feature = "std")]
+mod owned;
+
+fn indent(f: &mut Formatter<'_>, depth: usize) -> fmt::Result {
+    (0..depth).try_for_each(|_| f.write_char('\t'))
+}
+
+fn write_leading(f: &mut Formatter<'_>, comments: &Comments<'_>, depth: usize) -> fmt::Result {
+    comments.leading().try_for_each(|comment| {
+        indent(f, depth)?;
+        writeln!(f, "{comment}")
+    })
+}
+
+fn write_trailing(f: &mut Formatter<'_>, comments: &Comments<'_>) -> fmt::Result {
+    match comments.trailing() {
+        Some(comment) => write!(f, " {comment}"),
+        None => Ok(()),
+    }
+}
+
+/// Writes the statements of `body`, one per line. Source that failed to
+/// parse is written back unchanged.
+fn write_statements(f: &mut Formatter<'_>, mut body: FunctionBody<'_>, depth: usize) -> fmt::Result {
+    while let Some(statement) = body.next_recovering() {
+        match statement {
+            Ok(statement) => write_statement(f, &statement, depth)?,
+            Err(diagnostic) => {
+                indent(f, depth)?;
+                f.write_str(diagnostic.skipped())?;
+            }
+        }
+        f.write_char('\n')?;
+    }
+    Ok(())
+}
+
+fn write_statement(f: &mut Formatter<'_>, statement: &Statement<'_>, depth: usize) -> fmt::Result {
+    write_leading(f, statement.comments(), depth)?;
+    if !matches!(statement.line(), BodyLine::Label(_)) {
+        indent(f, depth)?;
+    }
+    write_line(f, statement.line(), depth)?;
+    write_trailing(f, statement.comments())
+}
+
+fn write_line(f: &mut Formatter<'_>, line: &BodyLine<'_>, depth: usize) -> fmt::Result {
+    match line {
+        BodyLine::Register(register) => write!(f, "{register};"),
+        BodyLine::Directive(directive) => write!(f, "{directive};"),
+        BodyLine::Instruction(instruction) => write!(f, "{instruction};"),
+        BodyLine::Operation(operation) => write!(f, "{operation};"),
+        BodyLine::Label(label) => write!(f, "{label}:"),
+        BodyLine::Goto(goto) => write!(f, "{goto};"),
+        BodyLine::Return { uniform: false } => f.write_str("ret;"),
+        BodyLine::Return { uniform: true } => f.write_str("ret.uni;"),
+        BodyLine::FunctionCall(call) => {
+            f.write_str("{\n")?;
+            write_statements(f, call.statements(), depth + 1)?;
+            indent(f, depth)?;
+            f.write_char('}')
+        }
+        BodyLine::Unknown(raw_string) => write!(f, "{raw_string};"),
+    }
+}
+
+#[cfg(feature = "std")]
+impl Display for PtxFile<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        self.header_comments().try_for_each(|comment| writeln!(f, "{comment}"))?;
+        if self.header_comments().next().is_some() {
+            f.write_char('\n')?;
+        }
+        write!(f, "{}", self.preamble)?;
+        self.items().try_for_each(|item| match item {
+            Item::Function(function) => write!(f, "\n{function}"),
+            Item::Global(global) => write!(f, "\n{global}"),
+        })
+    }
+}
+
+impl Display for Preamble<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}", self.version())?;
+        writeln!(f, "{}", self.target())?;
+        writeln!(f, "{}", self.address_size())
+    }
+}
+
+impl Display for Version<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, ".version {}.{}", self.major(), self.minor())
+    }
+}
+
+impl Display for Target<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, ".target {}", self.target())
+    }
+}
+
+impl Display for AddressSize<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, ".address_size {}", self.size())
+    }
+}
+
+impl Display for Global<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write_leading(f, self.comments(), 0)?;
+        write!(f, ".global {};", self.raw_string())?;
+        write_trailing(f, self.comments())?;
+        f.write_char('\n')
+    }
+}
+
+impl Display for Function<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write_leading(f, self.comments(), 0)?;
+        write!(f, "{}", self.signature())?;
+        match self.body() {
+            Some(body) => write!(f, "\n{body}")?,
+            None => f.write_char(';')?,
+        }
+        write_trailing(f, self.comments())?;
+        f.write_char('\n')
+    }
+}
+
+impl Display for FunctionSignature<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        if self.is_visible() {
+            f.write_str(".visible ")?;
+        }
+        f.write_str(if self.is_entry() { ".entry" } else { ".func" })?;
+        if let Some(return_value) = self.return_value() {
+            write!(f, " ({})", return_value.raw_string())?;
+        }
+        write!(f, " {}", self.name())?;
+        if let Some(parameters) = self.parameters() {
+            write!(f, "({})", parameters.raw_string())?;
+        }
+        Ok(())
+    }
+}
+
+impl Display for FunctionBody<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str("{\n")?;
+        write_statements(f, self.clone(), 1)?;
+        f.write_char('}')
+    }
+}
+
+impl Display for Statement<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write_statement(f, self, 0)
+    }
+}
+
+impl Display for BodyLine<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write_line(f, self, 0)
+    }
+}
+
+impl Display for Register<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, ".reg {}", self.raw_string())
+    }
+}
+
+impl Display for Directive<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, ".{}", self.name())?;
+        if !self.arguments().is_empty() {
+            write!(f, " {}", self.arguments())?;
+        }
+        Ok(())
+    }
+}
+
+impl Display for Operation<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.operation(), self.arguments())
+    }
+}
+
+impl Display for Goto<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        if let Some(predicate) = self.predicate() {
+            write!(f, "{predicate} ")?;
+        }
+        f.write_str(if self.is_uniform() { "bra.uni" } else { "bra" })?;
+        write!(f, " {}", self.label())
+    }
+}
+
+impl Display for Instruction<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        if let Some(predicate) = self.predicate() {
+            write!(f, "{predicate} ")?;
+        }
+        write!(f, "{}", self.opcode())?;
+        self.modifiers().try_for_each(|modifier| write!(f, ".{modifier}"))?;
+        let mut operands = self.operands();
+        if let Some(first) = operands.next() {
+            write!(f, " {first}")?;
+            operands.try_for_each(|operand| write!(f, ", {operand}"))?;
+        }
+        Ok(())
+    }
+}
+
+impl Display for Predicate<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Predicate::True(register) => write!(f, "@{register}"),
+            Predicate::False(register) => write!(f, "@!{register}"),
+        }
+    }
+}
+
+impl Display for Opcode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl Display for Type {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl Display for StateSpace {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Writes the modifier without its leading `.`.
+impl Display for Modifier<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Modifier::Type(ty) => ty.fmt(f),
+            Modifier::StateSpace(space, None) => space.fmt(f),
+            Modifier::StateSpace(space, Some(qualifier)) => write!(f, "{space}::{qualifier}"),
+            Modifier::Vector(width) => write!(f, "v{width}"),
+            Modifier::Known(modifier) | Modifier::Unknown(modifier) => f.write_str(modifier),
+        }
+    }
+}
+
+impl Display for Operand<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Operand::Register(name)
+            | Operand::Immediate(name)
+            | Operand::Symbol(name)
+            | Operand::Unknown(name) => f.write_str(name),
+            Operand::Negated(register) => write!(f, "!{register}"),
+            Operand::PredicatePair(first, second) => write!(f, "{first}|{second}"),
+            Operand::Address(address) => match address.offset() {
+                Some(offset) => write!(f, "[{}+{offset}]", address.base()),
+                None => write!(f, "[{}]", address.base()),
+            },
+            Operand::Vector(list) => write!(f, "{{{list}}}"),
+            Operand::List(list) => write!(f, "({list})"),
+        }
+    }
+}
+
+/// Writes the operands separated by `, `, without brackets.
+impl Display for OperandList<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let mut operands = self.operands();
+        if let Some(first) = operands.next() {
+            write!(f, "{first}")?;
+            operands.try_for_each(|operand| write!(f, ", {operand}"))?;
+        }
+        Ok(())
+    }
+}
+
+impl Display for Comment<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Comment::Line(_) => write!(f, "//{}", self.text()),
+            Comment::Block(text) => write!(f, "/*{text}*/"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+#[cfg(test)]
+mod test_round_trip {
+    use crate::{
+        parser::{BodyLine, Function, Item, Operand, PtxFile, Statement},
+        ptx_files::{a, b, c, d, kernel, _EXAMPLE_FILE},
+    };
+
+    fn assert_same_operand(left: Operand, right: Operand) {
+        match (left, right) {
+            (Operand::Vector(left), Operand::Vector(right)) | (Operand::List(left), Operand::List(right)) => {
+                let (left, right): (Vec<_>, Vec<_>) = (left.operands().collect(), right.operands().collect());
+                assert_eq!(left.len(), right.len());
+                left.into_iter().zip(right).for_each(|(left, right)| assert_same_operand(left, right));
+            }
+            (left, right) => assert_eq!(left, right),
+        }
+    }
+
+    /// Compares statements, ignoring spans and whitespace in raw text the parser
+    /// does not interpret.
+    fn assert_same_statement(left: &Statement, right: &Statement) {
+        assert!(left.comments().leading().eq(right.comments().leading()));
+        assert_eq!(left.comments().trailing(), right.comments().trailing());
+        match (left.line(), right.line()) {
+            (BodyLine::Instruction(left), BodyLine::Instruction(right)) => {
+                assert_eq!(left.predicate(), right.predicate());
+                assert_eq!(left.opcode(), right.opcode());
+                assert!(left.modifiers().eq(right.modifiers()));
+                let (left, right): (Vec<_>, Vec<_>) = (left.operands().collect(), right.operands().collect());
+                assert_eq!(left.len(), right.len());
+                left.into_iter().zip(right).for_each(|(left, right)| assert_same_operand(left, right));
+            }
+            (BodyLine::FunctionCall(left), BodyLine::FunctionCall(right)) => {
+                assert_eq!(left.function(), right.function());
+                assert_same_statements(left.statements(), right.statements());
+            }
+            (BodyLine::Register(left), BodyLine::Register(right)) => assert_eq!(left.raw_string(), right.raw_string()),
+            (BodyLine::Directive(left), BodyLine::Directive(right)) => {
+                assert_eq!((left.name(), left.arguments()), (right.name(), right.arguments()));
+            }
+            (BodyLine::Operation(left), BodyLine::Operation(right)) => {
+                assert_eq!((left.operation(), left.arguments()), (right.operation(), right.arguments()));
+            }
+            (BodyLine::Goto(left), BodyLine::Goto(right)) => {
+                assert_eq!(left.predicate(), right.predicate());
+                assert_eq!((left.is_uniform(), left.label()), (right.is_uniform(), right.label()));
+            }
+            (left, right) => assert_eq!(left, right),
+        }
+    }
+
+    fn assert_same_statements<'a>(
+        left: impl Iterator<Item = Result<Statement<'a>, crate::parser::ParseError<'a>>>,
+        right: impl Iterator<Item = Result<Statement<'a>, crate::parser::ParseError<'a>>>,
+    ) {
+        let left: Vec<_> = left.map(Result::unwrap).collect();
+        let right: Vec<_> = right.map(Result::unwrap).collect();
+        assert_eq!(left.len(), right.len());
+        left.iter().zip(&right).for_each(|(left, right)| assert_same_statement(left, right));
+    }
+
+    fn assert_same_function(left: &Function, right: &Function) {
+        let (left_signature, right_signature) = (left.signature(), right.signature());
+        assert_eq!(left_signature.name(), right_signature.name());
+        assert_eq!(left_signature.is_visible(), right_signature.is_visible());
+        assert_eq!(left_signature.is_entry(), right_signature.is_entry());
+        assert_eq!(
+            left_signature.return_value().map(|value| value.raw_string()),
+            right_signature.return_value().map(|value| value.raw_string()),
+        );
+        assert_eq!(
+            left_signature.parameters().map(|parameters| parameters.raw_string()),
+            right_signature.parameters().map(|parameters| parameters.raw_string()),
+        );
+        assert!(left.comments().leading().eq(right.comments().leading()));
+        assert_eq!(left.comments().trailing(), right.comments().trailing());
+        match (left.body(), right.body()) {
+            (Some(left), Some(right)) => assert_same_statements(left, right),
+            (left, right) => assert_eq!(left.is_none(), right.is_none()),
+        }
+    }
+
+    fn assert_same(left: &PtxFile, right: &PtxFile) {
+        assert!(left.header_comments().eq(right.header_comments()));
+        assert_eq!(left.preamble().to_string(), right.preamble().to_string());
+        let (left, right): (Vec<_>, Vec<_>) = (left.items().collect(), right.items().collect());
+        assert_eq!(left.len(), right.len());
+        for (left, right) in left.iter().zip(&right) {
+            match (left, right) {
+                (Item::Function(left), Item::Function(right)) => assert_same_function(left, right),
+                (Item::Global(left), Item::Global(right)) => {
+                    assert_eq!(left.raw_string(), right.raw_string());
+                    assert!(left.comments().leading().eq(right.comments().leading()));
+                    assert_eq!(left.comments().trailing(), right.comments().trailing());
+                }
+                _ => panic!("items out of order"),
+            }
+        }
+    }
+
+    #[test]
+    fn fixtures_round_trip() {
+        for input in [a::_PTX, b::_PTX, c::_PTX, d::_PTX, kernel::_PTX, _EXAMPLE_FILE] {
+            let ptx = PtxFile::try_from(input).unwrap();
+            let printed = ptx.to_string();
+            let reparsed = PtxFile::try_from(printed.as_str()).unwrap();
+            assert_same(&ptx, &reparsed);
+            assert_eq!(reparsed.to_string(), printed);
+        }
+    }
+
+    #[test]
+    fn statements() {
+        let input = ".version 7.5\n.target sm_30\n.address_size 64\n\
+            // .globl foo\n.visible .entry foo(.param .u64 x)\n{\n  .reg .pred %p<2>;\n\
+            @!%p1   bra.uni $L__BB0_1;   // skip\n  ld.param.v2.b64 {%rd1,%rd2}, [x+8];\n\
+            $L__BB0_1:\n  .local .align 8 .b8 depot[8];\n  ret.uni;\n}\n";
+        let ptx = PtxFile::try_from(input).unwrap();
+        assert_eq!(ptx.to_string(), ".version 7.5
+.target sm_30
+.address_size 64
+
+// .globl foo
+.visible .entry foo(.param .u64 x)
+{
+\t.reg .pred %p<2>;
+\t@!%p1 bra.uni $L__BB0_1; // skip
+\tld.param.v2.b64 {%rd1, %rd2}, [x+8];
+$L__BB0_1:
+\t.local .align 8 .b8 depot[8];
+\tret.uni;
+}
+");
+    }
+
+    #[test]
+    fn plain_labels() {
+        let input = ".version 7.5\n.target sm_30\n.address_size 64\n.visible .entry foo()\n{\nbra LBB0_2;\nLBB0_2: ret;\n}\n";
+        let ptx = PtxFile::try_from(input).unwrap();
+        assert!(ptx.to_string().ends_with("{\n\tbra LBB0_2;\nLBB0_2:\n\tret;\n}\n"));
+    }
+    #[test]
+    fn qualified_state_spaces() {
+        let body = "{\n\tld.shared::cta.u32 %r1, [%rd1];\n\tmbarrier.arrive.shared::cluster.b64 %rd2, [%rd1];\n\tret;\n}\n";
+        let input = [".version 8.0\n.target sm_90\n.address_size 64\n.visible .entry foo()\n", body].concat();
+        let ptx = PtxFile::try_from(input.as_str()).unwrap();
+        assert!(ptx.to_string().ends_with(body));
+        assert!(ptx.into_owned().to_string().ends_with(body));
+    }
+}
@@ -0,0 +1,238 @@
+//! `Display` impls for the owned AST, printing like the borrowed one.
+
+use core::fmt::{self, Display, Formatter, Write};
+
+use crate::parser::owned::{
+    BodyLine, Comment, Comments, Directive, Function, Global, Goto, Instruction, Item, Modifier, Operand,
+    Operation, Predicate, Preamble, PtxFile, Statement,
+};
+
+use super::indent;
+
+fn write_leading(f: &mut Formatter<'_>, comments: &Comments, depth: usize) -> fmt::Result {
+    comments.leading.iter().try_for_each(|comment| {
+        indent(f, depth)?;
+        writeln!(f, "{comment}")
+    })
+}
+
+fn write_trailing(f: &mut Formatter<'_>, comments: &Comments) -> fmt::Result {
+    match &comments.trailing {
+        Some(comment) => write!(f, " {comment}"),
+        None => Ok(()),
+    }
+}
+
+fn write_statements(f: &mut Formatter<'_>, statements: &[Statement], depth: usize) -> fmt::Result {
+    statements.iter().try_for_each(|statement| {
+        write_statement(f, statement, depth)?;
+        f.write_char('\n')
+    })
+}
+
+fn write_statement(f: &mut Formatter<'_>, statement: &Statement, depth: usize) -> fmt::Result {
+    write_leading(f, &statement.comments, depth)?;
+    if !matches!(statement.line, BodyLine::Label(_)) {
+        indent(f, depth)?;
+    }
+    match &statement.line {
+        BodyLine::FunctionCall(call) => {
+            f.write_str("{\n")?;
+            write_statements(f, &call.statements, depth + 1)?;
+            indent(f, depth)?;
+            f.write_char('}')?;
+        }
+        line => write!(f, "{line}")?,
+    }
+    write_trailing(f, &statement.comments)
+}
+
+impl Display for PtxFile {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        self.header_comments.iter().try_for_each(|comment| writeln!(f, "{comment}"))?;
+        if !self.header_comments.is_empty() {
+            f.write_char('\n')?;
+        }
+        write!(f, "{}", self.preamble)?;
+        self.items.iter().try_for_each(|item| match item {
+            Item::Function(function) => write!(f, "\n{function}"),
+            Item::Global(global) => write!(f, "\n{global}"),
+        })
+    }
+}
+
+impl Display for Preamble {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let (major, minor) = &self.version;
+        writeln!(f, ".version {major}.{minor}")?;
+        writeln!(f, ".target {}", self.target)?;
+        writeln!(f, ".address_size {}", self.address_size)
+    }
+}
+
+impl Display for Global {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write_leading(f, &self.comments, 0)?;
+        write!(f, ".global {};", self.raw_string)?;
+        write_trailing(f, &self.comments)?;
+        f.write_char('\n')
+    }
+}
+
+impl Display for Function {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let signature = &self.signature;
+        write_leading(f, &self.comments, 0)?;
+        if signature.visible {
+            f.write_str(".visible ")?;
+        }
+        f.write_str(if signature.entry { ".entry" } else { ".func" })?;
+        if let Some(return_value) = &signature.return_value {
+            write!(f, " ({return_value})")?;
+        }
+        write!(f, " {}", signature.name)?;
+        if let Some(parameters) = &signature.parameters {
+            write!(f, "({parameters})")?;
+        }
+        match &self.body {
+            Some(statements) => {
+                f.write_str("\n{\n")?;
+                write_statements(f, statements, 1)?;
+                f.write_char('}')?;
+            }
+            None => f.write_char(';')?,
+        }
+        write_trailing(f, &self.comments)?;
+        f.write_char('\n')
+    }
+}
+
+impl Display for Statement {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write_statement(f, self, 0)
+    }
+}
+
+/// Writes a call sequence on one line; use [`Statement`] to indent it.
+impl Display for BodyLine {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            BodyLine::Register(raw_string) => write!(f, ".reg {raw_string};"),
+            BodyLine::Directive(directive) => write!(f, "{directive};"),
+            BodyLine::Instruction(instruction) => write!(f, "{instruction};"),
+            BodyLine::Operation(operation) => write!(f, "{operation};"),
+            BodyLine::Label(label) => write!(f, "{label}:"),
+            BodyLine::Goto(goto) => write!(f, "{goto};"),
+            BodyLine::Return { uniform: false } => f.write_str("ret;"),
+            BodyLine::Return { uniform: true } => f.write_str("ret.uni;"),
+            BodyLine::FunctionCall(call) => {
+                f.write_char('{')?;
+                call.statements.iter().try_for_each(|statement| write!(f, " {}", statement.line))?;
+                f.write_str(" }")
+            }
+            BodyLine::Unknown(raw_string) => write!(f, "{raw_string};"),
+            BodyLine::Error(skipped) => f.write_str(skipped),
+        }
+    }
+}
+
+impl Display for Directive {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, ".{}", self.name)?;
+        if !self.arguments.is_empty() {
+            write!(f, " {}", self.arguments)?;
+        }
+        Ok(())
+    }
+}
+
+impl Display for Operation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.operation, self.arguments)
+    }
+}
+
+impl Display for Goto {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        if let Some(predicate) = &self.predicate {
+            write!(f, "{predicate} ")?;
+        }
+        f.write_str(if self.uniform { "bra.uni" } else { "bra" })?;
+        write!(f, " {}", self.label)
+    }
+}
+
+impl Display for Instruction {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        if let Some(predicate) = &self.predicate {
+            write!(f, "{predicate} ")?;
+        }
+        write!(f, "{}", self.opcode)?;
+        self.modifiers.iter().try_for_each(|modifier| write!(f, ".{modifier}"))?;
+        for (index, operand) in self.operands.iter().enumerate() {
+            write!(f, "{}{operand}", if index == 0 { " " } else { ", " })?;
+        }
+        Ok(())
+    }
+}
+
+impl Display for Predicate {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Predicate::True(register) => write!(f, "@{register}"),
+            Predicate::False(register) => write!(f, "@!{register}"),
+        }
+    }
+}
+
+/// Writes the modifier without its leading `.`.
+impl Display for Modifier {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Modifier::Type(ty) => ty.fmt(f),
+            Modifier::StateSpace(space, None) => space.fmt(f),
+            Modifier::StateSpace(space, Some(qualifier)) => write!(f, "{space}::{qualifier}"),
+            Modifier::Vector(width) => write!(f, "v{width}"),
+            Modifier::Known(modifier) | Modifier::Unknown(modifier) => f.write_str(modifier),
+        }
+    }
+}
+
+impl Display for Operand {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let list = |f: &mut Formatter<'_>, operands: &[Operand]| {
+            operands.iter().enumerate().try_for_each(|(index, operand)| {
+                write!(f, "{}{operand}", if index == 0 { "" } else { ", " })
+            })
+        };
+        match self {
+            Operand::Register(name)
+            | Operand::Immediate(name)
+            | Operand::Symbol(name)
+            | Operand::Unknown(name) => f.write_str(name),
+            Operand::Negated(register) => write!(f, "!{register}"),
+            Operand::PredicatePair(first, second) => write!(f, "{first}|{second}"),
+            Operand::Address { base, offset: Some(offset) } => write!(f, "[{base}+{offset}]"),
+            Operand::Address { base, offset: None } => write!(f, "[{base}]"),
+            Operand::Vector(operands) => {
+                f.write_char('{')?;
+                list(f, operands)?;
+                f.write_char('}')
+            }
+            Operand::List(operands) => {
+                f.write_char('(')?;
+                list(f, operands)?;
+                f.write_char(')')
+            }
+        }
+    }
+}
+
+impl Display for Comment {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Comment::Line(text) => write!(f, "//{text}"),
+            Comment::Block(text) => write!(f, "/*{text}*/"),
+        }
+    }
+}
@@ -1,11 +1,15 @@
-use nom::{branch::alt, combinator::opt, sequence::preceded, IResult, Parser};
+use nom::{branch::alt, combinator::opt, Parser};
 
 use super::{
     function::{Function, parse::parse_function},
     global::{Global, parse::parse_global},
     PtxParser,
-    comment::parse::many1_comments_or_whitespace,
+    comment::{parse::many1_comments_or_whitespace, trailing_comment, Comments},
+    error::{parse::expect, Diagnostic, Expected, ParseError},
+    recover, MODULE_DIRECTIVES,
 };
+#[cfg(feature = "std")]
+use super::{ItemKind, ParseOptions, PtxFile};
 
 mod try_from;
 
@@ -16,36 +20,100 @@ pub enum FunctionOrGlobal<'a> {
 }
 
 impl<'a> Iterator for PtxParser<'a> {
-    type Item = IResult<&'a str, FunctionOrGlobal<'a>>;
+    type Item = Result<FunctionOrGlobal<'a>, ParseError<'a>>;
 
+    /// Yields the next module item. After an error, parsing resumes at the
+    /// next `;`, `}` or module-level directive.
     fn next(&mut self) -> Option<Self::Item> {
+        self.next_recovering()
+            .map(|item| item.map_err(Diagnostic::into_error))
+    }
+}
+
+impl<'a> PtxParser<'a> {
+    pub(crate) fn next_recovering(&mut self) -> Option<Result<FunctionOrGlobal<'a>, Diagnostic<'a>>> {
         let body = self.body?;
-        Some(match preceded(
-                opt(many1_comments_or_whitespace),
-                alt((
-                parse_function
+        let item = opt(many1_comments_or_whitespace)(body)
+            .map_or(body, |(item, _)| item);
+        if item.is_empty() {
+            self.body = None;
+            return None
+        }
+        let leading = &body[..body.len() - item.len()];
+        Some(match expect(
+            Expected::Description("a function or global declaration"),
+            alt((
+                parse_function(self.source, self.options)
                 .map(FunctionOrGlobal::Function),
-                parse_global
+                parse_global(self.source)
                 .map(FunctionOrGlobal::Global),
-        )))(body) {
+            )),
+        )(item) {
             Ok((body, value)) => {
+                let (body, trailing) = trailing_comment(body);
                 self.body = Some(body);
-                Ok((body, value))
+                let comments = Comments::new(leading, trailing);
+                Ok(match value {
+                    FunctionOrGlobal::Function(function) => FunctionOrGlobal::Function(function.with_comments(comments)),
+                    FunctionOrGlobal::Global(global) => FunctionOrGlobal::Global(global.with_comments(comments)),
+                })
             }
-            err => {
-                let body = self.body?.trim();
-                if body.is_empty() {
-                    self.body = None;
-                    return None
-                } else {
-                    self.body = Some(body);
-                    err
-                }
+            Err(err) => {
+                let body = recover(item, MODULE_DIRECTIVES);
+                self.body = Some(body);
+                Err(Diagnostic::new(
+                    ParseError::from_nom(self.source, err),
+                    &item[..item.len() - body.len()],
+                ))
             }
         })
     }
 }
 
+#[cfg(feature = "std")]
+impl<'a> PtxFile<'a> {
+    /// Parses a whole module, recovering from errors in module items and
+    /// function bodies.
+    ///
+    /// Returns the items that parsed along with a diagnostic for each error.
+    /// Only a malformed preamble is fatal.
+    pub fn parse_with_diagnostics(source: &'a str) -> Result<(Self, Vec<Diagnostic<'a>>), ParseError<'a>> {
+        Self::parse_with_options(source, ParseOptions::default())
+    }
+
+    /// Like [`PtxFile::parse_with_diagnostics`], with the given options.
+    pub fn parse_with_options(
+        source: &'a str,
+        options: ParseOptions,
+    ) -> Result<(Self, Vec<Diagnostic<'a>>), ParseError<'a>> {
+        let mut ptx = PtxParser::with_options(source, options)?;
+        let mut functions = Vec::new();
+        let mut globals = Vec::new();
+        let mut order = Vec::new();
+        let mut diagnostics = Vec::new();
+        while let Some(item) = ptx.next_recovering() {
+            match item {
+                Ok(FunctionOrGlobal::Function(function)) => {
+                    if let Some(mut body) = function.body() {
+                        while let Some(line) = body.next_recovering() {
+                            diagnostics.extend(line.err());
+                        }
+                    }
+                    functions.push(function);
+                    order.push(ItemKind::Function);
+                }
+                Ok(FunctionOrGlobal::Global(global)) => {
+                    globals.push(global);
+                    order.push(ItemKind::Global);
+                }
+                Err(diagnostic) => diagnostics.push(diagnostic),
+            }
+        }
+        let preamble = ptx.preamble;
+        Ok((PtxFile { source, preamble, functions, globals, order }, diagnostics))
+    }
+}
+
 #[cfg(feature = "std")]
 #[cfg(test)]
 mod test_iterator {
@@ -70,3 +138,239 @@ mod test_iterator {
         }
     }
 }
+
+#[cfg(test)]
+mod test_errors {
+    use crate::parser::{Expected, PtxParser};
+
+    const PREAMBLE: &str = ".version 7.5\n.target sm_30\n.address_size 64\n";
+
+    #[test]
+    fn missing_target() {
+        let error = PtxParser::try_from(".version 7.5\n.address_size 64\n").unwrap_err();
+        assert_eq!((error.line(), error.column()), (2, 1));
+        assert_eq!(error.expected(), Some(&Expected::Token(".target")));
+        assert_eq!(error.context().unwrap().construct(), "target directive");
+    }
+
+    #[test]
+    fn unclosed_parameter_list() {
+        let input = [PREAMBLE, ".func foo(\n\t.param .b64 x\n;\n"].concat();
+        let mut ptx = PtxParser::try_from(input.as_str()).unwrap();
+        let error = ptx.next().unwrap().unwrap_err();
+        assert_eq!((error.line(), error.column()), (4, 10));
+        assert_eq!(error.expected(), Some(&Expected::Closing(')')));
+        let context = error.context().unwrap();
+        assert_eq!(context.construct(), "parameter list");
+        assert_eq!(context.owner(), Some(("function", "foo")));
+    }
+
+    #[test]
+    fn unknown_directive() {
+        let input = [PREAMBLE, "\n.shared foo;\n"].concat();
+        let mut ptx = PtxParser::try_from(input.as_str()).unwrap();
+        let error = ptx.next().unwrap().unwrap_err();
+        assert_eq!((error.line(), error.column()), (5, 1));
+        assert_eq!(
+            error.expected(),
+            Some(&Expected::Description("a function or global declaration")),
+        );
+    }
+}
+
+#[cfg(feature = "std")]
+#[cfg(test)]
+mod test_recovery {
+    use crate::parser::{FunctionOrGlobal, PtxFile, PtxParser};
+
+    const PREAMBLE: &str = ".version 7.5\n.target sm_30\n.address_size 64\n";
+
+    #[test]
+    fn skips_unknown_directive() {
+        let input = [PREAMBLE, ".shared .b8 foo[4];\n.global bar;\n"].concat();
+        let mut ptx = PtxParser::try_from(input.as_str()).unwrap();
+        assert!(ptx.next().unwrap().is_err());
+        assert!(matches!(ptx.next(), Some(Ok(FunctionOrGlobal::Global(_)))));
+        assert!(ptx.next().is_none());
+    }
+
+    #[test]
+    fn trailing_comment_ends_iteration() {
+        let input = [PREAMBLE, ".global bar;\n// trailing comment\n"].concat();
+        let ptx = PtxParser::try_from(input.as_str()).unwrap();
+        assert_eq!(ptx.filter(Result::is_err).count(), 0);
+    }
+
+    #[test]
+    fn skips_function_with_bad_signature() {
+        let input = [
+            PREAMBLE,
+            ".visible .entry\n{\n\tret;\n}\n",
+            ".func foo\n{\n\tret;\n}\n",
+        ].concat();
+        let (ptx, diagnostics) = PtxFile::parse_with_diagnostics(&input).unwrap();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].skipped(), ".visible .entry\n{\n\tret;\n}");
+        assert_eq!(ptx.functions().len(), 1);
+        assert_eq!(ptx.functions()[0].name(), "foo");
+    }
+
+    #[test]
+    fn reports_bad_statements_and_keeps_going() {
+        let input = [
+            PREAMBLE,
+            ".func foo\n{\n\tret;\n\tunterminated\n}\n",
+            ".global bar;\n",
+        ].concat();
+        let (ptx, diagnostics) = PtxFile::parse_with_diagnostics(&input).unwrap();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].error().line(), 8);
+        assert_eq!(diagnostics[0].error().context().unwrap().owner(), Some(("function", "foo")));
+        assert_eq!(ptx.functions().len(), 1);
+        assert_eq!(ptx.globals().len(), 1);
+        assert!(PtxFile::try_from(input.as_str()).is_err());
+    }
+
+    #[test]
+    fn fixtures_have_no_diagnostics() {
+        use crate::ptx_files::{a, b, c, d, kernel, _EXAMPLE_FILE};
+        for input in [a::_PTX, b::_PTX, c::_PTX, d::_PTX, kernel::_PTX, _EXAMPLE_FILE] {
+            let (_, diagnostics) = PtxFile::parse_with_diagnostics(input).unwrap();
+            assert_eq!(diagnostics, vec![]);
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+#[cfg(test)]
+mod test_strict {
+    use crate::parser::{BodyLine, Expected, ParseOptions, PtxFile};
+
+    const PREAMBLE: &str = ".version 7.5\n.target sm_30\n.address_size 64\n";
+
+    #[test]
+    fn fixtures_are_fully_understood() {
+        use crate::ptx_files::{a, b, c, d, kernel, _EXAMPLE_FILE};
+        for input in [a::_PTX, b::_PTX, c::_PTX, d::_PTX, kernel::_PTX, _EXAMPLE_FILE] {
+            let (_, diagnostics) = PtxFile::parse_with_options(input, ParseOptions::strict()).unwrap();
+            assert_eq!(diagnostics, vec![]);
+        }
+    }
+
+    #[test]
+    fn unknown_opcode() {
+        let input = [PREAMBLE, ".func foo\n{\n\tfrobnicate.u32 %r1, %r2;\n\tret;\n}\n"].concat();
+        let (ptx, diagnostics) = PtxFile::parse_with_diagnostics(&input).unwrap();
+        assert!(diagnostics.is_empty());
+        let mut body = ptx.functions()[0].body().unwrap();
+        assert!(matches!(body.next(), Some(Ok(statement)) if matches!(statement.line(), BodyLine::Operation(_))));
+
+        let (ptx, diagnostics) = PtxFile::parse_with_options(&input, ParseOptions::strict()).unwrap();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].skipped(), "frobnicate.u32 %r1, %r2;");
+        assert_eq!(diagnostics[0].error().expected(), Some(&Expected::Description("a known opcode")));
+        assert_eq!((diagnostics[0].error().line(), diagnostics[0].error().column()), (6, 2));
+        let statements: Vec<_> = ptx.functions()[0].body().unwrap().filter_map(Result::ok).collect();
+        assert_eq!(statements.len(), 1);
+    }
+
+    #[test]
+    fn unknown_modifier_and_directive() {
+        let input = [
+            PREAMBLE,
+            ".func foo\n{\n\t.frob 1;\n\tadd.bogus.u32 %r1, %r2, 1;\n\tret;\n}\n",
+        ].concat();
+        let (_, diagnostics) = PtxFile::parse_with_diagnostics(&input).unwrap();
+        assert!(diagnostics.is_empty());
+
+        let (_, diagnostics) = PtxFile::parse_with_options(&input, ParseOptions::strict()).unwrap();
+        let expected: Vec<_> = diagnostics.iter()
+            .map(|diagnostic| (diagnostic.error().column(), diagnostic.error().expected().copied()))
+            .collect();
+        assert_eq!(expected, [
+            (3, Some(Expected::Description("a known directive"))),
+            (6, Some(Expected::Description("a known modifier"))),
+        ]);
+    }
+
+    #[test]
+    fn checks_call_sequences() {
+        let input = [
+            PREAMBLE,
+            ".func foo\n{\n\t{ // callseq 0, 0\n\t.param .b64 param0;\n\tfrob.b64 [param0+0], 1;\n",
+            "\tcall.uni \n\tbar, \n\t(\n\tparam0\n\t);\n\t} // callseq 0\n\tret;\n}\n",
+        ].concat();
+        let (_, diagnostics) = PtxFile::parse_with_diagnostics(&input).unwrap();
+        assert!(diagnostics.is_empty());
+
+        let (_, diagnostics) = PtxFile::parse_with_options(&input, ParseOptions::strict()).unwrap();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].error().line(), 8);
+        assert!(diagnostics[0].skipped().starts_with("{ // callseq 0, 0"));
+        assert!(diagnostics[0].skipped().ends_with("\t}"));
+    }
+}
+
+#[cfg(feature = "std")]
+#[cfg(test)]
+mod test_comments {
+    use crate::parser::{BodyLine, Comment, FunctionOrGlobal, PtxFile, PtxParser};
+
+    const INPUT: &str = "//
+// Generated by LLVM NVPTX Back-End
+//
+
+.version 7.5
+.target sm_30
+.address_size 64
+
+.global .u32 counter; // shared counter
+
+	// .globl	foo
+.visible .entry foo()
+{
+	mov.u32 	%r1, %tid.x; // thread
+	{ // callseq 0, 0
+	call.uni
+	bar,
+	(
+	);
+	} // callseq 0
+// %bb.1:
+	ret;
+}
+";
+
+    fn texts<'a>(comments: impl Iterator<Item = Comment<'a>>) -> Vec<&'a str> {
+        comments.map(|comment| comment.text()).collect()
+    }
+
+    #[test]
+    fn module_items() {
+        let mut ptx = PtxParser::try_from(INPUT).unwrap();
+        assert_eq!(texts(ptx.header_comments()), ["", " Generated by LLVM NVPTX Back-End", ""]);
+        let Some(Ok(FunctionOrGlobal::Global(global))) = ptx.next() else { panic!() };
+        assert_eq!(global.comments().leading().count(), 0);
+        assert_eq!(global.comments().trailing(), Some(&Comment::Line(" shared counter")));
+        let Some(Ok(FunctionOrGlobal::Function(function))) = ptx.next() else { panic!() };
+        assert_eq!(texts(function.comments().leading()), [" .globl\tfoo"]);
+        assert_eq!(function.comments().trailing(), None);
+    }
+
+    #[test]
+    fn statements() {
+        let (ptx, diagnostics) = PtxFile::parse_with_diagnostics(INPUT).unwrap();
+        assert!(diagnostics.is_empty());
+        assert_eq!(ptx.header_comments().count(), 3);
+        let statements: Vec<_> = ptx.functions()[0].body().unwrap().map(Result::unwrap).collect();
+        assert_eq!(statements[0].comments().trailing(), Some(&Comment::Line(" thread")));
+
+        let BodyLine::FunctionCall(call) = statements[1].line() else { panic!() };
+        assert_eq!(statements[1].comments().trailing(), Some(&Comment::Line(" callseq 0")));
+        let call_statement = call.statements().next().unwrap().unwrap();
+        assert_eq!(texts(call_statement.comments().leading()), [" callseq 0, 0"]);
+
+        assert!(matches!(statements[2].line(), BodyLine::Return { uniform: false }));
+        assert_eq!(texts(statements[2].comments().leading()), [" %bb.1:"]);
+    }
+}
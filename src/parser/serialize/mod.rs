@@ -0,0 +1,178 @@
+//! `Serialize` impls for the borrowed AST that cannot be derived.
+//!
+//! The borrowed AST serializes to the same shape as the owned one, apart
+//! from extra spans, so JSON dumped from a parse can be loaded back as an
+//! [`owned::PtxFile`](super::owned::PtxFile). Lazily parsed bodies are
+//! written out statement by statement.
+//!
+//! The borrowed types only implement `Serialize`. They point into the
+//! source they were parsed from, which deserialized data has no
+//! counterpart for, and bodies are parsed on demand from that source, so
+//! deserialization goes through the owned types instead.
+
+use serde::{ser::SerializeStruct, Serialize, Serializer};
+
+use super::{
+    function::body::FunctionBody, AddressSize, Comments, Instruction, LeadingComments, Modifiers,
+    OperandList, Operands, Parameters, Register, ReturnValue, Span, Statement, Target, Version,
+};
+#[cfg(feature = "std")]
+use super::PtxFile;
+
+/// A statement of a body, or source skipped after a parse error, written
+/// like an owned `Statement` with a `BodyLine::Error`.
+#[derive(Serialize)]
+#[serde(untagged)]
+enum Entry<'a> {
+    Statement(Statement<'a>),
+    Error { span: Span, line: ErrorLine<'a>, comments: Comments<'a> },
+}
+
+#[derive(Serialize)]
+enum ErrorLine<'a> {
+    Error(&'a str),
+}
+
+impl Serialize for FunctionBody<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut body = self.clone();
+        let source = body.source();
+        serializer.collect_seq(core::iter::from_fn(|| body.next_recovering()).map(|statement| match statement {
+            Ok(statement) => Entry::Statement(statement),
+            Err(diagnostic) => Entry::Error {
+                span: Span::of(source, diagnostic.skipped()),
+                line: ErrorLine::Error(diagnostic.skipped()),
+                comments: Comments::default(),
+            },
+        }))
+    }
+}
+
+#[cfg(feature = "std")]
+impl Serialize for PtxFile<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        struct Items<'r, 'a>(&'r PtxFile<'a>);
+
+        impl Serialize for Items<'_, '_> {
+            fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                serializer.collect_seq(self.0.items())
+            }
+        }
+
+        let mut state = serializer.serialize_struct("PtxFile", 3)?;
+        state.serialize_field("header_comments", &self.header_comments())?;
+        state.serialize_field("preamble", self.preamble())?;
+        state.serialize_field("items", &Items(self))?;
+        state.end()
+    }
+}
+
+impl Serialize for Instruction<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("Instruction", 5)?;
+        state.serialize_field("span", &self.span())?;
+        state.serialize_field("predicate", &self.predicate())?;
+        state.serialize_field("opcode", &self.opcode())?;
+        state.serialize_field("modifiers", &self.modifiers())?;
+        state.serialize_field("operands", &self.operands())?;
+        state.end()
+    }
+}
+
+impl Serialize for Comments<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("Comments", 2)?;
+        state.serialize_field("leading", &self.leading())?;
+        state.serialize_field("trailing", &self.trailing())?;
+        state.end()
+    }
+}
+
+impl Serialize for LeadingComments<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.clone())
+    }
+}
+
+impl Serialize for Modifiers<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.clone())
+    }
+}
+
+impl Serialize for Operands<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.clone())
+    }
+}
+
+/// Written as the list of operands.
+impl Serialize for OperandList<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.operands())
+    }
+}
+
+/// Written as `(major, minor)`.
+impl Serialize for Version<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        (self.major(), self.minor()).serialize(serializer)
+    }
+}
+
+impl Serialize for Target<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.target())
+    }
+}
+
+impl Serialize for AddressSize<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.size())
+    }
+}
+
+impl Serialize for Register<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.raw_string())
+    }
+}
+
+impl Serialize for ReturnValue<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.raw_string())
+    }
+}
+
+impl Serialize for Parameters<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.raw_string())
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod test_serialize {
+    use serde_json::json;
+
+    use crate::parser::PtxFile;
+
+    #[test]
+    fn shape() {
+        let input = ".version 7.5\n.target sm_30\n.address_size 64\n\
+            .visible .entry foo()\n{\n\t@!%p1 ld.param.v2.u32 {%r1, %r2}, [foo_param_0+8]; // load\n}\n";
+        let ptx = PtxFile::try_from(input).unwrap();
+        let json = serde_json::to_value(&ptx).unwrap();
+        assert_eq!(json["preamble"]["version"], json!(["7", "5"]));
+        assert_eq!(json["items"][0]["Function"]["signature"]["name"], "foo");
+        let statement = &json["items"][0]["Function"]["body"][0];
+        assert_eq!(statement["comments"]["trailing"], json!({ "Line": " load" }));
+        let instruction = &statement["line"]["Instruction"];
+        assert_eq!(instruction["predicate"], json!({ "False": "%p1" }));
+        assert_eq!(instruction["opcode"], "Ld");
+        assert_eq!(instruction["modifiers"], json!([{ "StateSpace": ["Param", null] }, { "Vector": 2 }, { "Type": "U32" }]));
+        assert_eq!(instruction["operands"], json!([
+            { "Vector": [{ "Register": "%r1" }, { "Register": "%r2" }] },
+            { "Address": { "base": "foo_param_0", "offset": "8" } },
+        ]));
+    }
+}
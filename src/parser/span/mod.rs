@@ -0,0 +1,145 @@
+use nom::Offset;
+
+/// A byte range into the source a node was parsed from.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+/// A 1-based line and column. Columns count `char`s.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LineColumn {
+    pub line: usize,
+    pub column: usize,
+}
+
+/// Converts byte offsets into a source to lines and columns.
+#[cfg(feature = "std")]
+#[derive(Debug, Clone)]
+pub struct SourceMap<'a> {
+    source: &'a str,
+    line_starts: Vec<usize>,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize) -> Self {
+        Self { start, end }
+    }
+
+    /// The span of `slice`, which must be a subslice of `source`.
+    pub(crate) fn of(source: &str, slice: &str) -> Self {
+        let start = source.offset(slice);
+        Self { start, end: start + slice.len() }
+    }
+
+    pub fn len(&self) -> usize {
+        self.end - self.start
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+
+    pub fn contains(&self, offset: usize) -> bool {
+        self.start <= offset && offset < self.end
+    }
+
+    /// The smallest span covering both `self` and `other`.
+    pub fn join(&self, other: Span) -> Self {
+        Self {
+            start: self.start.min(other.start),
+            end: self.end.max(other.end),
+        }
+    }
+
+    /// The text this span covers in `source`.
+    pub fn text<'a>(&self, source: &'a str) -> &'a str {
+        &source[self.start..self.end]
+    }
+}
+
+#[cfg(feature = "std")]
+impl<'a> SourceMap<'a> {
+    pub fn new(source: &'a str) -> Self {
+        let line_starts = core::iter::once(0)
+            .chain(source.match_indices('\n').map(|(i, _)| i + 1))
+            .collect();
+        Self { source, line_starts }
+    }
+
+    pub fn source(&self) -> &'a str {
+        self.source
+    }
+
+    pub fn line_count(&self) -> usize {
+        self.line_starts.len()
+    }
+
+    /// The line and column of the byte at `offset`.
+    pub fn line_column(&self, offset: usize) -> LineColumn {
+        let offset = offset.min(self.source.len());
+        let line = self.line_starts.partition_point(|&start| start <= offset);
+        let start = self.line_starts[line - 1];
+        LineColumn {
+            line,
+            column: self.source[start..offset].chars().count() + 1,
+        }
+    }
+
+    /// The positions of the first byte and one past the last byte of `span`.
+    pub fn locate(&self, span: Span) -> (LineColumn, LineColumn) {
+        (self.line_column(span.start), self.line_column(span.end))
+    }
+
+    /// The text of the 1-based `line`, without its line ending.
+    pub fn line(&self, line: usize) -> Option<&'a str> {
+        let start = *self.line_starts.get(line.checked_sub(1)?)?;
+        let end = self.line_starts.get(line).map_or(self.source.len(), |&next| next - 1);
+        Some(self.source[start..end].trim_end_matches('\r'))
+    }
+
+    pub fn text(&self, span: Span) -> &'a str {
+        span.text(self.source)
+    }
+}
+
+#[cfg(feature = "std")]
+#[cfg(test)]
+mod test_source_map {
+    use super::{LineColumn, SourceMap, Span};
+
+    const SOURCE: &str = ".version 7.5\n.target sm_30\r\n\n// wörld ;";
+
+    #[test]
+    fn line_column() {
+        let map = SourceMap::new(SOURCE);
+        assert_eq!(map.line_column(0), LineColumn { line: 1, column: 1 });
+        assert_eq!(map.line_column(13), LineColumn { line: 2, column: 1 });
+        assert_eq!(map.line_column(21), LineColumn { line: 2, column: 9 });
+        assert_eq!(map.line_column(SOURCE.len() - 1), LineColumn { line: 4, column: 10 });
+    }
+
+    #[test]
+    fn locate() {
+        let map = SourceMap::new(SOURCE);
+        assert_eq!(
+            map.locate(Span::new(13, 26)),
+            (LineColumn { line: 2, column: 1 }, LineColumn { line: 2, column: 14 }),
+        );
+        assert_eq!(map.text(Span::new(13, 26)), ".target sm_30");
+    }
+
+    #[test]
+    fn lines() {
+        let map = SourceMap::new(SOURCE);
+        assert_eq!(map.line_count(), 4);
+        assert_eq!(map.line(2), Some(".target sm_30"));
+        assert_eq!(map.line(3), Some(""));
+        assert_eq!(map.line(4), Some("// wörld ;"));
+        assert_eq!(map.line(0), None);
+        assert_eq!(map.line(5), None);
+    }
+}
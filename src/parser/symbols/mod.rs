@@ -0,0 +1,641 @@
+//! Name resolution.
+//!
+//! PTX names live in nested scopes: the module declares functions and
+//! variables, a function body declares its parameters, registers, locals
+//! and labels, and each call sequence `{ ... }` is a block that declares
+//! the `.param` variables passed to the callee. [`SymbolTable`] resolves
+//! every name used by an instruction, predicate or branch to the innermost
+//! declaration that is in scope.
+
+use std::collections::HashMap;
+
+use super::{
+    global::declared_name,
+    instruction::parse::is_immediate,
+    visit::{walk_body, walk_function},
+    BodyLine, Function, FunctionBody, Instruction, Item, Operand, Predicate, PtxFile, Span, Statement,
+    Visitor,
+};
+
+/// Special registers, without their `.x`, `.y` or `.z` suffix.
+const SPECIAL_REGISTERS: &[&str] = &[
+    "%tid", "%ntid", "%laneid", "%warpid", "%nwarpid", "%ctaid", "%nctaid", "%smid", "%nsmid",
+    "%gridid", "%is_explicit_cluster", "%clusterid", "%nclusterid", "%cluster_ctaid",
+    "%cluster_nctaid", "%cluster_ctarank", "%cluster_nctarank", "%lanemask_eq", "%lanemask_le",
+    "%lanemask_lt", "%lanemask_ge", "%lanemask_gt", "%clock", "%clock_hi", "%clock64",
+    "%globaltimer", "%globaltimer_lo", "%globaltimer_hi", "%total_smem_size", "%aggr_smem_size",
+    "%dynamic_smem_size", "%current_graph_exec", "WARP_SZ",
+];
+
+/// Special registers that come in numbered variants, e.g. `%envreg3`.
+const NUMBERED_SPECIAL_REGISTERS: &[&str] = &["%pm", "%envreg", "%reserved_smem_offset_"];
+
+/// Opcodes whose first operand is read rather than written.
+const NO_DESTINATION: &[&str] = &[
+    "applypriority", "bar", "barrier", "bra", "brkpt", "brx", "discard", "exit", "fence",
+    "griddepcontrol", "membar", "nanosleep", "pmevent", "prefetch", "prefetchu", "red", "ret",
+    "setmaxnreg", "st", "stackrestore", "sured", "sust", "trap",
+];
+
+/// Opcodes that take an address first but do not store to it.
+const READ_ONLY_ADDRESS: &[&str] = &["applypriority", "prefetch", "prefetchu"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct SymbolId(usize);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ScopeId(usize);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SymbolKind {
+    Function,
+    /// A module-level variable.
+    Variable,
+    /// A function parameter or return value, or a `.param` declared in a body.
+    Parameter,
+    /// A `.local`, `.shared` or `.const` variable declared in a body.
+    Local,
+    Register,
+    Label,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Symbol<'a> {
+    name: &'a str,
+    kind: SymbolKind,
+    span: Span,
+    scope: ScopeId,
+    count: Option<u32>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScopeKind<'a> {
+    Module,
+    Function(&'a str),
+    /// A call sequence.
+    Block,
+}
+
+#[derive(Debug, Clone)]
+pub struct Scope<'a> {
+    kind: ScopeKind<'a>,
+    span: Span,
+    parent: Option<ScopeId>,
+    names: HashMap<&'a str, SymbolId>,
+}
+
+/// A use of a name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Reference<'a> {
+    name: &'a str,
+    span: Span,
+    scope: ScopeId,
+    resolution: Resolution,
+    kind: UseKind,
+}
+
+/// How a [`Reference`] uses its symbol.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum UseKind {
+    /// A register or predicate read, or a load from a variable.
+    Read,
+    /// A register written by an instruction, or a store to a variable.
+    Write,
+    /// A variable or function used as a value, as in `mov.u64 %rd1, buffer;`.
+    AddressTaken,
+    Call,
+    /// The target of a branch.
+    Branch,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Resolution {
+    Symbol(SymbolId),
+    /// A special register such as `%tid.x`, which needs no declaration.
+    Special,
+    Unresolved,
+}
+
+#[derive(Debug, Clone)]
+pub struct SymbolTable<'a> {
+    symbols: Vec<Symbol<'a>>,
+    scopes: Vec<Scope<'a>>,
+    references: Vec<Reference<'a>>,
+}
+
+impl<'a> SymbolTable<'a> {
+    pub fn new(ptx: &PtxFile<'a>) -> Self {
+        let source = ptx.source();
+        let mut table = Self { symbols: Vec::new(), scopes: Vec::new(), references: Vec::new() };
+        let module = table.push_scope(ScopeKind::Module, Span::new(0, source.len()), None);
+        for item in ptx.items() {
+            match item {
+                // A definition takes the place of an earlier declaration.
+                Item::Function(function) => match table.scopes[module.0].names.get(function.name()) {
+                    Some(id) if !function.is_declaration() => {
+                        table.symbols[id.0].span = Span::of(source, function.name());
+                    }
+                    Some(_) => {}
+                    None => table.declare(source, module, function.name(), SymbolKind::Function, None),
+                },
+                Item::Global(global) => {
+                    for name in global.names() {
+                        table.declare(source, module, name, SymbolKind::Variable, None);
+                    }
+                }
+            }
+        }
+        let mut resolver = Resolver { table: &mut table, source, scope: module };
+        ptx.functions().iter().for_each(|function| resolver.visit_function(function));
+        table.references.sort_by_key(|reference| reference.span.start);
+        table
+    }
+
+    pub fn module_scope(&self) -> ScopeId {
+        ScopeId(0)
+    }
+
+    /// The scope of a function body, if the function is defined.
+    pub fn function_scope(&self, name: &str) -> Option<ScopeId> {
+        let index = self.scopes.iter().position(|scope| scope.kind == ScopeKind::Function(name))?;
+        Some(ScopeId(index))
+    }
+
+    pub fn scope(&self, id: ScopeId) -> &Scope<'a> {
+        &self.scopes[id.0]
+    }
+
+    pub fn symbol(&self, id: SymbolId) -> &Symbol<'a> {
+        &self.symbols[id.0]
+    }
+
+    pub fn symbols(&self) -> impl Iterator<Item = (SymbolId, &Symbol<'a>)> {
+        self.symbols.iter().enumerate().map(|(index, symbol)| (SymbolId(index), symbol))
+    }
+
+    /// Every use of a name, in source order.
+    pub fn references(&self) -> &[Reference<'a>] {
+        &self.references
+    }
+
+    pub fn unresolved(&self) -> impl Iterator<Item = &Reference<'a>> {
+        self.references.iter().filter(|reference| reference.resolution == Resolution::Unresolved)
+    }
+
+    /// Resolves `name` as if it were used in `scope`.
+    ///
+    /// A register like `%r3` resolves to a parameterized declaration
+    /// `%r<N>` when `3 < N`.
+    pub fn lookup(&self, scope: ScopeId, name: &str) -> Resolution {
+        let mut scope = Some(scope);
+        while let Some(id) = scope {
+            let current = &self.scopes[id.0];
+            if let Some(symbol) = current.names.get(name).copied().or_else(|| self.lookup_numbered(current, name)) {
+                return Resolution::Symbol(symbol)
+            }
+            scope = current.parent;
+        }
+        match is_special_register(name) {
+            true => Resolution::Special,
+            false => Resolution::Unresolved,
+        }
+    }
+
+    fn lookup_numbered(&self, scope: &Scope<'a>, name: &str) -> Option<SymbolId> {
+        let prefix = name.trim_end_matches(|c: char| c.is_ascii_digit());
+        let index: u32 = name[prefix.len()..].parse().ok()?;
+        let id = *scope.names.get(prefix)?;
+        (index < self.symbols[id.0].count?).then_some(id)
+    }
+
+    /// The use of a name covering `offset`.
+    pub fn reference_at(&self, offset: usize) -> Option<&Reference<'a>> {
+        let index = self.references.partition_point(|reference| reference.span.end <= offset);
+        self.references.get(index).filter(|reference| reference.span.contains(offset))
+    }
+
+    /// The symbol named at `offset`, either by a use or by its declaration.
+    pub fn symbol_at(&self, offset: usize) -> Option<SymbolId> {
+        match self.reference_at(offset) {
+            Some(reference) => match reference.resolution {
+                Resolution::Symbol(id) => Some(id),
+                _ => None,
+            },
+            None => self.symbols.iter().position(|symbol| symbol.span.contains(offset)).map(SymbolId),
+        }
+    }
+
+    fn push_scope(&mut self, kind: ScopeKind<'a>, span: Span, parent: Option<ScopeId>) -> ScopeId {
+        self.scopes.push(Scope { kind, span, parent, names: HashMap::new() });
+        ScopeId(self.scopes.len() - 1)
+    }
+
+    /// Declares `name`, a subslice of `source`, unless `scope` already has it.
+    fn declare(&mut self, source: &'a str, scope: ScopeId, name: &'a str, kind: SymbolKind, count: Option<u32>) {
+        let names = &mut self.scopes[scope.0].names;
+        if name.is_empty() || names.contains_key(name) {
+            return
+        }
+        names.insert(name, SymbolId(self.symbols.len()));
+        self.symbols.push(Symbol { name, kind, span: Span::of(source, name), scope, count });
+    }
+}
+
+impl<'a> Symbol<'a> {
+    /// The declared name; `%r` for `%r<8>`, and labels include their `$`.
+    pub fn name(&self) -> &'a str {
+        self.name
+    }
+
+    pub fn kind(&self) -> SymbolKind {
+        self.kind
+    }
+
+    /// The span of the name in its declaration.
+    pub fn span(&self) -> Span {
+        self.span
+    }
+
+    pub fn scope(&self) -> ScopeId {
+        self.scope
+    }
+
+    /// The number of registers declared by `%r<N>`.
+    pub fn count(&self) -> Option<u32> {
+        self.count
+    }
+}
+
+impl<'a> Scope<'a> {
+    pub fn kind(&self) -> ScopeKind<'a> {
+        self.kind
+    }
+
+    pub fn span(&self) -> Span {
+        self.span
+    }
+
+    pub fn parent(&self) -> Option<ScopeId> {
+        self.parent
+    }
+
+    /// A name declared directly in this scope.
+    pub fn get(&self, name: &str) -> Option<SymbolId> {
+        self.names.get(name).copied()
+    }
+}
+
+impl<'a> Reference<'a> {
+    pub fn name(&self) -> &'a str {
+        self.name
+    }
+
+    pub fn span(&self) -> Span {
+        self.span
+    }
+
+    /// The innermost scope at the use.
+    pub fn scope(&self) -> ScopeId {
+        self.scope
+    }
+
+    pub fn resolution(&self) -> Resolution {
+        self.resolution
+    }
+
+    pub fn kind(&self) -> UseKind {
+        self.kind
+    }
+}
+
+fn is_special_register(name: &str) -> bool {
+    let name = name.strip_suffix(['x', 'y', 'z']).and_then(|name| name.strip_suffix('.')).unwrap_or(name);
+    SPECIAL_REGISTERS.contains(&name)
+        || NUMBERED_SPECIAL_REGISTERS.iter().any(|prefix| {
+            name.strip_prefix(prefix).is_some_and(|rest| {
+                let index = rest.strip_suffix("_64").unwrap_or(rest);
+                !index.is_empty() && index.bytes().all(|b| b.is_ascii_digit())
+            })
+        })
+}
+
+/// Declares the names of each scope before resolving its uses, so that
+/// branches can jump forward.
+struct Resolver<'t, 'a> {
+    table: &'t mut SymbolTable<'a>,
+    source: &'a str,
+    scope: ScopeId,
+}
+
+impl<'a> Resolver<'_, 'a> {
+    fn enter(&mut self, kind: ScopeKind<'a>, span: Span, body: FunctionBody<'a>) -> ScopeId {
+        let parent = self.scope;
+        self.scope = self.table.push_scope(kind, span, Some(parent));
+        self.declare_body(body);
+        parent
+    }
+
+    fn declare_body(&mut self, mut body: FunctionBody<'a>) {
+        while let Some(statement) = body.next_recovering() {
+            let Ok(statement) = statement else { continue };
+            match statement.line() {
+                BodyLine::Register(register) => {
+                    for declaration in register.raw_string().split(',') {
+                        let name = declared_name(declaration);
+                        match name.split_once('<') {
+                            Some((prefix, count)) => {
+                                let count = count.trim_end_matches('>').parse().ok();
+                                self.declare(prefix, SymbolKind::Register, count);
+                            }
+                            None => self.declare(name, SymbolKind::Register, None),
+                        }
+                    }
+                }
+                BodyLine::Directive(directive) => {
+                    let kind = match directive.name() {
+                        "param" => SymbolKind::Parameter,
+                        "local" | "shared" | "const" => SymbolKind::Local,
+                        _ => continue,
+                    };
+                    let declarations = directive.arguments().split('=').next().unwrap_or_default();
+                    declarations.split(',').for_each(|declaration| self.declare(declared_name(declaration), kind, None));
+                }
+                BodyLine::Label(label) => self.declare(label, SymbolKind::Label, None),
+                _ => {}
+            }
+        }
+    }
+
+    fn declare(&mut self, name: &'a str, kind: SymbolKind, count: Option<u32>) {
+        self.table.declare(self.source, self.scope, name, kind, count);
+    }
+
+    fn reference(&mut self, name: &'a str, kind: UseKind) {
+        let resolution = self.table.lookup(self.scope, name);
+        let span = Span::of(self.source, name);
+        self.table.references.push(Reference { name, span, scope: self.scope, resolution, kind });
+    }
+
+    fn predicate(&mut self, predicate: Option<&Predicate<'a>>) {
+        if let Some(Predicate::True(register) | Predicate::False(register)) = predicate {
+            self.reference(register, UseKind::Read);
+        }
+    }
+
+    /// Records the names in an operand. `access` is how registers are
+    /// used and `memory` how a variable in an address is.
+    fn operand(&mut self, operand: &Operand<'a>, access: UseKind, memory: UseKind) {
+        match operand {
+            Operand::Register(name) | Operand::Negated(name) => self.reference(name, access),
+            Operand::PredicatePair(first, second) => {
+                self.reference(first, access);
+                self.reference(second, access);
+            }
+            Operand::Symbol(name) => {
+                let kind = match self.table.lookup(self.scope, name) {
+                    Resolution::Symbol(id) => match self.table.symbol(id).kind() {
+                        SymbolKind::Label => UseKind::Branch,
+                        SymbolKind::Register => access,
+                        _ => UseKind::AddressTaken,
+                    },
+                    _ => access,
+                };
+                self.reference(name, kind);
+            }
+            Operand::Address(address) if !is_immediate(address.base()) => {
+                let kind = match self.table.lookup(self.scope, address.base()) {
+                    Resolution::Symbol(id) if self.table.symbol(id).kind() == SymbolKind::Register => UseKind::Read,
+                    Resolution::Special => UseKind::Read,
+                    _ => memory,
+                };
+                self.reference(address.base(), kind);
+            }
+            Operand::Vector(list) | Operand::List(list) => {
+                list.operands().for_each(|operand| self.operand(&operand, access, memory));
+            }
+            _ => {}
+        }
+    }
+
+    /// `call (retval0), callee, (param0, ...)`: the callee writes the
+    /// parameters before it and reads those after.
+    fn call(&mut self, instruction: &Instruction<'a>) {
+        let mut access = UseKind::Write;
+        for operand in instruction.operands() {
+            match operand {
+                Operand::List(list) => list.operands().for_each(|argument| match argument {
+                    Operand::Symbol(name) => self.reference(name, access),
+                    argument => self.operand(&argument, access, UseKind::Read),
+                }),
+                Operand::Symbol(name) if access == UseKind::Write => {
+                    self.reference(name, UseKind::Call);
+                    access = UseKind::Read;
+                }
+                operand => {
+                    self.operand(&operand, UseKind::Read, UseKind::Read);
+                    access = UseKind::Read;
+                }
+            }
+        }
+    }
+}
+
+impl<'a> Visitor<'a> for Resolver<'_, 'a> {
+    fn visit_function(&mut self, function: &Function<'a>) {
+        let Some(body) = function.body() else { return };
+        let parent = self.enter(ScopeKind::Function(function.name()), function.span(), body);
+        let signature = function.signature();
+        let return_value = signature.return_value().map(|value| value.raw_string());
+        let parameters = signature.parameters().map(|parameters| parameters.raw_string());
+        for declaration in return_value.into_iter().chain(parameters).flat_map(|list| list.split(',')) {
+            self.declare(declared_name(declaration), SymbolKind::Parameter, None);
+        }
+        walk_function(self, function);
+        self.scope = parent;
+    }
+
+    fn visit_statement(&mut self, statement: &Statement<'a>) {
+        match statement.line() {
+            BodyLine::Instruction(instruction) => self.visit_instruction(instruction),
+            BodyLine::Goto(goto) => {
+                self.predicate(goto.predicate());
+                self.reference(goto.label(), UseKind::Branch);
+            }
+            BodyLine::FunctionCall(call) => {
+                let parent = self.enter(ScopeKind::Block, call.span(), call.statements());
+                walk_body(self, call.statements());
+                self.scope = parent;
+            }
+            _ => {}
+        }
+    }
+
+    fn visit_instruction(&mut self, instruction: &Instruction<'a>) {
+        self.predicate(instruction.predicate());
+        let opcode = instruction.opcode().name();
+        if opcode == "call" {
+            return self.call(instruction)
+        }
+        for (index, operand) in instruction.operands().enumerate() {
+            let access = match index == 0 && !NO_DESTINATION.contains(&opcode) {
+                true => UseKind::Write,
+                false => UseKind::Read,
+            };
+            let memory = match (index == 0 && !READ_ONLY_ADDRESS.contains(&opcode)) || opcode == "atom" {
+                true => UseKind::Write,
+                false => UseKind::Read,
+            };
+            self.operand(&operand, access, memory);
+        }
+    }
+}
+#[cfg(test)]
+mod test_symbols {
+    use crate::{
+        parser::PtxFile,
+        ptx_files::{a, b, c, d, kernel, _EXAMPLE_FILE},
+    };
+
+    use super::{Resolution, ScopeKind, SymbolKind, SymbolTable};
+
+    const CALLS: &str = ".version 7.5
+.target sm_30
+.address_size 64
+
+.global .u32 counter;
+.func (.param .b32 ret) callee(.param .b32 x);
+
+.visible .entry caller(.param .u64 caller_param_0)
+{
+	.reg .pred %p<2>;
+	.reg .b32 %r<3>;
+	.local .align 4 .b8 depot[8];
+
+	ld.global.u32 %r1, [counter];
+	setp.eq.s32 %p1, %r1, 0;
+	@%p1 bra $L__done;
+	{
+		.param .b32 param0;
+		st.param.b32 [param0+0], %r1;
+		.param .b32 retval0;
+		call.uni (retval0), callee, (param0);
+		ld.param.b32 %r2, [retval0+0];
+	}
+	st.local.u32 [depot], %r3;
+	mov.u32 %r2, %tid.x;
+	st.global.u32 [missing], %r2;
+$L__done:
+	ret;
+}
+
+.func (.param .b32 ret) callee(.param .b32 x)
+{
+	.reg .b32 %r<2>;
+	ld.param.b32 %r1, [x];
+	st.param.b32 [ret], %r1;
+	ret;
+}
+";
+
+    /// Resolves the `nth` occurrence of `text`, skipping an opening `[`.
+    fn resolved<'a>(table: &SymbolTable<'a>, source: &str, text: &str, nth: usize) -> (SymbolKind, ScopeKind<'a>) {
+        let offset = source.match_indices(text).nth(nth).unwrap().0 + usize::from(text.starts_with('['));
+        let Resolution::Symbol(id) = table.reference_at(offset).unwrap().resolution() else { panic!("{text}") };
+        let symbol = table.symbol(id);
+        (symbol.kind(), table.scope(symbol.scope()).kind())
+    }
+
+    #[test]
+    fn scopes() {
+        let ptx = PtxFile::try_from(CALLS).unwrap();
+        let table = SymbolTable::new(&ptx);
+        assert_eq!(resolved(&table, CALLS, "[counter]", 0).0, SymbolKind::Variable);
+        assert_eq!(resolved(&table, CALLS, "%p1", 1), (SymbolKind::Register, ScopeKind::Function("caller")));
+        assert_eq!(resolved(&table, CALLS, "$L__done", 0), (SymbolKind::Label, ScopeKind::Function("caller")));
+        assert_eq!(resolved(&table, CALLS, "param0", 1), (SymbolKind::Parameter, ScopeKind::Block));
+        assert_eq!(resolved(&table, CALLS, "callee", 1), (SymbolKind::Function, ScopeKind::Module));
+        assert_eq!(resolved(&table, CALLS, "depot", 1), (SymbolKind::Local, ScopeKind::Function("caller")));
+        assert_eq!(resolved(&table, CALLS, "[x]", 0), (SymbolKind::Parameter, ScopeKind::Function("callee")));
+        assert_eq!(resolved(&table, CALLS, "%r1", 4), (SymbolKind::Register, ScopeKind::Function("callee")));
+
+        // The callee's definition replaces its declaration.
+        let callee = table.scope(table.module_scope()).get("callee").unwrap();
+        assert_eq!(table.symbol(callee).span().start, CALLS.rfind("callee").unwrap());
+
+        let tid = CALLS.find("%tid.x").unwrap();
+        assert_eq!(table.reference_at(tid).unwrap().resolution(), Resolution::Special);
+    }
+
+    #[test]
+    fn unresolved() {
+        let ptx = PtxFile::try_from(CALLS).unwrap();
+        let table = SymbolTable::new(&ptx);
+        // `%r<3>` declares `%r0` to `%r2`.
+        let unresolved: Vec<_> = table.unresolved().map(|reference| reference.name()).collect();
+        assert_eq!(unresolved, ["%r3", "missing"]);
+    }
+
+    #[test]
+    fn multiple_declarators() {
+        let input = ".version 7.5\n.target sm_30\n.address_size 64\n.global .u32 a, b[2] = {1, 2};\n\
+            .visible .entry k()\n{\n.reg .b32 %r<3>;\nld.global.u32 %r1, [a];\nld.global.u32 %r2, [b+4];\n}\n";
+        let ptx = PtxFile::try_from(input).unwrap();
+        let table = SymbolTable::new(&ptx);
+        assert_eq!(table.unresolved().count(), 0);
+        assert_eq!(resolved(&table, input, "[a]", 0), (SymbolKind::Variable, ScopeKind::Module));
+        assert_eq!(resolved(&table, input, "[b", 0), (SymbolKind::Variable, ScopeKind::Module));
+    }
+
+    #[test]
+    fn plain_labels() {
+        let input = ".version 7.5\n.target sm_30\n.address_size 64\n\
+            .visible .entry k()\n{\nbra LBB0_2;\nLBB0_2:\nret;\n}\n";
+        let ptx = PtxFile::try_from(input).unwrap();
+        let table = SymbolTable::new(&ptx);
+        assert_eq!(table.unresolved().count(), 0);
+        assert_eq!(resolved(&table, input, "LBB0_2;", 0), (SymbolKind::Label, ScopeKind::Function("k")));
+    }
+
+    #[test]
+    fn query_by_span() {
+        let ptx = PtxFile::try_from(CALLS).unwrap();
+        let table = SymbolTable::new(&ptx);
+        let declaration = CALLS.find("$L__done:").unwrap();
+        let label = table.symbol_at(declaration + 1).unwrap();
+        assert_eq!(table.symbol(label).name(), "$L__done");
+        assert_eq!(table.symbol_at(CALLS.find("$L__done;").unwrap()), Some(label));
+
+        let reference = table.reference_at(CALLS.find("[depot]").unwrap() + 1).unwrap();
+        assert_eq!(reference.span().text(CALLS), "depot");
+        assert!(table.reference_at(CALLS.find("ret;").unwrap()).is_none());
+    }
+
+    #[test]
+    fn fixtures_resolve() {
+        // The symbols, registers, labels and references of each module. `c`
+        // and `d` are the same module.
+        let expected = [
+            (a::_PTX, 124, 40, 16, 512),
+            (b::_PTX, 137, 43, 17, 562),
+            (c::_PTX, 116, 38, 16, 485),
+            (d::_PTX, 116, 38, 16, 485),
+            (kernel::_PTX, 10, 4, 2, 41),
+        ];
+        for (input, symbols, registers, labels, references) in expected {
+            let ptx = PtxFile::try_from(input).unwrap();
+            let table = SymbolTable::new(&ptx);
+            let count = |kind| table.symbols().filter(|(_, symbol)| symbol.kind() == kind).count();
+            assert_eq!(table.symbols().count(), symbols);
+            assert_eq!(count(SymbolKind::Register), registers);
+            assert_eq!(count(SymbolKind::Label), labels);
+            assert_eq!(table.references().len(), references);
+            assert_eq!(table.unresolved().count(), 0);
+        }
+
+        // The example declares neither its parameters nor `%rd3` to `%rd6`.
+        let ptx = PtxFile::try_from(_EXAMPLE_FILE).unwrap();
+        let table = SymbolTable::new(&ptx);
+        assert!(table.unresolved().any(|reference| reference.name() == "__cudaparm__Z6kernelPiS_i_a"));
+        assert!(table.unresolved().all(|reference| reference.name() != "%rd2"));
+    }
+}
@@ -0,0 +1,337 @@
+//! Traversal of the AST.
+//!
+//! [`Visitor`] walks the borrowed AST and [`VisitorMut`] the owned one.
+//! Each hook defaults to the matching `walk_*` function, which visits the
+//! node's children; an override can call it to keep descending.
+//!
+//! Function bodies are visited in source order, including the statements
+//! of call sequences. Statements that fail to parse are reported to
+//! [`Visitor::visit_diagnostic`].
+
+use super::{
+    function::body::FunctionBody, Address, BodyLine, Diagnostic, Function, Global, Instruction, Operand,
+    Predicate, Register, Span, Statement,
+};
+#[cfg(feature = "std")]
+use super::{owned, Item, PtxFile};
+
+pub trait Visitor<'a> {
+    #[cfg(feature = "std")]
+    fn visit_file(&mut self, file: &PtxFile<'a>) {
+        walk_file(self, file)
+    }
+
+    fn visit_function(&mut self, function: &Function<'a>) {
+        walk_function(self, function)
+    }
+
+    fn visit_global(&mut self, _global: &Global<'a>) {}
+
+    fn visit_statement(&mut self, statement: &Statement<'a>) {
+        walk_statement(self, statement)
+    }
+
+    fn visit_instruction(&mut self, instruction: &Instruction<'a>) {
+        walk_instruction(self, instruction)
+    }
+
+    fn visit_operand(&mut self, operand: &Operand<'a>) {
+        walk_operand(self, operand)
+    }
+
+    /// A `.reg` declaration.
+    fn visit_register(&mut self, _register: &Register<'a>) {}
+
+    /// The guard of an instruction or branch, like `@%p1`.
+    fn visit_predicate(&mut self, _predicate: &Predicate<'a>) {}
+
+    /// A memory operand, like `[%rd1+8]`.
+    fn visit_address(&mut self, _address: &Address<'a>) {}
+
+    /// A statement of a body that failed to parse, with the span of the
+    /// skipped source.
+    fn visit_diagnostic(&mut self, _diagnostic: &Diagnostic<'a>, _span: Span) {}
+}
+
+/// Visits the functions and globals in source order.
+#[cfg(feature = "std")]
+pub fn walk_file<'a, V: Visitor<'a> + ?Sized>(visitor: &mut V, file: &PtxFile<'a>) {
+    for item in file.items() {
+        match item {
+            Item::Function(function) => visitor.visit_function(function),
+            Item::Global(global) => visitor.visit_global(global),
+        }
+    }
+}
+
+pub fn walk_function<'a, V: Visitor<'a> + ?Sized>(visitor: &mut V, function: &Function<'a>) {
+    if let Some(body) = function.body() {
+        walk_body(visitor, body);
+    }
+}
+
+/// Visits the statements of a function body or call sequence.
+pub fn walk_body<'a, V: Visitor<'a> + ?Sized>(visitor: &mut V, mut body: FunctionBody<'a>) {
+    let source = body.source();
+    while let Some(statement) = body.next_recovering() {
+        match statement {
+            Ok(statement) => visitor.visit_statement(&statement),
+            Err(diagnostic) => {
+                let span = Span::of(source, diagnostic.skipped());
+                visitor.visit_diagnostic(&diagnostic, span);
+            }
+        }
+    }
+}
+
+pub fn walk_statement<'a, V: Visitor<'a> + ?Sized>(visitor: &mut V, statement: &Statement<'a>) {
+    match statement.line() {
+        BodyLine::Register(register) => visitor.visit_register(register),
+        BodyLine::Instruction(instruction) => visitor.visit_instruction(instruction),
+        BodyLine::Goto(goto) => {
+            if let Some(predicate) = goto.predicate() {
+                visitor.visit_predicate(predicate);
+            }
+        }
+        BodyLine::FunctionCall(call) => walk_body(visitor, call.statements()),
+        _ => {}
+    }
+}
+
+/// Visits the predicate, then the operands.
+pub fn walk_instruction<'a, V: Visitor<'a> + ?Sized>(visitor: &mut V, instruction: &Instruction<'a>) {
+    if let Some(predicate) = instruction.predicate() {
+        visitor.visit_predicate(predicate);
+    }
+    instruction.operands().for_each(|operand| visitor.visit_operand(&operand));
+}
+
+/// Visits the elements of vector and list operands and the address of
+/// memory operands.
+pub fn walk_operand<'a, V: Visitor<'a> + ?Sized>(visitor: &mut V, operand: &Operand<'a>) {
+    match operand {
+        Operand::Vector(list) | Operand::List(list) => {
+            list.operands().for_each(|operand| visitor.visit_operand(&operand))
+        }
+        Operand::Address(address) => visitor.visit_address(address),
+        _ => {}
+    }
+}
+
+/// Like [`Visitor`], but over the owned AST and with mutable access.
+#[cfg(feature = "std")]
+pub trait VisitorMut {
+    fn visit_file(&mut self, file: &mut owned::PtxFile) {
+        walk_file_mut(self, file)
+    }
+
+    fn visit_function(&mut self, function: &mut owned::Function) {
+        walk_function_mut(self, function)
+    }
+
+    fn visit_global(&mut self, _global: &mut owned::Global) {}
+
+    fn visit_statement(&mut self, statement: &mut owned::Statement) {
+        walk_statement_mut(self, statement)
+    }
+
+    fn visit_instruction(&mut self, instruction: &mut owned::Instruction) {
+        walk_instruction_mut(self, instruction)
+    }
+
+    fn visit_operand(&mut self, operand: &mut owned::Operand) {
+        walk_operand_mut(self, operand)
+    }
+
+    /// A `.reg` declaration, without the `.reg`.
+    fn visit_register(&mut self, _declaration: &mut String) {}
+
+    fn visit_predicate(&mut self, _predicate: &mut owned::Predicate) {}
+
+    /// The register or symbol and the offset of a memory operand.
+    fn visit_address(&mut self, _base: &mut String, _offset: &mut Option<String>) {}
+}
+
+#[cfg(feature = "std")]
+pub fn walk_file_mut<V: VisitorMut + ?Sized>(visitor: &mut V, file: &mut owned::PtxFile) {
+    for item in &mut file.items {
+        match item {
+            owned::Item::Function(function) => visitor.visit_function(function),
+            owned::Item::Global(global) => visitor.visit_global(global),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+pub fn walk_function_mut<V: VisitorMut + ?Sized>(visitor: &mut V, function: &mut owned::Function) {
+    for statement in function.body.iter_mut().flatten() {
+        visitor.visit_statement(statement);
+    }
+}
+
+#[cfg(feature = "std")]
+pub fn walk_statement_mut<V: VisitorMut + ?Sized>(visitor: &mut V, statement: &mut owned::Statement) {
+    match &mut statement.line {
+        owned::BodyLine::Register(declaration) => visitor.visit_register(declaration),
+        owned::BodyLine::Instruction(instruction) => visitor.visit_instruction(instruction),
+        owned::BodyLine::Goto(goto) => {
+            if let Some(predicate) = &mut goto.predicate {
+                visitor.visit_predicate(predicate);
+            }
+        }
+        owned::BodyLine::FunctionCall(call) => {
+            call.statements.iter_mut().for_each(|statement| visitor.visit_statement(statement));
+        }
+        _ => {}
+    }
+}
+
+#[cfg(feature = "std")]
+pub fn walk_instruction_mut<V: VisitorMut + ?Sized>(visitor: &mut V, instruction: &mut owned::Instruction) {
+    if let Some(predicate) = &mut instruction.predicate {
+        visitor.visit_predicate(predicate);
+    }
+    instruction.operands.iter_mut().for_each(|operand| visitor.visit_operand(operand));
+}
+
+#[cfg(feature = "std")]
+pub fn walk_operand_mut<V: VisitorMut + ?Sized>(visitor: &mut V, operand: &mut owned::Operand) {
+    match operand {
+        owned::Operand::Vector(operands) | owned::Operand::List(operands) => {
+            operands.iter_mut().for_each(|operand| visitor.visit_operand(operand))
+        }
+        owned::Operand::Address { base, offset } => visitor.visit_address(base, offset),
+        _ => {}
+    }
+}
+
+#[cfg(feature = "std")]
+#[cfg(test)]
+mod test_visit {
+    use crate::{
+        parser::{owned, Address, Function, Global, Instruction, Operand, Predicate, PtxFile, Register},
+        ptx_files::{b, kernel},
+    };
+
+    use super::{walk_function, Visitor, VisitorMut};
+
+    #[derive(Default)]
+    struct Counter<'a> {
+        functions: Vec<&'a str>,
+        globals: usize,
+        instructions: usize,
+        registers: usize,
+        declarations: usize,
+        predicates: usize,
+        addresses: Vec<&'a str>,
+    }
+
+    impl<'a> Visitor<'a> for Counter<'a> {
+        fn visit_function(&mut self, function: &Function<'a>) {
+            self.functions.push(function.name());
+            walk_function(self, function)
+        }
+
+        fn visit_global(&mut self, _global: &Global<'a>) {
+            self.globals += 1;
+        }
+
+        fn visit_instruction(&mut self, instruction: &Instruction<'a>) {
+            self.instructions += 1;
+            super::walk_instruction(self, instruction)
+        }
+
+        fn visit_operand(&mut self, operand: &Operand<'a>) {
+            self.registers += usize::from(matches!(operand, Operand::Register(_)));
+            super::walk_operand(self, operand)
+        }
+
+        fn visit_register(&mut self, _register: &Register<'a>) {
+            self.declarations += 1;
+        }
+
+        fn visit_predicate(&mut self, _predicate: &Predicate<'a>) {
+            self.predicates += 1;
+        }
+
+        fn visit_address(&mut self, address: &Address<'a>) {
+            self.addresses.push(address.base());
+        }
+    }
+
+    #[test]
+    fn counts() {
+        let ptx = PtxFile::try_from(kernel::_PTX).unwrap();
+        let mut counter = Counter::default();
+        counter.visit_file(&ptx);
+        assert_eq!(counter.functions, ["square_kernel"]);
+        assert_eq!(counter.globals, 0);
+        // Branches, labels, `ret` and register declarations have their own lines.
+        assert_eq!(counter.instructions, 16);
+        // Registers inside addresses are visited as addresses, not operands.
+        assert_eq!(counter.registers, 33);
+        assert_eq!(counter.declarations, 4);
+        assert_eq!(counter.predicates, 1);
+        assert_eq!(
+            counter.addresses,
+            ["square_kernel_param_2", "square_kernel_param_0", "square_kernel_param_1", "%rd2", "%rd1"],
+        );
+    }
+
+    #[test]
+    fn enters_call_sequences() {
+        struct Calls(usize);
+
+        impl<'a> Visitor<'a> for Calls {
+            fn visit_instruction(&mut self, instruction: &Instruction<'a>) {
+                self.0 += usize::from(instruction.opcode().name() == "call");
+            }
+        }
+
+        let ptx = PtxFile::try_from(b::_PTX).unwrap();
+        let mut calls = Calls(0);
+        calls.visit_file(&ptx);
+        assert!(calls.0 > 0);
+    }
+
+    #[test]
+    fn renames_registers() {
+        struct Rename;
+
+        fn rename(register: &mut String) {
+            *register = register.replace("%rd", "%x").replace("%p", "%q");
+        }
+
+        impl VisitorMut for Rename {
+            fn visit_operand(&mut self, operand: &mut owned::Operand) {
+                if let owned::Operand::Register(register) = operand {
+                    rename(register);
+                }
+                super::walk_operand_mut(self, operand)
+            }
+
+            fn visit_register(&mut self, declaration: &mut String) {
+                rename(declaration);
+            }
+
+            fn visit_predicate(&mut self, predicate: &mut owned::Predicate) {
+                let (owned::Predicate::True(register) | owned::Predicate::False(register)) = predicate;
+                rename(register);
+            }
+
+            fn visit_address(&mut self, base: &mut String, _offset: &mut Option<String>) {
+                rename(base);
+            }
+        }
+
+        let mut ptx = PtxFile::try_from(kernel::_PTX).unwrap().into_owned();
+        Rename.visit_file(&mut ptx);
+        let printed = ptx.to_string();
+        assert!(printed.contains("ld.param.u64 %x3, [square_kernel_param_0];"));
+        assert!(printed.contains("add.s64 %x1, %x5, %x7;"));
+        assert!(printed.contains("st.global.f32 [%x1], %f2;"));
+        assert!(printed.contains("@%q1 bra $L__BB0_2;"));
+        assert!(printed.contains(".reg .b64 \t%x<8>;"));
+        assert!(!printed.contains("%rd") && !printed.contains("%p"));
+    }
+}
@@ -0,0 +1,135 @@
+use std::collections::HashMap;
+
+use super::{PtxFile, Reference, Resolution, ScopeId, Symbol, SymbolId, SymbolTable};
+
+/// The definition and uses of every symbol in a module.
+///
+/// Registers declared together, as by `.reg .b32 %r<8>`, share a symbol
+/// but are indexed by their own names, so the uses of `%r3` do not
+/// include those of `%r4`.
+#[derive(Debug, Clone)]
+pub struct Xref<'a> {
+    table: SymbolTable<'a>,
+    uses: HashMap<(SymbolId, &'a str), Vec<usize>>,
+}
+
+impl<'a> Xref<'a> {
+    pub fn new(ptx: &PtxFile<'a>) -> Self {
+        Self::from(SymbolTable::new(ptx))
+    }
+
+    pub fn table(&self) -> &SymbolTable<'a> {
+        &self.table
+    }
+
+    /// The declaration that `name` resolves to in `scope`.
+    pub fn definition(&self, scope: ScopeId, name: &str) -> Option<&Symbol<'a>> {
+        Some(self.table.symbol(self.resolve(scope, name)?))
+    }
+
+    /// The uses of `name` as resolved in `scope`, in source order.
+    pub fn uses<'r>(&'r self, scope: ScopeId, name: &'r str) -> impl Iterator<Item = &'r Reference<'a>> {
+        let uses = self.resolve(scope, name).and_then(|id| self.uses.get(&(id, name)));
+        uses.into_iter().flatten().map(|&index| &self.table.references()[index])
+    }
+
+    /// The uses of a symbol under any of its names, in source order.
+    pub fn symbol_uses(&self, id: SymbolId) -> impl Iterator<Item = &Reference<'a>> {
+        self.table.references().iter().filter(move |reference| reference.resolution() == Resolution::Symbol(id))
+    }
+
+    fn resolve(&self, scope: ScopeId, name: &str) -> Option<SymbolId> {
+        match self.table.lookup(scope, name) {
+            Resolution::Symbol(id) => Some(id),
+            _ => None,
+        }
+    }
+}
+
+impl<'a> From<SymbolTable<'a>> for Xref<'a> {
+    fn from(table: SymbolTable<'a>) -> Self {
+        let mut uses: HashMap<_, Vec<_>> = HashMap::new();
+        for (index, reference) in table.references().iter().enumerate() {
+            if let Resolution::Symbol(id) = reference.resolution() {
+                uses.entry((id, reference.name())).or_default().push(index);
+            }
+        }
+        Self { table, uses }
+    }
+}
+
+#[cfg(test)]
+mod test_xref {
+    use crate::{
+        parser::{PtxFile, Reference, SymbolKind, UseKind},
+        ptx_files::{a, kernel},
+    };
+
+    use super::Xref;
+
+    fn kinds<'r>(uses: impl Iterator<Item = &'r Reference<'r>>) -> Vec<UseKind> {
+        uses.map(|reference| reference.kind()).collect()
+    }
+
+    #[test]
+    fn registers() {
+        let ptx = PtxFile::try_from(kernel::_PTX).unwrap();
+        let xref = Xref::new(&ptx);
+        let scope = xref.table().function_scope("square_kernel").unwrap();
+        let rd5 = xref.definition(scope, "%rd5").unwrap();
+        assert_eq!(rd5.name(), "%rd");
+        assert_eq!(rd5.span().text(kernel::_PTX), "%rd");
+        // `cvta.to.global.u64 %rd5, %rd4;` then `add.s64 %rd1, %rd5, %rd7;`.
+        assert_eq!(kinds(xref.uses(scope, "%rd5")), [UseKind::Write, UseKind::Read]);
+        // `setp` writes `%p1`, the branch reads it.
+        assert_eq!(kinds(xref.uses(scope, "%p1")), [UseKind::Write, UseKind::Read]);
+        // `st.global.f32 [%rd1], %f2;` reads the address register.
+        assert_eq!(kinds(xref.uses(scope, "%rd1")), [UseKind::Write, UseKind::Read]);
+        let rd = xref.table().scope(scope).get("%rd").unwrap();
+        assert!(xref.symbol_uses(rd).count() > xref.uses(scope, "%rd5").count());
+    }
+
+    #[test]
+    fn parameters_and_labels() {
+        let ptx = PtxFile::try_from(kernel::_PTX).unwrap();
+        let xref = Xref::new(&ptx);
+        let scope = xref.table().function_scope("square_kernel").unwrap();
+        let param = xref.definition(scope, "square_kernel_param_0").unwrap();
+        assert_eq!(param.kind(), SymbolKind::Parameter);
+        assert_eq!(kinds(xref.uses(scope, "square_kernel_param_0")), [UseKind::Read]);
+        assert_eq!(kinds(xref.uses(scope, "$L__BB0_1")), [UseKind::Branch]);
+        assert_eq!(xref.uses(xref.table().module_scope(), "square_kernel_param_0").count(), 0);
+    }
+
+    #[test]
+    fn calls_and_globals() {
+        let ptx = PtxFile::try_from(a::_PTX).unwrap();
+        let xref = Xref::new(&ptx);
+        let module = xref.table().module_scope();
+        let panic = "_ZN4core9panicking5panic17h691abfa2aca02139E";
+        assert_eq!(xref.definition(module, panic).unwrap().kind(), SymbolKind::Function);
+        assert!(xref.uses(module, panic).count() > 0);
+        assert!(xref.uses(module, panic).all(|reference| reference.kind() == UseKind::Call));
+
+        let string = "str_$_0";
+        assert_eq!(xref.definition(module, string).unwrap().kind(), SymbolKind::Variable);
+        assert!(xref.uses(module, string).count() > 0);
+        assert!(xref.uses(module, string).all(|reference| reference.kind() == UseKind::AddressTaken));
+    }
+
+    #[test]
+    fn call_sequences() {
+        let input = ".version 7.5\n.target sm_30\n.address_size 64\n\
+            .func (.param .b32 ret) f(.param .b32 x);\n\
+            .visible .entry k()\n{\n.reg .b32 %r<2>;\n{\n.param .b32 param0;\n\
+            st.param.b32 [param0+0], %r1;\n.param .b32 retval0;\n\
+            call.uni (retval0), f, (param0);\nld.param.b32 %r0, [retval0+0];\n}\nret;\n}\n";
+        let ptx = PtxFile::try_from(input).unwrap();
+        let xref = Xref::new(&ptx);
+        let block = xref.table().reference_at(input.find("[param0").unwrap() + 1).unwrap().scope();
+        assert_eq!(kinds(xref.uses(block, "param0")), [UseKind::Write, UseKind::Read]);
+        assert_eq!(kinds(xref.uses(block, "retval0")), [UseKind::Write, UseKind::Read]);
+        assert_eq!(kinds(xref.uses(block, "f")), [UseKind::Call]);
+        assert_eq!(kinds(xref.uses(block, "%r1")), [UseKind::Read]);
+    }
+}